@@ -0,0 +1,53 @@
+//! Throughput of the portable and accelerated SHA-256 implementations.
+//! Run with `cargo bench --features simd`.
+
+use merkle_tree::crypto::hash::{accelerated, portable};
+use merkle_tree::models::merkle::{MerkleNode, MerkleTree};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let started = Instant::now();
+    for _ in 0..5 {
+        f();
+    }
+    started.elapsed() / 5
+}
+
+fn main() {
+    // Bulk hashing: large values dominate leaf hashing
+    let block = vec![0x5a; 64 << 20];
+    for (name, hash) in [("portable", portable as fn(&[&[u8]]) -> Vec<u8>), ("accelerated", accelerated)] {
+        let elapsed = time(|| {
+            black_box(hash(&[black_box(&block)]));
+        });
+        let mib_per_sec = 64.0 / elapsed.as_secs_f64();
+        println!("{:>12} 64 MiB value: {:>8.1?} ({:.0} MiB/s)", name, elapsed, mib_per_sec);
+    }
+
+    // Node hashing: many small inputs, as in building a large tree
+    let pairs: Vec<[u8; 64]> = (0..1_000_000u32)
+        .map(|i| {
+            let mut pair = [0u8; 64];
+            pair[..4].copy_from_slice(&i.to_be_bytes());
+            pair
+        })
+        .collect();
+    for (name, hash) in [("portable", portable as fn(&[&[u8]]) -> Vec<u8>), ("accelerated", accelerated)] {
+        let elapsed = time(|| {
+            for pair in &pairs {
+                black_box(hash(&[&pair[..32], &pair[32..]]));
+            }
+        });
+        println!("{:>12} 1M node hashes: {:>8.1?}", name, elapsed);
+    }
+
+    // End to end with the active (accelerated) hasher
+    let values: Vec<Vec<u8>> = (0..100_000u32).map(|i| i.to_be_bytes().to_vec()).collect();
+    let elapsed = time(|| {
+        let leaves = values.iter().map(|v| MerkleNode::new_leaf(v)).collect();
+        black_box(MerkleTree::from_leaves(leaves));
+    });
+    println!("100k-leaf tree build: {:.1?}", elapsed);
+}
@@ -0,0 +1,10 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        // Use the vendored protoc so the grpc feature builds without a system install
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc is missing");
+        // SAFETY: build scripts are single-threaded
+        unsafe { std::env::set_var("PROTOC", protoc) };
+        tonic_prost_build::compile_protos("proto/merkle.proto").expect("failed to compile protos");
+    }
+}
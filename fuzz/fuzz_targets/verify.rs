@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use merkle_tree::crypto::proof::MerkleProof;
+
+// Proofs arrive from untrusted clients, so any input that deserializes
+// must verify (or fail to) without panicking.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = serde_json::from_slice::<MerkleProof>(data) {
+        let _ = proof.verify();
+    }
+});
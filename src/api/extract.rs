@@ -0,0 +1,74 @@
+use crate::error::AppError;
+
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+};
+
+/// A hex-encoded path segment decoded into bytes.
+/// Malformed segments are rejected with `AppError::InvalidInput` rather than
+/// axum's default plain-text rejection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexPath(pub Vec<u8>);
+
+impl<S: Send + Sync> FromRequestParts<S> for HexPath {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(segment) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::InvalidInput(e.body_text()))?;
+
+        hex::decode(&segment)
+            .map(HexPath)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid hex '{}': {}", segment, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{route::create_router, state::AppState};
+    use crate::models::merkle::MerkleNode;
+    use crate::storage::memory::MemoryStorage;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get(uri: &str) -> (StatusCode, Value) {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        state.storage().add_commitment(b"data".to_vec()).await.unwrap();
+
+        let response = create_router(state)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_valid_hex_path() {
+        let leaf_hash = hex::encode(MerkleNode::new_leaf(b"data").hash);
+        let (status, body) = get(&format!("/api/v1/commitments/by-hash/{}", leaf_hash)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["commitment"]["index"], 0);
+
+        let (status, body) = get(&format!("/api/v1/commitments/by-hash/{}", "00".repeat(32))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_hex_path() {
+        for segment in ["abc", "zz", "%FF%FE"] {
+            let (status, body) = get(&format!("/api/v1/commitments/by-hash/{}", segment)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "segment {}", segment);
+            assert_eq!(body["error"], "INVALID_INPUT");
+        }
+    }
+}
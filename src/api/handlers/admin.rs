@@ -0,0 +1,117 @@
+use crate::api::state::AppState;
+use crate::dto::request::{MigrateRequest, StorageBackend};
+use crate::dto::response::{IntegrityResponse, MigrateResponse};
+use crate::error::AppError;
+use crate::models::audit::AuditEntry;
+use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+use crate::storage::snapshot::copy_storage;
+use crate::storage::traits::CommitmentStorage;
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+
+/// Run a full integrity self-check of the storage
+pub async fn verify_integrity(
+    State(state): State<AppState>,
+) -> Result<Json<IntegrityResponse>, AppError> {
+    let valid = state.storage().verify_integrity().await?;
+    let commitment_count = state.storage().commitment_count().await?;
+
+    Ok(Json(IntegrityResponse {
+        valid,
+        commitment_count,
+    }))
+}
+
+/// Walk the prev_root chain to detect retroactive edits to the commitment log
+pub async fn verify_chain(
+    State(state): State<AppState>,
+) -> Result<Json<IntegrityResponse>, AppError> {
+    let valid = state.storage().verify_chain().await?;
+    let commitment_count = state.storage().commitment_count().await?;
+
+    Ok(Json(IntegrityResponse {
+        valid,
+        commitment_count,
+    }))
+}
+
+/// List every mutation of the commitment log, oldest first. Admin routes always
+/// require a token when auth is enabled.
+pub async fn get_audit_log(State(state): State<AppState>) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    Ok(Json(state.storage().audit_log().await?))
+}
+
+/// Copy every commitment into a freshly configured backend and atomically switch the
+/// API over to it. Only HTTP handlers follow the swap; the gRPC service and expiry
+/// sweeper keep the backend they started with.
+pub async fn migrate_storage(
+    State(state): State<AppState>,
+    Json(req): Json<MigrateRequest>,
+) -> Result<Json<MigrateResponse>, AppError> {
+    let current = state.storage();
+    if current.stores_hashes_only() {
+        return Err(AppError::InvalidInput(
+            "Hash-only storage cannot be migrated".to_string(),
+        ));
+    }
+    let target: Arc<dyn CommitmentStorage> = match req.backend {
+        StorageBackend::Memory => Arc::new(MemoryStorage::with_config(MemoryStorageConfig {
+            leaf_hash_mode: current.leaf_hash_mode(),
+            ..Default::default()
+        })),
+    };
+
+    let (commitment_count, merkle_root) = copy_storage(current.as_ref(), target.as_ref()).await?;
+    // Writes that landed mid-copy would be lost by the swap
+    if current.get_root_hash().await? != merkle_root {
+        return Err(AppError::Conflict(
+            "Storage changed during migration; retry".to_string(),
+        ));
+    }
+    state.swap_storage(target);
+
+    Ok(Json(MigrateResponse {
+        backend: req.backend,
+        commitment_count,
+        merkle_root,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migrate_storage_keeps_data_and_root() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        let old = state.storage();
+        for i in 0..5 {
+            old.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        old.insert_at(7, b"gap".to_vec()).await.unwrap();
+        let root = old.get_root_hash().await.unwrap();
+
+        let request = Json(MigrateRequest {
+            backend: StorageBackend::Memory,
+        });
+        let Json(response) = migrate_storage(State(state.clone()), request).await.unwrap();
+        assert_eq!(response.commitment_count, 8);
+        assert_eq!(response.merkle_root, root);
+
+        // Clones of the state now read from the new backend
+        let new = state.clone().storage();
+        assert!(!Arc::ptr_eq(&old, &new));
+        assert_eq!(new.get_root_hash().await.unwrap(), root);
+        assert_eq!(
+            new.get_all_commitments().await.unwrap(),
+            old.get_all_commitments().await.unwrap()
+        );
+        assert!(new.get_proof(7).await.unwrap().verify());
+
+        // Writes go to the new backend only
+        new.add_commitment(b"after".to_vec()).await.unwrap();
+        assert_eq!(old.commitment_count().await.unwrap(), 8);
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 9);
+    }
+}
@@ -1,44 +1,724 @@
-use crate::api::state::AppState;
-use crate::dto::{request::AddCommitmentRequest, response::*};
+use crate::api::{middleware::Claims, state::AppState};
+use crate::dto::{
+    encoding::Encoding,
+    request::{
+        AddCommitmentRequest, BatchCommitRequest, CommitBlockRequest, DeleteQuery, EncodingQuery, NamingQuery, PageQuery, PreviewQuery, RangeQuery,
+    },
+    response::*,
+};
 use crate::error::AppError;
+use crate::models::{audit::Checkpoint, commitment::CommitmentMetadata, merkle::LeafHashMode};
 
-use axum::{extract::State, Json};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    Extension, Json,
+};
+use futures_util::StreamExt;
+
+/// Commit a value streamed as the raw request body, hashing it chunk by chunk so it is
+/// never buffered whole. The value itself isn't kept, so this needs hash-only storage.
+#[tracing::instrument(skip_all, fields(index, root_prefix))]
+pub async fn stream_commitment(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Created<AddCommitmentResponse>, AppError> {
+    let storage = state.storage();
+    if !storage.stores_hashes_only() {
+        return Err(AppError::InvalidInput(
+            "Streaming commits require hash-only storage".to_string(),
+        ));
+    }
+    let declared_len = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let mode = storage.leaf_hash_mode();
+    let mut hasher = mode.hasher(declared_len).ok_or_else(|| {
+        AppError::InvalidInput(match mode {
+            LeafHashMode::IndexBound => "Index-bound leaves can't be streamed".to_string(),
+            _ => "Length-prefixed leaves need a Content-Length header".to_string(),
+        })
+    })?;
+
+    let mut received = 0u64;
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| AppError::InvalidInput(format!("Failed to read body: {}", e)))?;
+        received += chunk.len() as u64;
+        hasher.update(&chunk);
+    }
+    if received == 0 {
+        return Err(AppError::InvalidInput("Value cannot be empty".to_string()));
+    }
+    if declared_len.is_some_and(|len| len != received) {
+        return Err(AppError::InvalidInput(
+            "Body length does not match Content-Length".to_string(),
+        ));
+    }
+
+    let (index, merkle_root) = storage
+        .store_leaf_hash(hasher.finish(), CommitmentMetadata::default())
+        .await?;
+    record_commitment(index, &merkle_root);
+
+    Ok(Created::commitment(index, AddCommitmentResponse::new(index, merkle_root)))
+}
 
 /// Add a new commitment
+#[tracing::instrument(skip_all, fields(index, root_prefix))]
 pub async fn add_commitment(
     State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
     Json(req): Json<AddCommitmentRequest>,
-) -> Result<Json<AddCommitmentResponse>, AppError> {
+) -> Result<Created<AddCommitmentResponse>, AppError> {
     // Validate request
-    req.validate()
+    req.validate(&state.config.validation_context(claims.is_some()))
         .map_err(|e| AppError::InvalidInput(e.to_string()))?;
 
     // Add commitment
-    let (index, merkle_root) = state.storage.add_commitment(req.value).await?;
+    let metadata = req.metadata();
+    let (index, merkle_root) = state
+        .storage()
+        .store_commitment(None, req.value, metadata)
+        .await?;
+    record_commitment(index, &merkle_root);
+
+    Ok(Created::commitment(index, AddCommitmentResponse::new(index, merkle_root)))
+}
+
+/// Add many commitments in one request, rebuilding the tree once
+pub async fn add_commitments_batch(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Json(req): Json<BatchCommitRequest>,
+) -> Result<Created<BatchCommitResponse>, AppError> {
+    req.validate(&state.config.validation_context(claims.is_some()))
+        .map_err(AppError::InvalidInput)?;
+
+    let committed = state.storage().add_commitments(req.values).await?;
+    let (first, merkle_root) = committed
+        .first()
+        .cloned()
+        .ok_or(AppError::Internal("Batch committed no values".to_string()))?;
+
+    Ok(Created::commitment(
+        first,
+        BatchCommitResponse {
+            indices: committed.into_iter().map(|(index, _)| index).collect(),
+            merkle_root,
+        },
+    ))
+}
+
+/// Commit a pre-sorted block of values into consecutive slots and prove the whole
+/// block is in the tree with one proof
+pub async fn commit_block(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Json(req): Json<CommitBlockRequest>,
+) -> Result<Created<CommitBlockResponse>, AppError> {
+    req.validate(&state.config.validation_context(claims.is_some()))
+        .map_err(AppError::InvalidInput)?;
+
+    let block_len = req.values.len();
+    let storage = state.storage();
+    let (block_start, _) = storage.append_block(req.values).await?;
+    // Later writes may have landed since; the proof is against the current root
+    let proof = storage.snapshot().await?.tree().generate_block_proof(block_start, block_len)?;
+
+    Ok(Created::commitment(
+        block_start,
+        CommitBlockResponse {
+            block_start,
+            block_len,
+            proof,
+        },
+    ))
+}
+
+/// Create a commitment at a chosen index
+#[tracing::instrument(skip_all, fields(index, root_prefix))]
+pub async fn insert_commitment(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+    claims: Option<Extension<Claims>>,
+    Json(req): Json<AddCommitmentRequest>,
+) -> Result<Created<AddCommitmentResponse>, AppError> {
+    // Validate request
+    req.validate(&state.config.validation_context(claims.is_some()))
+        .map_err(|e| AppError::InvalidInput(e.to_string()))?;
+
+    let metadata = req.metadata();
+    let (index, merkle_root) = state
+        .storage()
+        .store_commitment(Some(index), req.value, metadata)
+        .await?;
+    record_commitment(index, &merkle_root);
+
+    Ok(Created::commitment(index, AddCommitmentResponse::new(index, merkle_root)))
+}
 
-    Ok(Json(AddCommitmentResponse::new(index, merkle_root)))
+/// Annotate the current span with the new commitment's position and root
+fn record_commitment(index: usize, merkle_root: &[u8]) {
+    let span = tracing::Span::current();
+    span.record("index", index);
+    span.record("root_prefix", hex::encode(&merkle_root[..merkle_root.len().min(4)]));
 }
 
 /// Get all commitments
 pub async fn get_all_commitments(
     State(state): State<AppState>,
+    Query(query): Query<PreviewQuery>,
 ) -> Result<Json<Vec<CommitmentResponse>>, AppError> {
-    let commitments = state.storage.get_all_commitments().await?;
-    let responses: Vec<CommitmentResponse> =
-        commitments.into_iter().map(|c| c.into()).collect();
+    let commitments = state.storage().get_all_commitments().await?;
+    let responses: Vec<CommitmentResponse> = commitments
+        .into_iter()
+        .map(|c| CommitmentResponse::from(c).with_preview(query.preview))
+        .collect();
 
     Ok(Json(responses))
 }
 
-/// Get current root hash
+/// Get one page of commitments
+pub async fn get_commitments_page(
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<PagedResponse<CommitmentResponse>>, AppError> {
+    let (offset, limit) = query.resolve().map_err(AppError::InvalidInput)?;
+
+    let commitments = state.storage().get_all_commitments().await?;
+    let total = commitments.len();
+    let items = commitments
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|c| c.into())
+        .collect();
+
+    Ok(Json(PagedResponse::new(items, total, offset, limit)))
+}
+
+/// Delete a commitment, leaving a placeholder unless `?compact=true` asks for the
+/// later indices to be shifted down
+pub async fn delete_commitment(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let (merkle_root, remapped) = if query.compact {
+        state.storage().compact_delete(index).await?
+    } else {
+        (state.storage().delete_commitment(index).await?, Vec::new())
+    };
+
+    Ok(Json(DeleteResponse {
+        index,
+        merkle_root,
+        remapped,
+    }))
+}
+
+/// Writes accepted but not yet reflected in a stale root response
+pub const ROOT_STALENESS_HEADER: &str = "x-root-staleness";
+
+/// Get current root hash. With `serve_stale_root` on and a backend that publishes
+/// its root, answers immediately from the last installed tree instead of queueing
+/// behind writers. Field names are camelCase with `?naming=camelCase`.
 pub async fn get_root(
     State(state): State<AppState>,
-) -> Result<Json<RootResponse>, AppError> {
-    let root_bytes = state.storage.get_root_hash().await?;
-    let commitment_count = state.storage.commitment_count().await?;
+    Query(query): Query<EncodingQuery>,
+    Query(naming): Query<NamingQuery>,
+) -> Result<(HeaderMap, Json<Named<RootResponse, RootResponseCamel>>), AppError> {
+    let encoding = Encoding::from_query(query.encoding.as_deref())?;
+    let naming = JsonNaming::from_query(naming.naming.as_deref(), state.config.json_naming)?;
+    let storage = state.storage();
+    let mut headers = HeaderMap::new();
+    let (root_bytes, commitment_count) =
+        match storage.stale_root().filter(|_| state.config.serve_stale_root) {
+            Some(stale) => {
+                headers.insert(ROOT_STALENESS_HEADER, stale.pending.into());
+                (stale.root, stale.commitment_count)
+            }
+            None => (storage.get_root_hash().await?, storage.commitment_count().await?),
+        };
+
+    Ok((
+        headers,
+        Json(Named::new(
+            RootResponse {
+                root_encoded: encoding.encode(&root_bytes),
+                root: root_bytes,
+                commitment_count,
+            },
+            naming,
+        )),
+    ))
+}
+
+/// Get the roots the commitments in [start, end) were added under, so an auditor
+/// knows which checkpoints to trust for the range
+pub async fn get_root_cover(
+    State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
+) -> Result<Json<RootCoverResponse>, AppError> {
+    let snapshot = state.storage().snapshot().await?;
+    let commitments = snapshot.commitments();
+    let Some(slots) = commitments.get(range.start..range.end).filter(|s| !s.is_empty()) else {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid range [{}, {}) for {} commitments",
+            range.start,
+            range.end,
+            commitments.len()
+        )));
+    };
 
-    Ok(Json(RootResponse {
-        root: root_bytes,
-        commitment_count,
+    // Expired values are hidden, and so are the roots they were added under
+    let mut roots: Vec<Vec<u8>> = Vec::new();
+    for commitment in slots.iter().filter(|c| !c.is_expired()) {
+        if !roots.contains(&commitment.merkle_root) {
+            roots.push(commitment.merkle_root.clone());
+        }
+    }
+    // Order by when each root appeared; rewritten slots can carry a later root
+    // than the slots after them
+    let history = state.storage().root_history().await?;
+    roots.sort_by_key(|root| history.iter().position(|h| h == root).unwrap_or(usize::MAX));
+
+    Ok(Json(RootCoverResponse {
+        start: range.start,
+        end: range.end,
+        roots,
     }))
-}
\ No newline at end of file
+}
+
+/// List the checkpointed roots with the tree sizes they cover, oldest first
+pub async fn get_checkpoints(State(state): State<AppState>) -> Result<Json<Vec<Checkpoint>>, AppError> {
+    Ok(Json(state.storage().checkpoints().await?))
+}
+
+/// Draw the current tree as plain text, for demos and teaching
+pub async fn get_tree_ascii(State(state): State<AppState>) -> Result<String, AppError> {
+    state.storage().read_tree(|tree| tree.render_ascii()).await
+}
+
+/// Count, for each node, how many leaf proofs include it as a sibling, to gauge
+/// how much multiproofs could save
+pub async fn get_sibling_fanout(
+    State(state): State<AppState>,
+) -> Result<Json<SiblingFanoutResponse>, AppError> {
+    state
+        .storage()
+        .read_tree(|tree| {
+            Json(SiblingFanoutResponse {
+                leaf_count: tree.leaf_count(),
+                nodes: tree.sibling_fanout(),
+            })
+        })
+        .await
+}
+
+/// Get the hash of the node at a level-order position (0 is the root)
+pub async fn get_tree_node(
+    State(state): State<AppState>,
+    Path(level_order_index): Path<usize>,
+) -> Result<Json<TreeNodeResponse>, AppError> {
+    let hash = state
+        .storage()
+        .read_tree(move |tree| tree.node_at(level_order_index))
+        .await?
+        .ok_or(AppError::NotFound(format!(
+            "Tree node {} not found",
+            level_order_index
+        )))?;
+    Ok(Json(TreeNodeResponse {
+        index: level_order_index,
+        hash,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::sha256;
+    use crate::models::merkle::{MerkleNode, MerkleTree};
+    use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn snake() -> Query<NamingQuery> {
+        Query(NamingQuery::default())
+    }
+
+    fn snake_body(body: Named<RootResponse, RootResponseCamel>) -> RootResponse {
+        match body {
+            Named::Snake(body) => body,
+            Named::Camel(body) => panic!("expected snake_case fields, got {:?}", body),
+        }
+    }
+
+    fn encoding(value: &str) -> Query<EncodingQuery> {
+        Query(EncodingQuery {
+            encoding: Some(value.to_string()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_add_commitment_enforces_record_size() {
+        let state = AppState::builder(Arc::new(MemoryStorage::new())).record_size(64).build();
+        let request = |len: usize| {
+            Json(AddCommitmentRequest {
+                value: vec![1; len],
+                content_type: None,
+                ttl_seconds: None,
+            })
+        };
+
+        assert!(add_commitment(State(state.clone()), None, request(64)).await.is_ok());
+        for len in [63, 65] {
+            let result = add_commitment(State(state.clone()), None, request(len)).await;
+            assert!(matches!(result, Err(AppError::InvalidInput(_))), "{}", len);
+        }
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_value_size_limit_is_tiered_by_auth() {
+        let state = AppState::builder(Arc::new(MemoryStorage::new()))
+            .max_value_size(16)
+            .authenticated_max_value_size(64)
+            .build();
+        let request = |len: usize| {
+            Json(AddCommitmentRequest {
+                value: vec![1; len],
+                content_type: None,
+                ttl_seconds: None,
+            })
+        };
+        let claims = || {
+            Some(Extension(Claims {
+                sub: Some("client".to_string()),
+                exp: u64::MAX,
+            }))
+        };
+
+        // Over the anonymous limit but within the authenticated one
+        let result = add_commitment(State(state.clone()), None, request(32)).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+        assert!(add_commitment(State(state.clone()), claims(), request(32)).await.is_ok());
+
+        // Authenticated callers still have a ceiling
+        let result = add_commitment(State(state.clone()), claims(), request(65)).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+        assert!(add_commitment(State(state.clone()), None, request(16)).await.is_ok());
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_commitment_matches_buffered_hash() {
+        use crate::storage::memory::MemoryStorageConfig;
+        use sha2::{Digest, Sha256};
+
+        let value: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let body = || {
+            let chunks: Vec<Result<Vec<u8>, std::io::Error>> =
+                value.chunks(64 * 1024).map(|c| Ok(c.to_vec())).collect();
+            Body::from_stream(futures_util::stream::iter(chunks))
+        };
+
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            hash_only: true,
+            ..Default::default()
+        });
+        let state = AppState::builder(Arc::new(storage)).build();
+        let created = stream_commitment(State(state.clone()), HeaderMap::new(), body()).await;
+        assert!(created.is_ok());
+        assert_eq!(
+            state.storage().get_leaf_hash(0).await.unwrap(),
+            Sha256::digest(&value).to_vec()
+        );
+
+        // A Content-Length that disagrees with the body is refused
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_LENGTH, "10".parse().unwrap());
+        let result = stream_commitment(State(state.clone()), headers, body()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 1);
+
+        // Storage that keeps values can't take a hash in their place
+        let state = AppState::builder(Arc::new(MemoryStorage::new())).build();
+        let result = stream_commitment(State(state), HeaderMap::new(), body()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_root_cover_spans_roots() {
+        let state = AppState::builder(Arc::new(MemoryStorage::new())).build();
+        let mut roots = Vec::new();
+        for i in 0..3 {
+            let (_, root) = state
+                .storage()
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+            roots.push(root);
+        }
+
+        let cover = |start, end| get_root_cover(State(state.clone()), Query(RangeQuery { start, end }));
+        let Json(response) = cover(1, 3).await.unwrap();
+        assert_eq!(response.roots, roots[1..].to_vec());
+        let Json(response) = cover(0, 1).await.unwrap();
+        assert_eq!(response.roots, vec![roots[0].clone()]);
+
+        for (start, end) in [(2, 2), (0, 4)] {
+            assert!(matches!(cover(start, end).await, Err(AppError::InvalidInput(_))));
+        }
+
+        // Ranges are slot positions, with expired slots skipped rather than shifted over
+        let expired = CommitmentMetadata {
+            expires_at: Some(0),
+            ..Default::default()
+        };
+        state.storage().store_commitment(None, b"gone".to_vec(), expired).await.unwrap();
+        let (_, last) = state.storage().add_commitment(b"data4".to_vec()).await.unwrap();
+        let Json(response) = cover(3, 5).await.unwrap();
+        assert_eq!(response.roots, vec![last]);
+        let Json(response) = cover(3, 4).await.unwrap();
+        assert!(response.roots.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_insert_commitment() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        let request = || AddCommitmentRequest {
+            value: b"keyed".to_vec(),
+            content_type: None,
+            ttl_seconds: None,
+        };
+
+        let created = insert_commitment(State(state.clone()), Path(3), None, Json(request()))
+            .await
+            .unwrap();
+        assert_eq!(created.location, "/api/v1/commitments/3");
+        assert_eq!(created.body.index, 3);
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 4);
+
+        let result = insert_commitment(State(state), Path(3), None, Json(request())).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_commitments_page() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        for i in 0..5u8 {
+            state.storage().add_commitment(vec![i]).await.unwrap();
+        }
+        let page = |offset, limit| {
+            Query(PageQuery {
+                offset: Some(offset),
+                limit: Some(limit),
+            })
+        };
+
+        let Json(response) = get_commitments_page(State(state.clone()), page(2, 2)).await.unwrap();
+        assert_eq!(response.items.iter().map(|c| c.value.clone()).collect::<Vec<_>>(), vec![vec![2], vec![3]]);
+        assert_eq!(response.total, 5);
+        assert!(response.has_next && response.has_prev);
+
+        let Json(response) = get_commitments_page(State(state.clone()), page(4, 2)).await.unwrap();
+        assert_eq!(response.items.len(), 1);
+        assert!(!response.has_next);
+
+        let result = get_commitments_page(State(state), page(0, 0)).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_root_encodings() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        state.storage().add_commitment(b"data".to_vec()).await.unwrap();
+        let root = state.storage().get_root_hash().await.unwrap();
+
+        let (_, Json(default)) = get_root(State(state.clone()), Query(EncodingQuery::default()), snake())
+            .await
+            .unwrap();
+        let default = snake_body(default);
+        assert_eq!(default.root_encoded, hex::encode(&root));
+
+        for (name, expected) in [
+            ("hex", Encoding::Hex),
+            ("base58", Encoding::Base58),
+            ("base64", Encoding::Base64),
+            ("multibase", Encoding::Multibase),
+        ] {
+            let (_, Json(response)) = get_root(State(state.clone()), encoding(name), snake()).await.unwrap();
+            let response = snake_body(response);
+            assert_eq!(response.root, root);
+            assert_eq!(response.root_encoded, expected.encode(&root));
+        }
+
+        let result = get_root(State(state), encoding("rot13"), snake()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_tree_node() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        for i in 0..3 {
+            state.storage().add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        let Json(root) = get_tree_node(State(state.clone()), Path(0)).await.unwrap();
+        assert_eq!(root.hash, state.storage().get_root_hash().await.unwrap());
+        let Json(leaf) = get_tree_node(State(state.clone()), Path(5)).await.unwrap();
+        assert_eq!(leaf.hash, state.storage().get_leaf_hash(2).await.unwrap());
+
+        let result = get_tree_node(State(state), Path(6)).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stale_root_does_not_wait_on_writes() {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            batch_window: Some(Duration::from_millis(500)),
+            ..Default::default()
+        });
+        let state = AppState::builder(Arc::new(storage)).serve_stale_root(true).build();
+        let (_, root) = state.storage().insert_at(0, b"first".to_vec()).await.unwrap();
+
+        let writers: Vec<_> = (0..3)
+            .map(|i| {
+                let storage = state.storage();
+                tokio::spawn(async move { storage.add_commitment(vec![i]).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let read = get_root(State(state.clone()), Query(EncodingQuery::default()), snake());
+        let (headers, Json(response)) = tokio::time::timeout(Duration::from_millis(100), read)
+            .await
+            .expect("root read blocked on pending writes")
+            .unwrap();
+        let response = snake_body(response);
+        assert_eq!(response.root, root);
+        assert_eq!(response.commitment_count, 1);
+        assert_eq!(headers[ROOT_STALENESS_HEADER], "3");
+
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+        let (headers, Json(response)) = get_root(State(state), Query(EncodingQuery::default()), snake())
+            .await
+            .unwrap();
+        assert_eq!(snake_body(response).commitment_count, 4);
+        assert_eq!(headers[ROOT_STALENESS_HEADER], "0");
+    }
+
+    #[tokio::test]
+    async fn test_commit_block_proves_whole_block() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        for i in 0..3 {
+            state.storage().add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        let block: Vec<Vec<u8>> = ["apple", "banana", "cherry", "date"].iter().map(|v| v.as_bytes().to_vec()).collect();
+        let request = CommitBlockRequest { values: block.clone() };
+        let created = commit_block(State(state.clone()), None, Json(request)).await.unwrap();
+        assert_eq!(created.location, "/api/v1/commitments/3");
+
+        let root = state.storage().get_root_hash().await.unwrap();
+        for i in 0..2 {
+            state.storage().add_commitment(format!("after{}", i).into_bytes()).await.unwrap();
+        }
+
+        let proof = created.body.proof;
+        assert_eq!((created.body.block_start, created.body.block_len), (3, 4));
+        assert_eq!(proof.root, root);
+        assert!(proof.verify());
+        let leaf_hashes: Vec<Vec<u8>> = block.iter().map(|v| sha256(&[v])).collect();
+        assert_eq!(proof.leaf_hashes, leaf_hashes);
+        let block_tree = MerkleTree::from_leaves(block.iter().map(|v| MerkleNode::new_leaf(v)).collect());
+        assert_eq!(proof.block_root, block_tree.root_hash().unwrap());
+
+        // The same block proves against the grown tree too, under the same block root
+        let snapshot = state.storage().snapshot().await.unwrap();
+        let later = snapshot.tree().generate_block_proof(3, 4).unwrap();
+        assert!(later.verify());
+        assert_eq!(later.block_root, proof.block_root);
+        assert_eq!(later.root, state.storage().get_root_hash().await.unwrap());
+
+        // A forged leaf breaks the proof
+        let mut forged = proof.clone();
+        forged.leaf_hashes[2] = sha256(&[b"mallory"]);
+        assert!(!forged.verify());
+
+        let unsorted = CommitBlockRequest { values: vec![b"b".to_vec(), b"a".to_vec()] };
+        let result = commit_block(State(state), None, Json(unsorted)).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_add_commitments_batch() {
+        let storage = Arc::new(MemoryStorage::new());
+        let state = AppState::new(storage.clone());
+        state.storage().add_commitment(b"first".to_vec()).await.unwrap();
+        let rebuilds = storage.rebuild_count();
+
+        let values: Vec<Vec<u8>> = (0..5).map(|i| format!("batch{}", i).into_bytes()).collect();
+        let created = add_commitments_batch(State(state.clone()), None, Json(BatchCommitRequest { values }))
+            .await
+            .unwrap();
+        assert_eq!(created.location, "/api/v1/commitments/1");
+        assert_eq!(created.body.indices, vec![1, 2, 3, 4, 5]);
+        assert_eq!(created.body.merkle_root, state.storage().get_root_hash().await.unwrap());
+        assert_eq!(storage.rebuild_count(), rebuilds + 1);
+        assert_eq!(state.storage().get_commitment(3).await.unwrap().value, b"batch2".to_vec());
+
+        // One bad value rejects the whole batch before anything is written
+        let values = vec![b"ok".to_vec(), Vec::new()];
+        let result = add_commitments_batch(State(state.clone()), None, Json(BatchCommitRequest { values })).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(message)) if message.starts_with("Value 1")));
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_root_response_naming() {
+        let storage = Arc::new(MemoryStorage::new());
+        let naming = |value: &str| {
+            Query(NamingQuery {
+                naming: Some(value.to_string()),
+            })
+        };
+        let keys = |body: &Named<RootResponse, RootResponseCamel>| {
+            let value = serde_json::to_value(body).unwrap();
+            let mut keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+
+        let state = AppState::new(storage.clone());
+        state.storage().add_commitment(b"data".to_vec()).await.unwrap();
+        let (_, Json(body)) = get_root(State(state.clone()), Query(EncodingQuery::default()), snake())
+            .await
+            .unwrap();
+        assert_eq!(keys(&body), ["commitment_count", "root", "root_encoded"]);
+        let (_, Json(body)) = get_root(State(state.clone()), Query(EncodingQuery::default()), naming("camelCase"))
+            .await
+            .unwrap();
+        assert_eq!(keys(&body), ["commitmentCount", "root", "rootEncoded"]);
+        let result = get_root(State(state), Query(EncodingQuery::default()), naming("kebab")).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+
+        // A camelCase server default can still be overridden per request
+        let state = AppState::builder(storage).json_naming(JsonNaming::CamelCase).build();
+        let (_, Json(body)) = get_root(State(state.clone()), Query(EncodingQuery::default()), snake())
+            .await
+            .unwrap();
+        assert_eq!(keys(&body), ["commitmentCount", "root", "rootEncoded"]);
+        let (_, Json(body)) = get_root(State(state), Query(EncodingQuery::default()), naming("snake_case"))
+            .await
+            .unwrap();
+        assert_eq!(keys(&body), ["commitment_count", "root", "root_encoded"]);
+    }
+}
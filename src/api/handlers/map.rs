@@ -0,0 +1,99 @@
+use crate::api::state::AppState;
+use crate::dto::request::{CommitMapRequest, KeyQuery};
+use crate::dto::response::{CommitMapResponse, Created, KeyProofResponse};
+use crate::error::AppError;
+use crate::models::keyed_map::{KeyedEntry, KeyedMap};
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+
+/// Commit a batch of key→value entries as one map sorted by key
+pub async fn commit_map(
+    State(state): State<AppState>,
+    Json(request): Json<CommitMapRequest>,
+) -> Result<Created<CommitMapResponse>, AppError> {
+    let entries = request
+        .entries
+        .into_iter()
+        .map(|e| KeyedEntry {
+            key: e.key,
+            value: e.value,
+        })
+        .collect();
+    let map = KeyedMap::from_entries(entries)?;
+    let (root, leaf_count) = (map.root(), map.len());
+    let map_id = state.add_map(map);
+
+    Ok(Created {
+        location: format!("/api/v1/maps/{}", map_id),
+        body: CommitMapResponse {
+            map_id,
+            root,
+            leaf_count,
+        },
+    })
+}
+
+/// Prove a key's value in a committed map, or that the key is absent
+pub async fn get_key_proof(
+    State(state): State<AppState>,
+    Path(map_id): Path<usize>,
+    Query(query): Query<KeyQuery>,
+) -> Result<Json<KeyProofResponse>, AppError> {
+    let key = hex::decode(&query.key)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid key hex: {}", e)))?;
+    let map = state.map(map_id)?;
+
+    Ok(Json(KeyProofResponse {
+        map_id,
+        root: map.root(),
+        leaf_count: map.len(),
+        proof: map.generate_proof_for_key(&key),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::request::MapEntryRequest;
+    use crate::models::keyed_map::KeyProof;
+    use crate::storage::memory::MemoryStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_commit_map_and_prove_keys() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        let entries = ["b", "a", "d"]
+            .iter()
+            .map(|k| MapEntryRequest {
+                key: k.as_bytes().to_vec(),
+                value: b"v".to_vec(),
+            })
+            .collect();
+        let created = commit_map(State(state.clone()), Json(CommitMapRequest { entries }))
+            .await
+            .unwrap();
+        assert_eq!(created.location, "/api/v1/maps/0");
+
+        let prove = |key: &str| {
+            get_key_proof(
+                State(state.clone()),
+                Path(0),
+                Query(KeyQuery {
+                    key: hex::encode(key),
+                }),
+            )
+        };
+        let Json(present) = prove("d").await.unwrap();
+        assert!(matches!(present.proof, KeyProof::Present(_)));
+        assert!(present.proof.verify(&created.body.root, created.body.leaf_count));
+        let Json(absent) = prove("c").await.unwrap();
+        assert!(matches!(absent.proof, KeyProof::Absent { .. }));
+        assert!(absent.proof.verify(&created.body.root, created.body.leaf_count));
+
+        let missing = get_key_proof(State(state), Path(1), Query(KeyQuery { key: "00".to_string() })).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+}
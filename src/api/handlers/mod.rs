@@ -1,3 +1,13 @@
 pub mod proof;
 
-pub mod commitment;
\ No newline at end of file
+pub mod commitment;
+
+pub mod stats;
+
+pub mod replication;
+
+pub mod admin;
+
+pub mod map;
+
+pub mod reveal;
\ No newline at end of file
@@ -1,54 +1,1067 @@
-use crate::api::state::AppState;
-use crate::crypto::proof::{generate_proof, MerkleProof};
-use crate::dto::response::{CommitmentResponse, ProofResponse};
+use crate::api::{extract::HexPath, middleware::RawBody, state::AppState};
+use crate::crypto::ct;
+use crate::crypto::proof::{
+    generate_consistency_proof, generate_proof_extension, generate_range_proof,
+    ConsistencyProof, MerkleProof, RangeProof,
+};
+use crate::dto::encoding::Encoding;
+use crate::dto::request::{
+    BatchProofRequest, ConsistencyQuery, CtVerifyRequest, EncodingQuery, ExtendQuery, PreviewQuery, RangeQuery,
+    VerifyTrustedRequest,
+};
+use crate::dto::response::{
+    CommitmentResponse, Conditional, ConsistencyProofResponse, CtInclusionProofResponse, CtVerifyResponse, JsonLdProofResponse, LeafHashResponse, NodePathResponse, ProofExtensionResponse, ProofResponse, RangeProofResponse, VerifyResponse,
+};
 use crate::error::AppError;
+use crate::models::merkle::EMPTY_LEAF_HASH;
+use crate::storage::traits::{CommitmentStorage, TreeSnapshot};
 
 use axum::{
-    extract::{Path, State},
-    Json,
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
+use futures_util::stream;
+use sha2::{Digest, Sha256};
 
 /// Get a specific commitment by index
 pub async fn get_commitment(
     State(state): State<AppState>,
     Path(index): Path<usize>,
+    Query(query): Query<PreviewQuery>,
+) -> Result<Json<CommitmentResponse>, AppError> {
+    let commitment = state.storage().get_commitment(index).await?;
+    Ok(Json(CommitmentResponse::from(commitment).with_preview(query.preview)))
+}
+
+/// Serve a commitment's value as-is, with the content type it was committed with
+pub async fn get_commitment_raw(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+) -> Result<Response, AppError> {
+    let commitment = state.storage().get_commitment(index).await?;
+    let content_type = commitment
+        .content_type
+        .as_deref()
+        .and_then(|ct| HeaderValue::from_str(ct).ok())
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+
+    Ok(([(header::CONTENT_TYPE, content_type)], Extension(RawBody), commitment.value).into_response())
+}
+
+/// Get the leaf hash of a commitment without its value
+pub async fn get_leaf_hash(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+) -> Result<Json<LeafHashResponse>, AppError> {
+    let leaf_hash = state.storage().get_leaf_hash(index).await?;
+    Ok(Json(LeafHashResponse { index, leaf_hash }))
+}
+
+/// Get the hashes of the nodes on the path from the root to a commitment's leaf
+pub async fn get_node_path(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+) -> Result<Json<NodePathResponse>, AppError> {
+    // Fails for missing and expired commitments
+    state.storage().get_leaf_hash(index).await?;
+    let node_hashes = state
+        .storage()
+        .read_tree(move |tree| tree.node_path(index))
+        .await?
+        .ok_or(AppError::NotFound(format!(
+            "Commitment with index {} not found",
+            index
+        )))?;
+
+    Ok(Json(NodePathResponse { index, node_hashes }))
+}
+
+/// Get a commitment by its leaf hash
+pub async fn get_commitment_by_hash(
+    State(state): State<AppState>,
+    HexPath(leaf_hash): HexPath,
 ) -> Result<Json<CommitmentResponse>, AppError> {
-    let commitment = state.storage.get_commitment(index).await?;
+    let (hash_only, mode) = (state.storage().stores_hashes_only(), state.storage().leaf_hash_mode());
+    let commitments = state.storage().get_all_commitments().await?;
+    let commitment = commitments
+        .into_iter()
+        .find(|c| !c.placeholder && c.leaf(hash_only, mode).hash == leaf_hash)
+        .ok_or(AppError::NotFound(format!(
+            "Commitment with leaf hash {} not found",
+            hex::encode(&leaf_hash)
+        )))?;
+
+    Ok(Json(commitment.into()))
+}
+
+/// Get the commitment whose write produced a root, to tie a published root to its event
+pub async fn get_commitment_by_root(
+    State(state): State<AppState>,
+    HexPath(root): HexPath,
+) -> Result<Json<CommitmentResponse>, AppError> {
+    let commitment = state.storage().get_commitment_by_root(&root).await?;
     Ok(Json(commitment.into()))
 }
 
-/// Get Merkle proof for a commitment
+/// Strong ETag for the proof of `index` against `root`: SHA-256(index || root), quoted
+pub fn proof_etag(index: usize, root: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((index as u64).to_be_bytes());
+    hasher.update(root);
+    format!("\"{}\"", hex::encode(hasher.finalize()))
+}
+
+/// Get Merkle proof for a commitment. A proof only changes with the root, so it is
+/// tagged by index and root and `If-None-Match` gets a `304` while the root stands.
+#[tracing::instrument(skip(state, query, headers), fields(root_prefix, proof_len))]
 pub async fn get_proof(
     State(state): State<AppState>,
     Path(index): Path<usize>,
-) -> Result<Json<ProofResponse>, AppError> {
-    // Get commitment
-    let commitment = state.storage.get_commitment(index).await?;
+    Query(query): Query<EncodingQuery>,
+    headers: HeaderMap,
+) -> Result<Conditional<ProofResponse>, AppError> {
+    let encoding = Encoding::from_query(query.encoding.as_deref())?;
+    // Say so outright rather than reporting the index as missing
+    if state.storage().commitment_count().await? == 0 {
+        return Err(AppError::NotFound("tree is empty".to_string()));
+    }
+
+    // Shared with the storage cache; repeated requests reuse the same proof
+    let proof = state.storage().get_proof(index).await?;
+
+    let span = tracing::Span::current();
+    span.record("root_prefix", hex::encode(&proof.root[..proof.root.len().min(4)]));
+    span.record("proof_len", proof.proof.len());
+
+    let if_none_match = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok());
+    Ok(Conditional::new(
+        proof_etag(index, &proof.root),
+        if_none_match,
+        ProofResponse::from(proof).with_encoding(encoding),
+    ))
+}
+
+/// Get proofs for several indices in one request, up to the configured batch limit
+pub async fn get_batch_proofs(
+    State(state): State<AppState>,
+    Json(request): Json<BatchProofRequest>,
+) -> Result<Json<Vec<ProofResponse>>, AppError> {
+    // Refuse oversized batches before generating anything
+    let max_batch = state.config.max_batch_proofs;
+    if request.indices.len() > max_batch {
+        return Err(AppError::InvalidInput(format!(
+            "Batch requests {} proofs (max {})",
+            request.indices.len(),
+            max_batch
+        )));
+    }
+
+    let mut proofs = Vec::with_capacity(request.indices.len());
+    for index in request.indices {
+        proofs.push(state.storage().get_proof(index).await?.into());
+    }
+
+    Ok(Json(proofs))
+}
+
+/// Get the sibling hashes that extend an old proof for `index` to the current root
+pub async fn extend_proof(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+    Query(query): Query<ExtendQuery>,
+) -> Result<Json<ProofExtensionResponse>, AppError> {
+    let from_root = hex::decode(&query.from_root)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid from_root hex: {}", e)))?;
 
-    // Get tree
-    let tree = state.storage.get_tree().await?;
-    let root = tree.root().ok_or(AppError::NotFound(
-        "Merkle tree root not found".to_string(),
-    ))?;
+    // One snapshot of every slot; each records the root it produced, which gives the
+    // old tree size
+    let snapshot = state.storage().snapshot().await?;
+    let old_size = snapshot
+        .commitments()
+        .iter()
+        .rposition(|c| c.merkle_root == from_root)
+        .map(|position| position + 1)
+        .ok_or(AppError::NotFound(format!("Unknown root {}", query.from_root)))?;
+    if index >= old_size {
+        return Err(AppError::InvalidInput(format!(
+            "Commitment {} is not in the tree with root {}",
+            index, query.from_root
+        )));
+    }
 
-    // Generate proof
-    let proof_elements = generate_proof(root, index, tree.leaf_count());
+    let old_tree = snapshot
+        .tree_at(old_size)
+        .filter(|tree| tree.root_hash().as_ref() == Some(&from_root))
+        .ok_or(AppError::InvalidInput(format!(
+            "Root {} can't be reproduced from the current log",
+            query.from_root
+        )))?;
 
-    let proof = MerkleProof::new(
-        index,
-        commitment.value,
-        proof_elements,
-        root.hash.clone(),
-    );
+    let (Some(old_root), Some(new_root)) = (old_tree.root(), snapshot.tree().root()) else {
+        return Err(AppError::NotFound("Merkle tree root not found".to_string()));
+    };
+    let extension =
+        generate_proof_extension(old_root, old_size, new_root, snapshot.leaf_count(), index);
+
+    Ok(Json(extension.into()))
+}
+
+/// Prove the tree of the first `from` commitments is a prefix of the tree of the
+/// first `to`, so an auditor holding both roots can confirm the log was only appended to
+pub async fn get_consistency_proof(
+    State(state): State<AppState>,
+    Query(query): Query<ConsistencyQuery>,
+) -> Result<Json<ConsistencyProofResponse>, AppError> {
+    // One snapshot of every slot, so sizes count the leaves the tree is built over
+    let snapshot = state.storage().snapshot().await?;
+    let size = snapshot.leaf_count();
+    if query.from == 0 || query.from > query.to || query.to > size {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid consistency range from {} to {} for {} commitments",
+            query.from, query.to, size
+        )));
+    }
+
+    let new_tree = snapshot
+        .tree_at(query.to)
+        .ok_or(AppError::NotFound("Merkle tree root not found".to_string()))?;
+    let Some(root) = new_tree.root() else {
+        return Err(AppError::NotFound("Merkle tree root not found".to_string()));
+    };
+    // Roots the storage actually published, not ones recomputed from the current
+    // slots, which a delete since then could have changed
+    let old_root = published_root(&state, &snapshot, query.from).await?;
+    let new_root = match query.to == size {
+        true => root.hash.clone(),
+        false => published_root(&state, &snapshot, query.to).await?,
+    };
+
+    let proof = ConsistencyProof::new(query.from, query.to, generate_consistency_proof(root, query.from, query.to));
+    if !proof.verify(&old_root, &new_root) {
+        return Err(AppError::Conflict(format!(
+            "The tree of {} commitments is not a prefix of the tree of {}",
+            query.from, query.to
+        )));
+    }
+
+    Ok(Json(ConsistencyProofResponse {
+        proof,
+        old_root,
+        new_root,
+    }))
+}
+
+/// The root published when the tree had `size` slots: the one recorded by the write
+/// that filled slot `size - 1`, which must be in the root history if the backend
+/// keeps one
+async fn published_root(state: &AppState, snapshot: &TreeSnapshot, size: usize) -> Result<Vec<u8>, AppError> {
+    let not_found = || AppError::NotFound(format!("No root was published for {} commitments", size));
+    let root = snapshot
+        .commitments()
+        .get(size.wrapping_sub(1))
+        .map(|c| c.merkle_root.clone())
+        .ok_or_else(not_found)?;
+    let history = state.storage().root_history().await?;
+    if !history.is_empty() && !history.contains(&root) {
+        return Err(not_found());
+    }
+    Ok(root)
+}
+
+/// Stream a proof for every committed index as NDJSON, all against the same root
+pub async fn export_all_proofs(State(state): State<AppState>) -> Result<Response, AppError> {
+    // One snapshot, so every proof is against the same root and the values it covers
+    let snapshot = state.storage().snapshot().await?;
+
+    // Proofs are generated one line at a time as the client reads. Expired
+    // commitments are still leaves, but their values aren't served.
+    let lines = stream::iter((0..snapshot.leaf_count()).filter_map(move |index| {
+        let proof = snapshot.proof(index).ok()?;
+        let line = serde_json::to_vec(&proof)
+            .map(|mut line| {
+                line.push(b'\n');
+                Bytes::from(line)
+            })
+            .map_err(|e| AppError::Internal(format!("Failed to serialize proof: {}", e)));
+        Some(line)
+    }));
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+/// Get an RFC 6962 (Certificate Transparency) inclusion proof for a commitment.
+/// CT hashing differs from the stored tree, so the CT tree is computed per request.
+pub async fn get_ct_proof(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+) -> Result<Json<CtInclusionProofResponse>, AppError> {
+    // CT leaves hash the raw value, which hash-only storage no longer has
+    if state.storage().stores_hashes_only() {
+        return Err(AppError::InvalidInput(
+            "CT proofs are unavailable when only hashes are stored".to_string(),
+        ));
+    }
+
+    // Fails for missing and expired commitments; a placeholder has no value to prove
+    let snapshot = state.storage().snapshot().await?;
+    if snapshot.commitment(index)?.placeholder {
+        return Err(AppError::NotFound(format!(
+            "Commitment with index {} not found",
+            index
+        )));
+    }
+
+    // Every slot, including ones hidden from reads, is a leaf of the tree. Null slots
+    // take `EMPTY_LEAF_HASH` as in the stored tree, which no CT leaf hash can equal.
+    let leaf_hashes: Vec<Vec<u8>> = snapshot
+        .commitments()
+        .iter()
+        .map(|c| match c.placeholder {
+            true => EMPTY_LEAF_HASH.to_vec(),
+            false => ct::leaf_hash(&c.value),
+        })
+        .collect();
+
+    let base64 = Encoding::Base64;
+    Ok(Json(CtInclusionProofResponse {
+        leaf_index: index,
+        tree_size: leaf_hashes.len(),
+        audit_path: ct::inclusion_proof(&leaf_hashes, index)
+            .iter()
+            .map(|hash| base64.encode(hash))
+            .collect(),
+        root_hash: base64.encode(&ct::root_hash(&leaf_hashes)),
+    }))
+}
+
+/// Get a proof as a JSON-LD document, for verifiable-credentials tooling
+pub async fn get_jsonld_proof(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+) -> Result<Response, AppError> {
+    // One snapshot, so the tree size is the size of the tree the proof is against
+    let snapshot = state.storage().snapshot().await?;
+    let proof = snapshot.proof(index)?;
+    let document = JsonLdProofResponse::new(&proof, snapshot.leaf_count());
+    Ok(([(header::CONTENT_TYPE, "application/ld+json")], Json(document)).into_response())
+}
+
+/// Get a proof for the contiguous leaf range [start, end)
+pub async fn get_range_proof(
+    State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
+) -> Result<Json<RangeProofResponse>, AppError> {
+    // Range proofs rehash the values as plain leaves, which hash-only storage no longer has
+    if state.storage().stores_hashes_only() {
+        return Err(AppError::InvalidInput(
+            "Range proofs are unavailable when only hashes are stored".to_string(),
+        ));
+    }
+    if !state.storage().leaf_hash_mode().is_plain() {
+        return Err(AppError::InvalidInput(
+            "Range proofs are unavailable with length-prefixed leaves".to_string(),
+        ));
+    }
+
+    // One snapshot, so the values are the leaves the boundary was generated around
+    let snapshot = state.storage().snapshot().await?;
+    let total_leaves = snapshot.leaf_count();
+    let Some(root) = snapshot
+        .tree()
+        .root()
+        .filter(|_| range.start < range.end && range.end <= total_leaves)
+    else {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid range {}..{} for {} commitments",
+            range.start, range.end, total_leaves
+        )));
+    };
+    let boundary = generate_range_proof(root, range.start, range.end, total_leaves);
+
+    // Fails if the range covers a commitment that had expired
+    let slots = (range.start..range.end)
+        .map(|index| snapshot.commitment(index))
+        .collect::<Result<Vec<_>, AppError>>()?;
+    let values = slots.iter().map(|c| c.value.to_vec()).collect();
+    let placeholders = slots.iter().filter(|c| c.placeholder).map(|c| c.index).collect();
+
+    let proof = RangeProof::new(range.start, total_leaves, values, boundary, root.hash.clone())
+        .with_placeholders(placeholders);
 
     Ok(Json(proof.into()))
 }
 
 /// Verify a Merkle proof
 pub async fn verify_proof(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Json(proof): Json<MerkleProof>,
-) -> Result<Json<bool>, AppError> {
-    let is_valid = proof.verify();
-    Ok(Json(is_valid))
-}
\ No newline at end of file
+) -> Result<Json<VerifyResponse>, AppError> {
+    let max_path_len = state.config.max_proof_path_len;
+    ensure_path_len(proof.path_len(), max_path_len)?;
+    ensure_hashing(&proof, state.storage().as_ref())?;
+
+    Ok(Json(VerifyResponse {
+        valid: proof.verify_with_max_path(max_path_len),
+        computed_root: proof.compute_root(),
+    }))
+}
+
+/// Verify a proof and require its root to be one of the caller's published roots
+pub async fn verify_trusted_proof(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyTrustedRequest>,
+) -> Result<Json<VerifyResponse>, AppError> {
+    ensure_path_len(req.proof.path_len(), state.config.max_proof_path_len)?;
+    ensure_hashing(&req.proof, state.storage().as_ref())?;
+
+    Ok(Json(VerifyResponse {
+        valid: req.proof.verify_against_roots(&req.trusted_roots),
+        computed_root: req.proof.compute_root(),
+    }))
+}
+
+/// Refuse proofs built with another hash function or leaf encoding than the storage
+/// uses up front; they would only fail verification with no hint why, or worse, pass
+/// against a leaf this storage could never have written
+fn ensure_hashing(proof: &MerkleProof, storage: &dyn CommitmentStorage) -> Result<(), AppError> {
+    let expected = storage.hash_algorithm();
+    if proof.algorithm != expected {
+        return Err(AppError::InvalidInput(format!(
+            "algorithm mismatch: expected {}, got {}",
+            expected, proof.algorithm
+        )));
+    }
+    let expected = storage.stores_hashes_only();
+    if proof.prehashed != expected {
+        return Err(AppError::InvalidInput(format!(
+            "prehashed mismatch: expected {}, got {}",
+            expected, proof.prehashed
+        )));
+    }
+    let expected = storage.leaf_hash_mode();
+    if proof.leaf_mode != expected {
+        return Err(AppError::InvalidInput(format!(
+            "leaf mode mismatch: expected {:?}, got {:?}",
+            expected, proof.leaf_mode
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse oversized paths before spending any time hashing them
+fn ensure_path_len(path_len: usize, max_path_len: usize) -> Result<(), AppError> {
+    if path_len > max_path_len {
+        return Err(AppError::InvalidInput(format!(
+            "Proof path has {} elements (max {})",
+            path_len, max_path_len
+        )));
+    }
+    Ok(())
+}
+
+/// Verify an RFC 6962 inclusion proof, e.g. one issued by another CT log
+pub async fn verify_ct_proof(
+    State(state): State<AppState>,
+    Json(req): Json<CtVerifyRequest>,
+) -> Result<Json<CtVerifyResponse>, AppError> {
+    ensure_path_len(req.audit_path.len(), state.config.max_proof_path_len)?;
+
+    Ok(Json(CtVerifyResponse {
+        valid: req.decode()?.verify(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::HashAlgorithm;
+    use crate::crypto::proof::verify_range_proof;
+    use crate::models::commitment::CommitmentMetadata;
+    use crate::models::merkle::{LeafHashMode, MerkleNode, SingleLeafMode};
+    use base64::Engine;
+    use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+    use std::sync::Arc;
+
+    async fn state_with(count: usize) -> AppState {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        for i in 0..count {
+            state
+                .storage()
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+        }
+        state
+    }
+
+    async fn proof_of(state: &AppState, index: usize) -> ProofResponse {
+        let query = Query(EncodingQuery::default());
+        let proof = get_proof(State(state.clone()), Path(index), query, HeaderMap::new());
+        proof.await.unwrap().body.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_proof_derived_index_is_consistent() {
+        for leaf_count in 1..=16 {
+            let state = state_with(leaf_count).await;
+            for index in 0..leaf_count {
+                let response = proof_of(&state, index).await;
+                assert_eq!(response.derived_index, index, "leaf {} of {}", index, leaf_count);
+                assert!(response.index_consistent);
+            }
+        }
+
+        // A proof claiming another index is flagged
+        let state = state_with(4).await;
+        let mut proof = Arc::unwrap_or_clone(proof_of(&state, 1).await.proof);
+        proof.index = 2;
+        let response = ProofResponse::from(proof);
+        assert_eq!(response.derived_index, 1);
+        assert!(!response.index_consistent);
+    }
+
+    #[tokio::test]
+    async fn test_get_commitment_by_root() {
+        let state = state_with(3).await;
+        let (_, root) = state.storage().add_commitment(b"data3".to_vec()).await.unwrap();
+        state.storage().add_commitment(b"data4".to_vec()).await.unwrap();
+
+        let Json(response) = get_commitment_by_root(State(state.clone()), HexPath(root))
+            .await
+            .unwrap();
+        assert_eq!(response.commitment.index, 3);
+        assert_eq!(response.value.as_ref(), b"data3");
+
+        // Padding stamps placeholders with the same root; the inserted slot produced it
+        let (_, padded) = state.storage().insert_at(8, b"gap".to_vec()).await.unwrap();
+        let Json(response) = get_commitment_by_root(State(state.clone()), HexPath(padded))
+            .await
+            .unwrap();
+        assert_eq!(response.commitment.index, 8);
+
+        let unknown = get_commitment_by_root(State(state), HexPath(vec![0; 32])).await;
+        assert!(matches!(unknown, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_on_empty_tree() {
+        let state = state_with(0).await;
+        for index in [0, 7] {
+            let query = Query(EncodingQuery::default());
+            let result = get_proof(State(state.clone()), Path(index), query, HeaderMap::new()).await;
+            match result {
+                Err(AppError::NotFound(message)) => assert_eq!(message, "tree is empty"),
+                other => panic!("expected NotFound, got {:?}", other.map(|_| ())),
+            }
+        }
+
+        // Once there is a leaf, a missing index is reported as such
+        state.storage().add_commitment(b"data".to_vec()).await.unwrap();
+        let query = Query(EncodingQuery::default());
+        let result = get_proof(State(state), Path(7), query, HeaderMap::new()).await;
+        assert!(matches!(result, Err(AppError::NotFound(message)) if message != "tree is empty"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_proof_limit() {
+        let mut state = state_with(5).await;
+        state.config.max_batch_proofs = 3;
+        let batch = |indices: Vec<usize>| Json(BatchProofRequest { indices });
+
+        let Json(proofs) = get_batch_proofs(State(state.clone()), batch(vec![4, 0, 2])).await.unwrap();
+        assert_eq!(proofs.iter().map(|p| p.proof.index).collect::<Vec<_>>(), vec![4, 0, 2]);
+        assert!(proofs.iter().all(|p| p.proof.verify()));
+
+        let result = get_batch_proofs(State(state), batch(vec![0, 1, 2, 3])).await;
+        let Err(AppError::InvalidInput(message)) = result else {
+            panic!("expected InvalidInput, got {:?}", result.map(|_| ()));
+        };
+        assert!(message.contains("max 3"));
+    }
+
+    #[tokio::test]
+    async fn test_get_node_path() {
+        let state = state_with(5).await;
+        let root = state.storage().get_root_hash().await.unwrap();
+
+        let Json(response) = get_node_path(State(state.clone()), Path(3)).await.unwrap();
+        assert_eq!(response.node_hashes.first(), Some(&root));
+        assert_eq!(response.node_hashes.last(), Some(&MerkleNode::new_leaf(b"data3").hash));
+
+        let result = get_node_path(State(state), Path(5)).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_proof() {
+        let state = state_with(5).await;
+
+        for index in 0..5 {
+            let response = proof_of(&state, index).await;
+            assert_eq!(response.proof.index, index);
+            assert!(response.proof.verify());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_encoding() {
+        let state = state_with(3).await;
+        let query = EncodingQuery {
+            encoding: Some("base58".to_string()),
+        };
+
+        let response = get_proof(State(state.clone()), Path(1), Query(query), HeaderMap::new())
+            .await
+            .unwrap()
+            .body
+            .unwrap();
+        assert_eq!(response.root_encoded, bs58::encode(&response.root).into_string());
+
+        let query = EncodingQuery {
+            encoding: Some("bogus".to_string()),
+        };
+        let result = get_proof(State(state), Path(1), Query(query), HeaderMap::new()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_proof_reports_computed_root() {
+        let state = state_with(3).await;
+        let response = proof_of(&state, 2).await;
+        let mut proof = Arc::unwrap_or_clone(response.proof);
+
+        let Json(result) = verify_proof(State(state.clone()), Json(proof.clone())).await.unwrap();
+        assert!(result.valid);
+        assert_eq!(result.computed_root, proof.root);
+
+        proof.value = b"tampered".to_vec();
+        let Json(result) = verify_proof(State(state), Json(proof.clone())).await.unwrap();
+        assert!(!result.valid);
+        assert_ne!(result.computed_root, proof.root);
+    }
+
+    #[tokio::test]
+    async fn test_verify_proof_rejects_over_length_path() {
+        let state = state_with(3).await;
+        let response = proof_of(&state, 0).await;
+        let mut proof = Arc::unwrap_or_clone(response.proof);
+        proof.proof = vec![proof.proof[0].clone(); state.config.max_proof_path_len + 1];
+
+        let result = verify_proof(State(state), Json(proof)).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_mismatched_algorithm() {
+        let state = state_with(3).await;
+        let response = proof_of(&state, 1).await;
+        let mut json = serde_json::to_value(response.proof.as_ref()).unwrap();
+        assert!(json.get("algorithm").is_none());
+        json["algorithm"] = "blake3".into();
+        let proof: MerkleProof = serde_json::from_value(json).unwrap();
+        assert_eq!(proof.algorithm, HashAlgorithm::Blake3);
+
+        let expected = "algorithm mismatch: expected sha256, got blake3";
+        match verify_proof(State(state.clone()), Json(proof.clone())).await {
+            Err(AppError::InvalidInput(message)) => assert_eq!(message, expected),
+            other => panic!("expected an algorithm mismatch, got {:?}", other.map(|_| ())),
+        }
+        let request = VerifyTrustedRequest {
+            trusted_roots: vec![proof.root.clone()],
+            proof,
+        };
+        match verify_trusted_proof(State(state), Json(request)).await {
+            Err(AppError::InvalidInput(message)) => assert_eq!(message, expected),
+            other => panic!("expected an algorithm mismatch, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_mismatched_leaf_encoding() {
+        let config = MemoryStorageConfig {
+            leaf_hash_mode: LeafHashMode::LengthPrefixed,
+            ..Default::default()
+        };
+        let state = AppState::new(Arc::new(MemoryStorage::with_config(config)));
+        for i in 0..3 {
+            state.storage().add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        let proof = proof_of(&state, 1).await.proof.as_ref().clone();
+        assert!(verify_proof(State(state.clone()), Json(proof.clone())).await.unwrap().valid);
+
+        let plain = proof.clone().with_leaf_mode(LeafHashMode::Plain);
+        let prehashed = proof.with_prehashed(true);
+        for (proof, expected) in [
+            (plain, "leaf mode mismatch: expected LengthPrefixed, got Plain"),
+            (prehashed, "prehashed mismatch: expected false, got true"),
+        ] {
+            match verify_proof(State(state.clone()), Json(proof.clone())).await {
+                Err(AppError::InvalidInput(message)) => assert_eq!(message, expected),
+                other => panic!("expected a leaf encoding mismatch, got {:?}", other.map(|_| ())),
+            }
+            let request = VerifyTrustedRequest {
+                trusted_roots: vec![proof.root.clone()],
+                proof,
+            };
+            match verify_trusted_proof(State(state.clone()), Json(request)).await {
+                Err(AppError::InvalidInput(message)) => assert_eq!(message, expected),
+                other => panic!("expected a leaf encoding mismatch, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_trusted_proof() {
+        let state = state_with(3).await;
+        let published = state.storage().get_root_hash().await.unwrap();
+        let old = proof_of(&state, 1).await;
+        state.storage().add_commitment(b"data3".to_vec()).await.unwrap();
+        let current = proof_of(&state, 1).await;
+        let verify = |proof: &ProofResponse| {
+            let request = VerifyTrustedRequest {
+                proof: proof.proof.as_ref().clone(),
+                trusted_roots: vec![vec![0; 32], published.clone()],
+            };
+            verify_trusted_proof(State(state.clone()), Json(request))
+        };
+
+        // Both proofs are internally valid, but only one root was published
+        assert!(current.proof.verify());
+        assert!(verify(&old).await.unwrap().valid);
+        assert!(!verify(&current).await.unwrap().valid);
+    }
+
+    #[tokio::test]
+    async fn test_extend_proof() {
+        let state = state_with(4).await;
+        let old = proof_of(&state, 2).await;
+
+        for i in 4..8 {
+            state
+                .storage()
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+        }
+        let query = ExtendQuery {
+            from_root: hex::encode(&old.root),
+        };
+        let Json(response) = extend_proof(State(state.clone()), Path(2), Query(query))
+            .await
+            .unwrap();
+
+        let extended = response.extension.apply(&old.proof);
+        assert_eq!(extended.root, state.storage().get_root_hash().await.unwrap());
+        assert!(extended.verify());
+
+        let query = ExtendQuery {
+            from_root: hex::encode([0u8; 32]),
+        };
+        let result = extend_proof(State(state), Path(2), Query(query)).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_extend_proof_counts_every_slot() {
+        let config = MemoryStorageConfig {
+            single_leaf_mode: SingleLeafMode::DuplicateToParent,
+            ..Default::default()
+        };
+        let state = AppState::new(Arc::new(MemoryStorage::with_config(config)));
+        state.storage().add_commitment(b"data0".to_vec()).await.unwrap();
+        let first = proof_of(&state, 0).await;
+        let expired = CommitmentMetadata {
+            expires_at: Some(0),
+            ..Default::default()
+        };
+        state.storage().store_commitment(None, b"gone".to_vec(), expired).await.unwrap();
+        state.storage().add_commitment(b"data2".to_vec()).await.unwrap();
+        let old = proof_of(&state, 2).await;
+        state.storage().add_commitment(b"data3".to_vec()).await.unwrap();
+
+        // Extends past the expired slot, and from a one-leaf tree built by the storage's rules
+        for (index, old) in [(2, old), (0, first)] {
+            let query = ExtendQuery {
+                from_root: hex::encode(&old.root),
+            };
+            let Json(response) = extend_proof(State(state.clone()), Path(index), Query(query))
+                .await
+                .unwrap();
+            let extended = response.extension.apply(&old.proof);
+            assert_eq!(extended.root, state.storage().get_root_hash().await.unwrap());
+            assert!(extended.verify(), "{}", index);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_ct_proof_matches_test_vector() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        let leaves: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0x00],
+            vec![0x10],
+            vec![0x20, 0x21],
+            vec![0x30, 0x31],
+            vec![0x40, 0x41, 0x42, 0x43],
+            (0x50..0x58).collect(),
+            (0x60..0x70).collect(),
+        ];
+        for leaf in leaves {
+            state.storage().add_commitment(leaf).await.unwrap();
+        }
+
+        let Json(response) = get_ct_proof(State(state), Path(0)).await.unwrap();
+        let decode = |s: &String| hex::encode(base64::engine::general_purpose::STANDARD.decode(s).unwrap());
+        assert_eq!(response.leaf_index, 0);
+        assert_eq!(response.tree_size, 8);
+        assert_eq!(
+            response.audit_path.iter().map(decode).collect::<Vec<_>>(),
+            vec![
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ]
+        );
+        assert_eq!(
+            decode(&response.root_hash),
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_ct_proof_around_placeholders() {
+        let state = state_with(4).await;
+        state.storage().delete_commitment(1).await.unwrap();
+
+        let result = get_ct_proof(State(state.clone()), Path(1)).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        let Json(proof) = get_ct_proof(State(state), Path(0)).await.unwrap();
+        let base64 = |s: &String| base64::engine::general_purpose::STANDARD.decode(s).unwrap();
+        let audit_path: Vec<_> = proof.audit_path.iter().map(base64).collect();
+        assert_eq!(audit_path[0], EMPTY_LEAF_HASH.to_vec());
+        assert!(ct::verify_inclusion(&ct::leaf_hash(b"data0"), 0, 4, &audit_path, &base64(&proof.root_hash)));
+
+        // Hash-only storage has no values to hash as CT leaves
+        let config = MemoryStorageConfig {
+            hash_only: true,
+            ..Default::default()
+        };
+        let state = AppState::new(Arc::new(MemoryStorage::with_config(config)));
+        state.storage().add_commitment(b"data0".to_vec()).await.unwrap();
+        let result = get_ct_proof(State(state), Path(0)).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_ct_proof() {
+        let state = state_with(5).await;
+        let Json(proof) = get_ct_proof(State(state.clone()), Path(3)).await.unwrap();
+        let request = |leaf: &[u8], audit_path: Vec<String>| CtVerifyRequest {
+            leaf_index: proof.leaf_index,
+            tree_size: proof.tree_size,
+            audit_path,
+            leaf_hash: Encoding::Base64.encode(&ct::leaf_hash(leaf)),
+            root_hash: proof.root_hash.clone(),
+        };
+
+        let Json(response) = verify_ct_proof(State(state.clone()), Json(request(b"data3", proof.audit_path.clone())))
+            .await
+            .unwrap();
+        assert!(response.valid);
+
+        let Json(response) = verify_ct_proof(State(state.clone()), Json(request(b"data4", proof.audit_path.clone())))
+            .await
+            .unwrap();
+        assert!(!response.valid);
+
+        let mut tampered = proof.audit_path.clone();
+        tampered[0] = Encoding::Base64.encode(&[0; 32]);
+        let Json(response) = verify_ct_proof(State(state.clone()), Json(request(b"data3", tampered)))
+            .await
+            .unwrap();
+        assert!(!response.valid);
+
+        let result = verify_ct_proof(State(state), Json(request(b"data3", vec!["%%".to_string()]))).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_export_all_proofs() {
+        let state = state_with(7).await;
+        let root = state.storage().get_root_hash().await.unwrap();
+
+        let response = export_all_proofs(State(state)).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        let proofs: Vec<MerkleProof> = bytes
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(proofs.len(), 7);
+        for (index, proof) in proofs.iter().enumerate() {
+            assert_eq!(proof.index, index);
+            assert_eq!(proof.root, root);
+            assert!(proof.verify());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_jsonld_proof() {
+        let state = state_with(5).await;
+        let root = state.storage().get_root_hash().await.unwrap();
+
+        let response = get_jsonld_proof(State(state.clone()), Path(3)).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/ld+json");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["@context"][0], "https://w3id.org/security/v2");
+        assert_eq!(json["type"], "MerkleProof2019");
+        assert_eq!(json["treeSize"], 5);
+        assert_eq!(json["merkleRoot"], bs58::encode(&root).into_string());
+
+        let document: JsonLdProofResponse = serde_json::from_value(json).unwrap();
+        let proof = document.to_proof().unwrap();
+        assert_eq!(proof.value, state.storage().get_leaf_hash(3).await.unwrap());
+        assert!(proof.verify());
+
+        let result = get_jsonld_proof(State(state), Path(5)).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_export_all_proofs_skips_expired() {
+        let state = state_with(2).await;
+        let expired = CommitmentMetadata {
+            expires_at: Some(0),
+            ..Default::default()
+        };
+        state.storage().store_commitment(None, b"gone".to_vec(), expired).await.unwrap();
+        state.storage().add_commitment(b"data3".to_vec()).await.unwrap();
+
+        let response = export_all_proofs(State(state)).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let indices: Vec<usize> = bytes
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<MerkleProof>(line).unwrap().index)
+            .collect();
+        assert_eq!(indices, vec![0, 1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_export_all_proofs_empty() {
+        let state = state_with(0).await;
+        let response = export_all_proofs(State(state)).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(bytes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_range_proof() {
+        let state = state_with(6).await;
+
+        let Json(response) = get_range_proof(State(state), Query(RangeQuery { start: 1, end: 4 }))
+            .await
+            .unwrap();
+
+        assert_eq!(response.proof.values.len(), 3);
+        assert_eq!(response.proof.values[0], b"data1".to_vec());
+        assert!(verify_range_proof(&response.proof));
+    }
+
+    #[tokio::test]
+    async fn test_get_range_proof_over_placeholders_and_expired() {
+        let state = state_with(3).await;
+        let expired = CommitmentMetadata {
+            expires_at: Some(0),
+            ..Default::default()
+        };
+        state.storage().store_commitment(None, b"gone".to_vec(), expired).await.unwrap();
+        state.storage().add_commitment(b"data4".to_vec()).await.unwrap();
+        state.storage().delete_commitment(1).await.unwrap();
+
+        let Json(response) = get_range_proof(State(state.clone()), Query(RangeQuery { start: 0, end: 3 }))
+            .await
+            .unwrap();
+        assert_eq!(response.proof.placeholders, vec![1]);
+        assert_eq!(response.proof.values[2], b"data2".to_vec());
+        assert!(verify_range_proof(&response.proof));
+
+        // A null slot in the range can't carry a value
+        let mut forged = response.proof.clone();
+        forged.values[1] = b"I owe you 1M".to_vec();
+        assert!(!verify_range_proof(&forged));
+
+        // The expired slot is still a leaf, but its value isn't served
+        let result = get_range_proof(State(state), Query(RangeQuery { start: 2, end: 5 })).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_range_proof_invalid_range() {
+        let state = state_with(3).await;
+
+        for (start, end) in [(2, 2), (2, 1), (0, 4)] {
+            let result = get_range_proof(State(state.clone()), Query(RangeQuery { start, end })).await;
+            assert!(matches!(result, Err(AppError::InvalidInput(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consistency_proof_between_roots() {
+        let state = state_with(5).await;
+        let old_root = state.storage().get_root_hash().await.unwrap();
+        for i in 5..9 {
+            state
+                .storage()
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+        }
+        let new_root = state.storage().get_root_hash().await.unwrap();
+
+        let query = |from, to| Query(ConsistencyQuery { from, to });
+        let Json(response) = get_consistency_proof(State(state.clone()), query(5, 9)).await.unwrap();
+        assert_eq!((response.old_root.clone(), response.new_root.clone()), (old_root.clone(), new_root.clone()));
+        assert!(response.proof.verify(&old_root, &new_root));
+
+        for (from, to) in [(0, 3), (6, 4), (2, 10)] {
+            let result = get_consistency_proof(State(state.clone()), query(from, to)).await;
+            assert!(matches!(result, Err(AppError::InvalidInput(_))), "{} -> {}", from, to);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consistency_proof_uses_published_roots() {
+        let state = state_with(5).await;
+        let old_root = state.storage().get_root_hash().await.unwrap();
+        let expired = CommitmentMetadata {
+            expires_at: Some(0),
+            ..Default::default()
+        };
+        state.storage().store_commitment(None, b"gone".to_vec(), expired).await.unwrap();
+        state.storage().add_commitment(b"data6".to_vec()).await.unwrap();
+
+        // The expired slot is still a leaf and counts towards the sizes
+        let query = |from, to| Query(ConsistencyQuery { from, to });
+        let Json(response) = get_consistency_proof(State(state.clone()), query(5, 7)).await.unwrap();
+        assert_eq!(response.old_root, old_root);
+        assert_eq!(response.new_root, state.storage().get_root_hash().await.unwrap());
+        assert!(response.proof.verify(&old_root, &response.new_root));
+
+        // Deleting from the old tree rewrites history, which no proof can hide
+        state.storage().delete_commitment(1).await.unwrap();
+        let result = get_consistency_proof(State(state), query(5, 7)).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+    }
+}
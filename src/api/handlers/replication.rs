@@ -0,0 +1,72 @@
+use crate::api::state::AppState;
+use crate::dto::{request::ReplicationQuery, response::CheckpointResponse};
+use crate::error::AppError;
+use crate::models::{commitment::Commitment, merkle::EMPTY_ROOT};
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+
+/// Stream commitments after the follower's cursor, up to the first expired one.
+/// Expired values are never served, and leaving a gap would stall the follower, so
+/// the stream resumes once the sweeper has turned the slot into a placeholder.
+pub async fn get_commitments_after(
+    State(state): State<AppState>,
+    Query(query): Query<ReplicationQuery>,
+) -> Result<Json<Vec<Commitment>>, AppError> {
+    let mut commitments = state.storage().get_commitments_after(query.after).await?;
+    if let Some(expired) = commitments.iter().position(Commitment::is_expired) {
+        commitments.truncate(expired);
+    }
+    Ok(Json(commitments))
+}
+
+/// Get the leader's replication checkpoint
+pub async fn get_checkpoint(
+    State(state): State<AppState>,
+) -> Result<Json<CheckpointResponse>, AppError> {
+    let (last_index, root) = match state.storage().replica_checkpoint().await? {
+        Some((index, root)) => (Some(index), root),
+        None => (None, EMPTY_ROOT.to_vec()),
+    };
+
+    Ok(Json(CheckpointResponse { last_index, root }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::commitment::{unix_now, CommitmentMetadata};
+    use crate::storage::{memory::MemoryStorage, replica::ReplicaApplier, traits::CommitmentStorage};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_replication_stops_before_expired_values() {
+        let leader = Arc::new(MemoryStorage::new());
+        let state = AppState::new(leader.clone());
+        let now = unix_now();
+        leader.add_commitment(b"a".to_vec()).await.unwrap();
+        let expiring = CommitmentMetadata {
+            expires_at: Some(now),
+            ..Default::default()
+        };
+        leader.store_commitment(None, b"secret".to_vec(), expiring).await.unwrap();
+        leader.add_commitment(b"c".to_vec()).await.unwrap();
+
+        let follower = Arc::new(MemoryStorage::new());
+        let applier = ReplicaApplier::new(follower.clone());
+        let fetch = |after| get_commitments_after(State(state.clone()), Query(ReplicationQuery { after }));
+
+        let Json(batch) = fetch(None).await.unwrap();
+        assert_eq!(batch.iter().map(|c| c.index).collect::<Vec<_>>(), vec![0]);
+        applier.apply_all(batch).await.unwrap();
+
+        // Once swept, the slot is served as a placeholder and the follower catches up
+        leader.sweep_expired(now).await.unwrap();
+        let Json(batch) = fetch(applier.cursor().await.unwrap()).await.unwrap();
+        assert!(batch.iter().all(|c| !c.value.starts_with(b"secret")));
+        assert_eq!(applier.apply_all(batch).await.unwrap(), 2);
+        assert_eq!(follower.get_root_hash().await.unwrap(), leader.get_root_hash().await.unwrap());
+    }
+}
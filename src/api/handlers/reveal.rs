@@ -0,0 +1,140 @@
+use crate::api::state::AppState;
+use crate::crypto::proof::HASH_LEN;
+use crate::dto::request::{RevealRequest, SealedCommitRequest};
+use crate::dto::response::{AddCommitmentResponse, Created, ProofResponse};
+use crate::error::AppError;
+use crate::models::commitment::{seal, CommitmentMetadata, RevealState};
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+/// Commit to a value without disclosing it, by storing its sealed hash
+pub async fn commit_sealed(
+    State(state): State<AppState>,
+    Json(request): Json<SealedCommitRequest>,
+) -> Result<Created<AddCommitmentResponse>, AppError> {
+    if request.hash.len() != HASH_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Sealed hash must be {} bytes (got {})",
+            HASH_LEN,
+            request.hash.len()
+        )));
+    }
+
+    let metadata = CommitmentMetadata {
+        reveal: Some(RevealState::Sealed),
+        ..Default::default()
+    };
+    // Hash-only storage would hash the sealed hash again; store it as the leaf instead
+    let storage = state.storage();
+    let (index, merkle_root) = if storage.stores_hashes_only() {
+        storage.store_leaf_hash(request.hash, metadata).await?
+    } else {
+        storage.store_commitment(None, request.hash, metadata).await?
+    };
+
+    Ok(Created::commitment(index, AddCommitmentResponse::new(index, merkle_root)))
+}
+
+/// Reveal the value and nonce behind a sealed commitment, returning its proof once
+/// they match
+pub async fn reveal(
+    State(state): State<AppState>,
+    Path(index): Path<usize>,
+    Json(request): Json<RevealRequest>,
+) -> Result<Json<ProofResponse>, AppError> {
+    let storage = state.storage();
+    let commitment = storage.get_commitment(index).await?;
+    match commitment.reveal {
+        Some(RevealState::Sealed) => {}
+        Some(RevealState::Revealed) => {
+            return Err(AppError::Conflict(format!(
+                "Commitment {} was already revealed",
+                index
+            )))
+        }
+        None => {
+            return Err(AppError::InvalidInput(format!(
+                "Commitment {} was not made through commit-reveal",
+                index
+            )))
+        }
+    }
+    if commitment.value != seal(&request.value, &request.nonce) {
+        return Err(AppError::InvalidInput(format!(
+            "Value and nonce do not match commitment {}",
+            index
+        )));
+    }
+
+    storage.mark_revealed(index).await?;
+    Ok(Json(ProofResponse::from(storage.get_proof(index).await?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::sha256;
+    use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+    use std::sync::Arc;
+
+    fn reveal_request(value: &[u8], nonce: &[u8]) -> Json<RevealRequest> {
+        Json(RevealRequest {
+            value: value.to_vec(),
+            nonce: nonce.to_vec(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_commit_then_reveal() {
+        for hash_only in [false, true] {
+            let storage = MemoryStorage::with_config(MemoryStorageConfig {
+                hash_only,
+                ..Default::default()
+            });
+            let state = AppState::new(Arc::new(storage));
+            // Clients compute seals themselves, so the format is fixed
+            let hash = seal(b"bid: 42", b"nonce");
+            assert_eq!(hash, sha256(&[b"bid: 42nonce"]));
+            let created = commit_sealed(State(state.clone()), Json(SealedCommitRequest { hash }))
+                .await
+                .unwrap();
+            let index = created.body.index;
+
+            let Json(response) = reveal(State(state.clone()), Path(index), reveal_request(b"bid: 42", b"nonce"))
+                .await
+                .unwrap();
+            assert!(response.proof.verify());
+            assert_eq!(response.root, created.body.merkle_root);
+            let commitment = state.storage().get_commitment(index).await.unwrap();
+            assert_eq!(commitment.reveal, Some(RevealState::Revealed));
+
+            // A second reveal is refused
+            let again = reveal(State(state), Path(index), reveal_request(b"bid: 42", b"nonce")).await;
+            assert!(matches!(again, Err(AppError::Conflict(_))), "{}", hash_only);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_reveal_is_rejected() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        let hash = seal(b"bid: 42", b"nonce");
+        commit_sealed(State(state.clone()), Json(SealedCommitRequest { hash }))
+            .await
+            .unwrap();
+
+        for (value, nonce) in [(&b"bid: 43"[..], &b"nonce"[..]), (b"bid: 42", b"other")] {
+            let result = reveal(State(state.clone()), Path(0), reveal_request(value, nonce)).await;
+            assert!(matches!(result, Err(AppError::InvalidInput(_))));
+        }
+        let commitment = state.storage().get_commitment(0).await.unwrap();
+        assert_eq!(commitment.reveal, Some(RevealState::Sealed));
+
+        // Ordinary commitments can't be revealed
+        state.storage().add_commitment(b"plain".to_vec()).await.unwrap();
+        let result = reveal(State(state), Path(1), reveal_request(b"plain", b"")).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::api::state::AppState;
+use crate::dto::response::StatsResponse;
+use crate::error::AppError;
+
+use axum::{extract::State, Json};
+
+/// Get aggregate statistics
+pub async fn get_stats(State(state): State<AppState>) -> Result<Json<StatsResponse>, AppError> {
+    let commitment_count = state.storage().commitment_count().await?;
+    let total_bytes = state.storage().total_bytes().await?;
+    let tree = state.storage().get_tree().await?;
+
+    let average_leaf_size = if commitment_count == 0 {
+        0.0
+    } else {
+        total_bytes as f64 / commitment_count as f64
+    };
+
+    Ok(Json(StatsResponse {
+        commitment_count,
+        root_hex: tree.root_hash().map(hex::encode),
+        tree_height: tree.height(),
+        average_leaf_size,
+        total_bytes,
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_stats_aggregate() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        for value in [b"ab".to_vec(), b"abcd".to_vec(), b"abcdef".to_vec()] {
+            state.storage().add_commitment(value).await.unwrap();
+        }
+
+        let Json(stats) = get_stats(State(state.clone())).await.unwrap();
+        let root = state.storage().get_root_hash().await.unwrap();
+
+        assert_eq!(stats.commitment_count, 3);
+        assert_eq!(stats.root_hex, Some(hex::encode(root)));
+        assert_eq!(stats.tree_height, 3);
+        assert_eq!(stats.total_bytes, 12);
+        assert_eq!(stats.average_leaf_size, 4.0);
+    }
+
+    #[tokio::test]
+    async fn test_stats_empty() {
+        let state = AppState::new(Arc::new(MemoryStorage::new()));
+        let Json(stats) = get_stats(State(state)).await.unwrap();
+
+        assert_eq!(stats.commitment_count, 0);
+        assert_eq!(stats.root_hex, None);
+        assert_eq!(stats.tree_height, 0);
+        assert_eq!(stats.average_leaf_size, 0.0);
+    }
+}
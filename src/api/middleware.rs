@@ -0,0 +1,184 @@
+use crate::api::state::{AppState, KeyAccess};
+use crate::dto::response::{Envelope, EnvelopeMeta};
+use crate::error::AppError;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http_body_util::Limited;
+use jsonwebtoken::Validation;
+use serde::{Deserialize, Serialize};
+
+/// POST endpoints that don't change any state
+const READ_ONLY_POSTS: &[&str] = &[
+    "/api/v1/proof/verify",
+    "/api/v1/proof/verify/trusted",
+    "/api/v1/proof/batch",
+    "/api/v1/proof/ct/verify",
+];
+
+/// Header carrying a scoped API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Claims carried by an API bearer token; available to handlers as a request extension
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    /// Who the token was issued to
+    #[serde(default)]
+    pub sub: Option<String>,
+    /// Expiry as seconds since the Unix epoch
+    pub exp: u64,
+}
+
+/// Reject bodies over `max_size` bytes: up front when the declared Content-Length
+/// exceeds it, otherwise when reading passes it
+pub async fn limit_body(
+    State(max_size): State<usize>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > max_size as u64) {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Request body exceeds {} bytes",
+            max_size
+        )));
+    }
+    let request = request.map(|body| Body::new(Limited::new(body, max_size)));
+    Ok(next.run(request).await)
+}
+
+/// Require a valid JWT bearer token on write and admin endpoints (and on reads
+/// when configured). Passes everything through when auth isn't configured.
+pub async fn require_auth(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(auth) = &state.config.auth else {
+        return Ok(next.run(request).await);
+    };
+    if !auth.protect_reads && is_read_only(&request) {
+        return Ok(next.run(request).await);
+    }
+
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized("Missing bearer token".to_string()))?;
+    let claims = jsonwebtoken::decode::<Claims>(token, &auth.key, &Validation::new(auth.algorithm))
+        .map_err(|e| AppError::Unauthorized(format!("Invalid token: {}", e)))?
+        .claims;
+
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+/// Require a known `x-api-key` when API keys are configured. Writes with a read-only
+/// key, and requests outside a tree-scoped key's trees, are refused with 403.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let keys = &state.config.api_keys;
+    if keys.is_empty() || request.uri().path() == "/health" {
+        return Ok(next.run(request).await);
+    }
+
+    let scope = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|key| keys.get(key))
+        .ok_or(AppError::Unauthorized("Missing or unknown API key".to_string()))?;
+    if scope.access == KeyAccess::ReadOnly && !is_read_only(&request) {
+        return Err(AppError::Forbidden("API key is read-only".to_string()));
+    }
+    let path = request.uri().path();
+    if let Some(trees) = &scope.trees
+        && !map_id(path).is_some_and(|id| trees.contains(&id))
+    {
+        return Err(AppError::Forbidden("API key is not scoped to this tree".to_string()));
+    }
+    Ok(next.run(request).await)
+}
+
+/// The map id in a `/api/v1/maps/{map_id}/...` path
+fn map_id(path: &str) -> Option<usize> {
+    path.strip_prefix("/api/v1/maps/")?.split('/').next()?.parse().ok()
+}
+
+fn is_read_only(request: &Request) -> bool {
+    let path = request.uri().path();
+    if path == "/health" {
+        return true;
+    }
+    if path.starts_with("/api/v1/admin/") {
+        return false;
+    }
+    matches!(*request.method(), Method::GET | Method::HEAD) || READ_ONLY_POSTS.contains(&path)
+}
+
+/// Response extension marking a body that is a stored value, never API JSON
+#[derive(Debug, Clone, Copy)]
+pub struct RawBody;
+
+/// Wrap successful JSON responses in an `Envelope` when enabled in config
+pub async fn envelope(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if !state.config.envelope
+        || !response.status().is_success()
+        || !is_json(&response)
+        || response.extensions().get::<RawBody>().is_some()
+    {
+        return response;
+    }
+
+    match wrap(&state, response).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+async fn wrap(state: &AppState, response: Response) -> Result<Response, AppError> {
+    let (mut parts, body) = response.into_parts();
+    let bytes = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to read response body: {}", e)))?;
+    let data: serde_json::Value = serde_json::from_slice(&bytes)
+        .map_err(|e| AppError::Internal(format!("Response body is not JSON: {}", e)))?;
+
+    let root = state.storage().get_root_hash().await?;
+    let envelope = Envelope {
+        data,
+        meta: EnvelopeMeta {
+            request_id: state.next_request_id(),
+            root: hex::encode(root),
+        },
+    };
+
+    // The body changes size, so let it be recomputed
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let body = Json(envelope).into_response().into_body();
+    Ok(Response::from_parts(parts, body))
+}
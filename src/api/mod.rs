@@ -2,4 +2,8 @@ pub mod route;
 
 pub mod state;
 
-pub mod handlers;
\ No newline at end of file
+pub mod handlers;
+
+pub mod middleware;
+
+pub mod extract;
\ No newline at end of file
@@ -1,15 +1,25 @@
-use crate::{api::{handlers, state::AppState}, dto::response::HealthResponse};
+use crate::{
+    api::{handlers, middleware, state::{AppState, SecurityConfig}},
+    dto::response::HealthResponse,
+    error::AppError,
+};
 use axum::{
-    Json, Router, extract::State, routing::{get, post}
+    Json, Router, error_handling::HandleErrorLayer, extract::State, middleware::from_fn_with_state,
+    routing::{delete, get, post, put}
+};
+use tower::{
+    limit::GlobalConcurrencyLimitLayer, load_shed::error::Overloaded, timeout::error::Elapsed,
+    BoxError, ServiceBuilder,
 };
 // use tower_http::trace::TraceLayer;
 
 /// Create the application router with all routes
 pub fn create_router(state: AppState) -> Router {
-    Router::new()
+    let security = state.config.security.clone();
+    let router = Router::new()
         // Health check
         .route("/health", get(|State(state): State<AppState>| async move {
-            let commitment_count = state.storage.commitment_count().await.unwrap_or(0);
+            let commitment_count = state.storage().commitment_count().await.unwrap_or(0);
             Json(HealthResponse {
                 status: "healthy".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -20,16 +30,471 @@ pub fn create_router(state: AppState) -> Router {
         // Commitment endpoints
         .route("/api/v1/commitments", post(handlers::commitment::add_commitment))
         .route("/api/v1/commitments", get(handlers::commitment::get_all_commitments))
+        .route("/api/v1/commitments/page", get(handlers::commitment::get_commitments_page))
+        .route("/api/v1/commitments/stream", post(handlers::commitment::stream_commitment))
+        .route("/api/v1/commitments/batch", post(handlers::commitment::add_commitments_batch))
+        .route("/api/v1/commitments/block", post(handlers::commitment::commit_block))
         .route("/api/v1/commitments/{index}", get(handlers::proof::get_commitment))
+        .route("/api/v1/commitments/{index}/raw", get(handlers::proof::get_commitment_raw))
+        .route("/api/v1/commitments/{index}/leaf-hash", get(handlers::proof::get_leaf_hash))
+        .route("/api/v1/commitments/{index}/node-path", get(handlers::proof::get_node_path))
+        .route("/api/v1/commitments/by-hash/{hash}", get(handlers::proof::get_commitment_by_hash))
+        .route("/api/v1/commitments/by-root/{root}", get(handlers::proof::get_commitment_by_root))
+        .route("/api/v1/commitments/{index}", put(handlers::commitment::insert_commitment))
+        .route("/api/v1/commitments/{index}", delete(handlers::commitment::delete_commitment))
 
         // Proof endpoints
+        .route("/api/v1/proof/all", get(handlers::proof::export_all_proofs))
+        .route("/api/v1/proof/range", get(handlers::proof::get_range_proof))
+        .route("/api/v1/proof/batch", post(handlers::proof::get_batch_proofs))
         .route("/api/v1/proof/{index}", get(handlers::proof::get_proof))
+        .route("/api/v1/proof/{index}/extend", get(handlers::proof::extend_proof))
+        .route("/api/v1/proof/{index}/ct", get(handlers::proof::get_ct_proof))
+        .route("/api/v1/proof/{index}/jsonld", get(handlers::proof::get_jsonld_proof))
         .route("/api/v1/proof/verify", post(handlers::proof::verify_proof))
+        .route("/api/v1/proof/verify/trusted", post(handlers::proof::verify_trusted_proof))
+        .route("/api/v1/proof/ct/verify", post(handlers::proof::verify_ct_proof))
 
         // Root endpoint
         .route("/api/v1/root", get(handlers::commitment::get_root))
+        .route("/api/v1/consistency", get(handlers::proof::get_consistency_proof))
+        .route("/api/v1/commit", post(handlers::reveal::commit_sealed))
+        .route("/api/v1/reveal/{index}", post(handlers::reveal::reveal))
+        .route("/api/v1/maps", post(handlers::map::commit_map))
+        .route("/api/v1/maps/{map_id}/proof", get(handlers::map::get_key_proof))
+        .route("/api/v1/root/cover", get(handlers::commitment::get_root_cover))
+        .route("/api/v1/checkpoints", get(handlers::commitment::get_checkpoints))
+        .route("/api/v1/tree/ascii", get(handlers::commitment::get_tree_ascii))
+        .route("/api/v1/tree/sibling-fanout", get(handlers::commitment::get_sibling_fanout))
+        .route("/api/v1/tree/node/{level_order_index}", get(handlers::commitment::get_tree_node))
+
+        // Replication endpoints
+        .route("/api/v1/replication/commitments", get(handlers::replication::get_commitments_after))
+        .route("/api/v1/replication/checkpoint", get(handlers::replication::get_checkpoint))
+
+        // Admin endpoints
+        .route("/api/v1/admin/integrity", get(handlers::admin::verify_integrity))
+        .route("/api/v1/admin/verify-chain", get(handlers::admin::verify_chain))
+        .route("/api/v1/admin/audit", get(handlers::admin::get_audit_log))
+        .route("/api/v1/admin/migrate", post(handlers::admin::migrate_storage))
+
+        // Stats endpoint
+        .route("/api/v1/stats", get(handlers::stats::get_stats))
+
+        // Optional response envelope
+        .layer(from_fn_with_state(state.clone(), middleware::envelope))
+
+        // JWT bearer auth on writes and admin routes, when configured
+        .layer(from_fn_with_state(state.clone(), middleware::require_auth))
+
+        // Scoped API keys, when configured
+        .layer(from_fn_with_state(state.clone(), middleware::require_api_key))
 
         // Add tracing middleware
         // .layer(TraceLayer::new_for_http())
-        .with_state(state)
-}
\ No newline at end of file
+        .with_state(state);
+
+    let router = security_layers(router, &security);
+
+    // Link request spans to the caller's trace
+    #[cfg(feature = "otel")]
+    let router = router.layer(axum::middleware::from_fn(crate::telemetry::propagate_context));
+
+    router
+}
+
+/// Wrap `router` in the hardening layers `config` enables: a body size limit, a
+/// request timeout and a cap on requests in flight, outermost last
+pub fn security_layers(mut router: Router, config: &SecurityConfig) -> Router {
+    if let Some(max_size) = config.max_body_size {
+        router = router.layer(from_fn_with_state(max_size, middleware::limit_body));
+    }
+    if let Some(timeout) = config.request_timeout {
+        router = router.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(security_rejection))
+                .timeout(timeout),
+        );
+    }
+    if let Some(max_requests) = config.max_concurrent_requests {
+        // Shared across routes, so the cap is on the whole server rather than per route
+        router = router.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(security_rejection))
+                .load_shed()
+                .layer(GlobalConcurrencyLimitLayer::new(max_requests)),
+        );
+    }
+    router
+}
+
+/// Map a rejection from the timeout or load-shedding layers to its `AppError`
+async fn security_rejection(error: BoxError) -> AppError {
+    if error.is::<Elapsed>() {
+        AppError::Timeout("Request took too long".to_string())
+    } else if error.is::<Overloaded>() {
+        AppError::Overloaded("Too many requests in flight".to_string())
+    } else {
+        AppError::Internal(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::middleware::Claims;
+    use crate::api::state::{ApiConfig, ApiKeys, AuthConfig};
+    use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+    use axum::{
+        body::{to_bytes, Body},
+        http::{Request, StatusCode},
+    };
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tower::ServiceExt;
+
+    async fn get_json(state: AppState, uri: &str) -> (StatusCode, Value) {
+        let response = create_router(state)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    async fn state_with_commitment(config: ApiConfig) -> AppState {
+        let state = AppState::with_config(Arc::new(MemoryStorage::new()), config);
+        state.storage().add_commitment(b"data".to_vec()).await.unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn test_commitment_bare_by_default() {
+        let state = state_with_commitment(ApiConfig::default()).await;
+
+        let (status, body) = get_json(state, "/api/v1/commitments/0").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["value"], serde_json::json!(b"data"));
+        assert!(body.get("data").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_commitment_enveloped() {
+        let state = state_with_commitment(ApiConfig { envelope: true, ..Default::default() }).await;
+        let root = state.storage().get_root_hash().await.unwrap();
+
+        let (status, body) = get_json(state, "/api/v1/commitments/0").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["value"], serde_json::json!(b"data"));
+        assert_eq!(body["meta"]["root"], hex::encode(root));
+        assert!(body["meta"]["request_id"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_raw_value_served_with_content_type() {
+        let state = AppState::with_config(
+            Arc::new(MemoryStorage::new()),
+            ApiConfig { envelope: true, ..Default::default() },
+        );
+        let value = br#"{"hello":"world"}"#;
+        let body = serde_json::json!({ "value": value, "content_type": "application/json" });
+        let response = create_router(state.clone())
+            .oneshot(
+                Request::post("/api/v1/commitments")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = create_router(state.clone())
+            .oneshot(Request::get("/api/v1/commitments/0/raw").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], value);
+
+        // The content type is metadata only; the leaf hash covers the value alone
+        let expected = crate::models::merkle::MerkleNode::new_leaf(value).hash;
+        assert_eq!(state.storage().get_root_hash().await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_add_commitment_created_with_location() {
+        let state = state_with_commitment(ApiConfig::default()).await;
+        let body = serde_json::json!({ "value": b"second" });
+        let response = create_router(state)
+            .oneshot(
+                Request::post("/api/v1/commitments")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["location"], "/api/v1/commitments/1");
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["index"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors_not_enveloped() {
+        let state = state_with_commitment(ApiConfig { envelope: true, ..Default::default() }).await;
+
+        let (status, body) = get_json(state, "/api/v1/commitments/9").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "NOT_FOUND");
+    }
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn token(expires_in: i64) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let claims = Claims {
+            sub: Some("writer".to_string()),
+            exp: (now + expires_in) as u64,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    async fn post_commitment(state: AppState, bearer: Option<String>) -> StatusCode {
+        let mut request = Request::post("/api/v1/commitments").header("content-type", "application/json");
+        if let Some(token) = bearer {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        let body = Body::from(serde_json::json!({ "value": b"data" }).to_string());
+        create_router(state).oneshot(request.body(body).unwrap()).await.unwrap().status()
+    }
+
+    fn auth_state() -> AppState {
+        let config = ApiConfig {
+            auth: Some(AuthConfig::hs256(SECRET)),
+            ..Default::default()
+        };
+        AppState::with_config(Arc::new(MemoryStorage::new()), config)
+    }
+
+    #[tokio::test]
+    async fn test_auth_valid_token() {
+        let state = auth_state();
+        assert_eq!(post_commitment(state.clone(), Some(token(3600))).await, StatusCode::CREATED);
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 1);
+
+        // Reads stay public
+        let (status, _) = get_json(state, "/api/v1/commitments/0").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_auth_missing_token() {
+        let state = auth_state();
+        assert_eq!(post_commitment(state.clone(), None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 0);
+
+        let (status, body) = get_json(state, "/api/v1/admin/integrity").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn test_auth_expired_token() {
+        let state = auth_state();
+        assert_eq!(post_commitment(state.clone(), Some(token(-3600))).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_proof_etag_revalidation() {
+        let state = state_with_commitment(ApiConfig::default()).await;
+        let fetch = |etag: Option<String>| {
+            let mut request = Request::get("/api/v1/proof/0");
+            if let Some(etag) = etag {
+                request = request.header("if-none-match", etag);
+            }
+            create_router(state.clone()).oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = fetch(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+        // Same root: not modified, with no body
+        let response = fetch(Some(etag.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        assert!(to_bytes(response.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        // A new commitment changes the root, so the cached proof is stale
+        state.storage().add_commitment(b"more".to_vec()).await.unwrap();
+        let response = fetch(Some(etag.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_requires_token() {
+        let state = auth_state();
+        assert_eq!(post_commitment(state.clone(), Some(token(3600))).await, StatusCode::CREATED);
+
+        let (status, _) = get_json(state.clone(), "/api/v1/admin/audit").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let request = Request::get("/api/v1/admin/audit")
+            .header("authorization", format!("Bearer {}", token(3600)))
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body[0]["op"], "add");
+        assert_eq!(body[0]["index"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_requires_token() {
+        let state = auth_state();
+        assert_eq!(post_commitment(state.clone(), Some(token(3600))).await, StatusCode::CREATED);
+        let migrate = |bearer: Option<String>| {
+            let mut request = Request::post("/api/v1/admin/migrate").header("content-type", "application/json");
+            if let Some(token) = bearer {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            let body = Body::from(serde_json::json!({ "backend": "memory" }).to_string());
+            create_router(state.clone()).oneshot(request.body(body).unwrap())
+        };
+        let before = state.storage();
+
+        assert_eq!(migrate(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert!(Arc::ptr_eq(&before, &state.storage()));
+
+        assert_eq!(migrate(Some(token(3600))).await.unwrap().status(), StatusCode::OK);
+        assert!(!Arc::ptr_eq(&before, &state.storage()));
+        assert_eq!(state.storage().commitment_count().await.unwrap(), 1);
+    }
+
+    /// A router whose appends wait out a batch window, so each write is slow
+    fn slow_router(security: SecurityConfig) -> Router {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            batch_window: Some(Duration::from_millis(300)),
+            ..Default::default()
+        });
+        let config = ApiConfig {
+            security,
+            ..Default::default()
+        };
+        create_router(AppState::with_config(Arc::new(storage), config))
+    }
+
+    fn commit_request(value: &[u8]) -> Request<Body> {
+        let body = serde_json::json!({ "value": value }).to_string();
+        Request::post("/api/v1/commitments")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_security_layers_reject_slow_large_and_excess_requests() {
+        let router = slow_router(SecurityConfig {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let response = router.oneshot(commit_request(b"slow")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let router = slow_router(SecurityConfig {
+            max_body_size: Some(64),
+            ..Default::default()
+        });
+        let mut request = commit_request(&[7u8; 64]);
+        let len = axum::body::HttpBody::size_hint(request.body()).exact().unwrap();
+        request.headers_mut().insert("content-length", len.into());
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "PAYLOAD_TOO_LARGE");
+        // Without a declared length the body is cut off as it's read
+        let response = router.oneshot(commit_request(&[7u8; 64])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // The second request arrives while the first holds the only slot
+        let router = slow_router(SecurityConfig {
+            max_concurrent_requests: Some(1),
+            ..Default::default()
+        });
+        let first = tokio::spawn(router.clone().oneshot(commit_request(b"first")));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let second = router.clone().oneshot(commit_request(b"second")).await.unwrap();
+        assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::CREATED);
+        let third = router.oneshot(commit_request(b"third")).await.unwrap();
+        assert_eq!(third.status(), StatusCode::CREATED);
+    }
+
+    fn keyed_state() -> AppState {
+        let config = ApiConfig {
+            api_keys: ApiKeys::parse("reader=read;writer=write;tenant=write:0").unwrap(),
+            ..Default::default()
+        };
+        AppState::with_config(Arc::new(MemoryStorage::new()), config)
+    }
+
+    async fn send_with_key(
+        state: &AppState,
+        request: axum::http::request::Builder,
+        key: Option<&str>,
+        body: Value,
+    ) -> StatusCode {
+        let request = match key {
+            Some(key) => request.header(middleware::API_KEY_HEADER, key),
+            None => request,
+        };
+        let request = request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        create_router(state.clone()).oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_api_key_scopes() {
+        let state = keyed_state();
+        let commit = serde_json::json!({ "value": b"data" });
+        let commit_request = || Request::post("/api/v1/commitments");
+
+        assert_eq!(send_with_key(&state, commit_request(), None, commit.clone()).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send_with_key(&state, commit_request(), Some("bogus"), commit.clone()).await, StatusCode::UNAUTHORIZED);
+        // A read-only key can read but not write
+        assert_eq!(send_with_key(&state, commit_request(), Some("reader"), commit.clone()).await, StatusCode::FORBIDDEN);
+        assert_eq!(send_with_key(&state, commit_request(), Some("writer"), commit.clone()).await, StatusCode::CREATED);
+        let read = || Request::get("/api/v1/commitments/0");
+        assert_eq!(send_with_key(&state, read(), Some("reader"), Value::Null).await, StatusCode::OK);
+
+        // Two maps, 0 and 1; the tenant key only reaches map 0
+        let entries = serde_json::json!({ "entries": [{ "key": b"k", "value": b"v" }] });
+        for _ in 0..2 {
+            let status = send_with_key(&state, Request::post("/api/v1/maps"), Some("writer"), entries.clone()).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+        let prove = |map_id: usize| Request::get(format!("/api/v1/maps/{}/proof?key=6b", map_id));
+        assert_eq!(send_with_key(&state, prove(0), Some("tenant"), Value::Null).await, StatusCode::OK);
+        assert_eq!(send_with_key(&state, prove(1), Some("tenant"), Value::Null).await, StatusCode::FORBIDDEN);
+        assert_eq!(send_with_key(&state, read(), Some("tenant"), Value::Null).await, StatusCode::FORBIDDEN);
+        assert_eq!(send_with_key(&state, prove(1), Some("reader"), Value::Null).await, StatusCode::OK);
+    }
+}
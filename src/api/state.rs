@@ -1,14 +1,468 @@
+use crate::crypto::proof::MAX_PROOF_PATH_LEN;
+use crate::dto::request::{ValidationContext, DEFAULT_MAX_VALUE_SIZE};
+use crate::dto::response::JsonNaming;
+use crate::error::AppError;
+use crate::models::keyed_map::KeyedMap;
 use crate::storage::traits::CommitmentStorage;
-use std::sync::Arc;
+use arc_swap::ArcSwap;
+use jsonwebtoken::{Algorithm, DecodingKey};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Default cap on indices in one batch proof request
+pub const DEFAULT_MAX_BATCH_PROOFS: usize = 1000;
+
+/// API behaviour configuration
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// Wrap successful responses in a `{ data, meta }` envelope
+    pub envelope: bool,
+    /// Longest proof path accepted by the verify endpoint
+    pub max_proof_path_len: usize,
+    /// Most indices accepted by one batch proof request
+    pub max_batch_proofs: usize,
+    /// Exact length every committed value must have, for fixed-size record logs
+    pub record_size: Option<usize>,
+    /// Largest value accepted from unauthenticated callers
+    pub max_value_size: usize,
+    /// Largest value accepted from callers with a valid token; None applies
+    /// `max_value_size` to everyone
+    pub authenticated_max_value_size: Option<usize>,
+    /// JWT bearer authentication; None leaves every endpoint open
+    pub auth: Option<AuthConfig>,
+    /// Request timeout, body size and concurrency limits
+    pub security: SecurityConfig,
+    /// Scoped API keys; when any are set, every request but `/health` needs one
+    pub api_keys: ApiKeys,
+    /// Answer root reads from the last published root instead of waiting on
+    /// in-flight writes, reporting how far behind it is in `X-Root-Staleness`
+    pub serve_stale_root: bool,
+    /// Field naming of responses that offer a camelCase form, when the request
+    /// doesn't pick one with `?naming=`
+    pub json_naming: JsonNaming,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            envelope: false,
+            max_proof_path_len: MAX_PROOF_PATH_LEN,
+            max_batch_proofs: DEFAULT_MAX_BATCH_PROOFS,
+            record_size: None,
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            authenticated_max_value_size: None,
+            auth: None,
+            security: SecurityConfig::default(),
+            api_keys: ApiKeys::default(),
+            serve_stale_root: false,
+            json_naming: JsonNaming::default(),
+        }
+    }
+}
+
+impl ApiConfig {
+    /// Read configuration from `MERKLE_*` environment variables
+    pub fn from_env() -> Result<Self, AppError> {
+        Ok(Self {
+            envelope: env_flag("MERKLE_RESPONSE_ENVELOPE"),
+            max_proof_path_len: std::env::var("MERKLE_MAX_PROOF_PATH_LEN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(MAX_PROOF_PATH_LEN),
+            max_batch_proofs: std::env::var("MERKLE_MAX_BATCH_PROOFS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_BATCH_PROOFS),
+            record_size: std::env::var("MERKLE_RECORD_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            max_value_size: std::env::var("MERKLE_MAX_VALUE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_VALUE_SIZE),
+            authenticated_max_value_size: std::env::var("MERKLE_AUTH_MAX_VALUE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            auth: AuthConfig::from_env()?,
+            security: SecurityConfig::from_env(),
+            api_keys: match std::env::var("MERKLE_API_KEYS") {
+                Ok(spec) => ApiKeys::parse(&spec)?,
+                Err(_) => ApiKeys::default(),
+            },
+            serve_stale_root: env_flag("MERKLE_SERVE_STALE_ROOT"),
+            json_naming: match std::env::var("MERKLE_JSON_NAMING") {
+                Ok(naming) => naming.parse()?,
+                Err(_) => JsonNaming::default(),
+            },
+        })
+    }
+
+    /// Limits for a commitment request, tiered on whether the caller authenticated
+    pub fn validation_context(&self, authenticated: bool) -> ValidationContext {
+        let max_value_size = match self.authenticated_max_value_size {
+            Some(size) if authenticated => size,
+            _ => self.max_value_size,
+        };
+        ValidationContext {
+            max_value_size,
+            record_size: self.record_size,
+        }
+    }
+}
+
+/// Limits applied to every request ahead of routing; each is off when None
+#[derive(Debug, Clone, Default)]
+pub struct SecurityConfig {
+    /// Fail requests still running after this long with 408
+    pub request_timeout: Option<Duration>,
+    /// Fail request bodies larger than this with 413
+    pub max_body_size: Option<usize>,
+    /// Shed requests beyond this many in flight with 503 rather than queueing them
+    pub max_concurrent_requests: Option<usize>,
+}
+
+impl SecurityConfig {
+    /// Read `MERKLE_REQUEST_TIMEOUT_MS`, `MERKLE_MAX_BODY_SIZE` and
+    /// `MERKLE_MAX_CONCURRENT_REQUESTS`; unset, unparsable or zero values are off
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+        };
+        Self {
+            request_timeout: var("MERKLE_REQUEST_TIMEOUT_MS").map(|ms| Duration::from_millis(ms as u64)),
+            max_body_size: var("MERKLE_MAX_BODY_SIZE"),
+            max_concurrent_requests: var("MERKLE_MAX_CONCURRENT_REQUESTS"),
+        }
+    }
+}
+
+/// Key and scope for JWT bearer authentication
+#[derive(Clone)]
+pub struct AuthConfig {
+    /// Signing algorithm tokens must use
+    pub algorithm: Algorithm,
+    /// Key tokens are verified against
+    pub key: DecodingKey,
+    /// Also require a token on read-only endpoints
+    pub protect_reads: bool,
+}
+
+impl AuthConfig {
+    /// Verify HS256 tokens signed with a shared secret
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self {
+            algorithm: Algorithm::HS256,
+            key: DecodingKey::from_secret(secret),
+            protect_reads: false,
+        }
+    }
+
+    /// Verify RS256 tokens against a PEM-encoded RSA public key
+    pub fn rs256(public_key_pem: &[u8]) -> Result<Self, AppError> {
+        let key = DecodingKey::from_rsa_pem(public_key_pem)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid RSA public key: {}", e)))?;
+        Ok(Self {
+            algorithm: Algorithm::RS256,
+            key,
+            protect_reads: false,
+        })
+    }
+
+    /// Read `MERKLE_JWT_SECRET` or `MERKLE_JWT_PUBLIC_KEY` (PEM), plus
+    /// `MERKLE_AUTH_PROTECT_READS`; None when neither key is set
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        let config = if let Ok(secret) = std::env::var("MERKLE_JWT_SECRET") {
+            Self::hs256(secret.as_bytes())
+        } else if let Ok(pem) = std::env::var("MERKLE_JWT_PUBLIC_KEY") {
+            Self::rs256(pem.as_bytes())?
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            protect_reads: env_flag("MERKLE_AUTH_PROTECT_READS"),
+            ..config
+        }))
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep key material out of logs
+        f.debug_struct("AuthConfig")
+            .field("algorithm", &self.algorithm)
+            .field("protect_reads", &self.protect_reads)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What an API key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// The operations and trees an API key reaches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyScope {
+    pub access: KeyAccess,
+    /// Map ids the key is confined to; None reaches every tree, including the
+    /// commitment log
+    pub trees: Option<Vec<usize>>,
+}
+
+/// API keys and their scopes, sent in the `x-api-key` header
+#[derive(Clone, Default)]
+pub struct ApiKeys(HashMap<String, ApiKeyScope>);
+
+impl ApiKeys {
+    /// Parse `key=access[:tree,tree...]` entries separated by `;`, with access
+    /// `read` or `write`, e.g. `ops=write;audit=read;tenant=write:3,4`
+    pub fn parse(spec: &str) -> Result<Self, AppError> {
+        let invalid = |entry: &str| AppError::InvalidInput(format!("Invalid API key entry '{}'", entry));
+        let mut keys = Self::default();
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, scope) = entry.split_once('=').ok_or_else(|| invalid(entry))?;
+            let (access, trees) = match scope.split_once(':') {
+                Some((access, trees)) => (access, Some(trees)),
+                None => (scope, None),
+            };
+            let access = match access {
+                "read" => KeyAccess::ReadOnly,
+                "write" => KeyAccess::ReadWrite,
+                _ => return Err(invalid(entry)),
+            };
+            let trees = trees
+                .map(|trees| trees.split(',').map(|id| id.trim().parse()).collect::<Result<Vec<_>, _>>())
+                .transpose()
+                .map_err(|_| invalid(entry))?;
+            keys.insert(key.trim(), ApiKeyScope { access, trees });
+        }
+        Ok(keys)
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, scope: ApiKeyScope) {
+        self.0.insert(key.into(), scope);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ApiKeyScope> {
+        self.0.get(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep the keys themselves out of logs
+        f.debug_list().entries(self.0.values()).finish()
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
 
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
-    pub storage: Arc<dyn CommitmentStorage>,
+    /// Swappable at runtime, see `storage` and `swap_storage`
+    storage: Arc<ArcSwap<Arc<dyn CommitmentStorage>>>,
+    pub config: ApiConfig,
+    /// When the server state was created, for uptime reporting
+    pub started_at: Instant,
+    request_counter: Arc<AtomicU64>,
+    /// Committed key→value maps, by id
+    maps: Arc<RwLock<Vec<Arc<KeyedMap>>>>,
 }
 
 impl AppState {
     pub fn new(storage: Arc<dyn CommitmentStorage>) -> Self {
-        Self { storage }
+        Self::with_config(storage, ApiConfig::default())
+    }
+
+    /// Start building state over `storage`, with every option at its default
+    pub fn builder(storage: Arc<dyn CommitmentStorage>) -> AppStateBuilder {
+        AppStateBuilder {
+            storage,
+            config: ApiConfig::default(),
+        }
+    }
+
+    pub fn with_config(storage: Arc<dyn CommitmentStorage>, config: ApiConfig) -> Self {
+        Self {
+            storage: Arc::new(ArcSwap::from_pointee(storage)),
+            config,
+            started_at: Instant::now(),
+            request_counter: Arc::new(AtomicU64::new(0)),
+            maps: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// The backend currently serving requests
+    pub fn storage(&self) -> Arc<dyn CommitmentStorage> {
+        Arc::clone(&self.storage.load())
+    }
+
+    /// Atomically point every clone of this state at `storage`, returning the old
+    /// backend. Requests already holding the old backend finish against it.
+    pub fn swap_storage(&self, storage: Arc<dyn CommitmentStorage>) -> Arc<dyn CommitmentStorage> {
+        Arc::unwrap_or_clone(self.storage.swap(Arc::new(storage)))
+    }
+
+    /// Keep a committed map, returning its id
+    pub fn add_map(&self, map: KeyedMap) -> usize {
+        let mut maps = self.maps.write().unwrap_or_else(|e| e.into_inner());
+        maps.push(Arc::new(map));
+        maps.len() - 1
+    }
+
+    /// Get a committed map by id
+    pub fn map(&self, id: usize) -> Result<Arc<KeyedMap>, AppError> {
+        let maps = self.maps.read().unwrap_or_else(|e| e.into_inner());
+        maps.get(id)
+            .cloned()
+            .ok_or(AppError::NotFound(format!("Map {} not found", id)))
+    }
+
+    /// Allocate a new id for an incoming request
+    pub fn next_request_id(&self) -> String {
+        let id = self.request_counter.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("req-{}", id)
+    }
+}
+
+/// Fluent construction of `AppState`; unset options keep their defaults
+pub struct AppStateBuilder {
+    storage: Arc<dyn CommitmentStorage>,
+    config: ApiConfig,
+}
+
+impl AppStateBuilder {
+    /// Replace the whole configuration, e.g. with `ApiConfig::from_env()`
+    pub fn config(mut self, config: ApiConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Wrap successful responses in a `{ data, meta }` envelope
+    pub fn envelope(mut self, envelope: bool) -> Self {
+        self.config.envelope = envelope;
+        self
+    }
+
+    /// Longest proof path accepted by the verify endpoint
+    pub fn max_proof_path_len(mut self, max_proof_path_len: usize) -> Self {
+        self.config.max_proof_path_len = max_proof_path_len;
+        self
+    }
+
+    /// Most indices accepted by one batch proof request
+    pub fn max_batch_proofs(mut self, max_batch_proofs: usize) -> Self {
+        self.config.max_batch_proofs = max_batch_proofs;
+        self
+    }
+
+    /// Require every committed value to be exactly `record_size` bytes
+    pub fn record_size(mut self, record_size: usize) -> Self {
+        self.config.record_size = Some(record_size);
+        self
+    }
+
+    /// Largest value accepted from unauthenticated callers
+    pub fn max_value_size(mut self, max_value_size: usize) -> Self {
+        self.config.max_value_size = max_value_size;
+        self
+    }
+
+    /// Largest value accepted from callers with a valid token
+    pub fn authenticated_max_value_size(mut self, max_value_size: usize) -> Self {
+        self.config.authenticated_max_value_size = Some(max_value_size);
+        self
+    }
+
+    /// Require JWT bearer tokens
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.config.auth = Some(auth);
+        self
+    }
+
+    /// Request timeout, body size and concurrency limits
+    pub fn security(mut self, security: SecurityConfig) -> Self {
+        self.config.security = security;
+        self
+    }
+
+    /// Serve the last published root without waiting on in-flight writes
+    pub fn serve_stale_root(mut self, serve_stale_root: bool) -> Self {
+        self.config.serve_stale_root = serve_stale_root;
+        self
+    }
+
+    /// Default field naming of responses that offer a camelCase form
+    pub fn json_naming(mut self, json_naming: JsonNaming) -> Self {
+        self.config.json_naming = json_naming;
+        self
     }
-}
\ No newline at end of file
+
+    pub fn build(self) -> AppState {
+        AppState::with_config(self.storage, self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[test]
+    fn test_builder_sets_options() {
+        let state = AppState::builder(Arc::new(MemoryStorage::new()))
+            .envelope(true)
+            .max_proof_path_len(16)
+            .max_batch_proofs(10)
+            .record_size(64)
+            .auth(AuthConfig::hs256(b"secret"))
+            .build();
+
+        assert!(state.config.envelope);
+        assert_eq!(state.config.max_proof_path_len, 16);
+        assert_eq!(state.config.max_batch_proofs, 10);
+        assert_eq!(state.config.record_size, Some(64));
+        let auth = state.config.auth.unwrap();
+        assert_eq!(auth.algorithm, Algorithm::HS256);
+        assert!(!auth.protect_reads);
+
+        // Unset options keep their defaults
+        let state = AppState::builder(Arc::new(MemoryStorage::new())).build();
+        assert!(!state.config.envelope);
+        assert_eq!(state.config.max_proof_path_len, MAX_PROOF_PATH_LEN);
+        assert_eq!(state.config.max_batch_proofs, DEFAULT_MAX_BATCH_PROOFS);
+        assert!(state.config.auth.is_none());
+    }
+
+    #[test]
+    fn test_parse_api_keys() {
+        let keys = ApiKeys::parse("ops=write; audit=read;tenant=write:3,4").unwrap();
+        assert_eq!(
+            keys.get("ops"),
+            Some(&ApiKeyScope { access: KeyAccess::ReadWrite, trees: None })
+        );
+        assert_eq!(keys.get("audit").unwrap().access, KeyAccess::ReadOnly);
+        assert_eq!(keys.get("tenant").unwrap().trees, Some(vec![3, 4]));
+        assert!(!format!("{:?}", keys).contains("tenant"));
+
+        for spec in ["ops", "ops=admin", "ops=write:x"] {
+            assert!(matches!(ApiKeys::parse(spec), Err(AppError::InvalidInput(_))), "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! RFC 6962 (Certificate Transparency) Merkle tree hashing and inclusion proofs.
+//! Leaves and interior nodes are domain-separated with 0x00 / 0x01 prefixes, and
+//! an unbalanced tree splits at the largest power of two instead of duplicating.
+
+use sha2::{Digest, Sha256};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hash of a leaf: SHA-256(0x00 || data)
+pub fn leaf_hash(data: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().to_vec()
+}
+
+/// Hash of an interior node: SHA-256(0x01 || left || right)
+pub fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+/// Largest power of two strictly less than `n` (n > 1)
+fn split_point(n: usize) -> usize {
+    // The highest set bit of n - 1
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// Merkle Tree Hash over the given leaf hashes; SHA-256 of the empty string when empty
+pub fn root_hash(leaf_hashes: &[Vec<u8>]) -> Vec<u8> {
+    match leaf_hashes {
+        [] => Sha256::digest(b"").to_vec(),
+        [leaf] => leaf.clone(),
+        _ => {
+            let k = split_point(leaf_hashes.len());
+            node_hash(&root_hash(&leaf_hashes[..k]), &root_hash(&leaf_hashes[k..]))
+        }
+    }
+}
+
+/// Audit path for the leaf at `index`, ordered from the leaf up to the root
+pub fn inclusion_proof(leaf_hashes: &[Vec<u8>], index: usize) -> Vec<Vec<u8>> {
+    if leaf_hashes.len() <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(leaf_hashes.len());
+    let (left, right) = leaf_hashes.split_at(k);
+    if index < k {
+        let mut path = inclusion_proof(left, index);
+        path.push(root_hash(right));
+        path
+    } else {
+        let mut path = inclusion_proof(right, index - k);
+        path.push(root_hash(left));
+        path
+    }
+}
+
+/// Verify an audit path for `leaf_hash` at `index` in a tree of `tree_size` leaves
+/// (RFC 9162 section 2.1.3.2)
+pub fn verify_inclusion(
+    leaf_hash: &[u8],
+    index: usize,
+    tree_size: usize,
+    audit_path: &[Vec<u8>],
+    root: &[u8],
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    let (mut fn_, mut sn) = (index, tree_size - 1);
+    let mut r = leaf_hash.to_vec();
+    for p in audit_path {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            if fn_ & 1 == 0 {
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    sn == 0 && r == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Leaf inputs from the RFC 6962 reference test vectors
+    fn test_leaves() -> Vec<Vec<u8>> {
+        vec![
+            vec![],
+            vec![0x00],
+            vec![0x10],
+            vec![0x20, 0x21],
+            vec![0x30, 0x31],
+            vec![0x40, 0x41, 0x42, 0x43],
+            (0x50..0x58).collect(),
+            (0x60..0x70).collect(),
+        ]
+    }
+
+    #[test]
+    fn test_split_point() {
+        let expected = [(2, 1), (3, 2), (4, 2), (5, 4), (7, 4), (8, 4), (9, 8), (16, 8), (17, 16)];
+        for (n, k) in expected {
+            assert_eq!(split_point(n), k, "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_known_roots() {
+        let hashes: Vec<Vec<u8>> = test_leaves().iter().map(|l| leaf_hash(l)).collect();
+        assert_eq!(
+            hex::encode(root_hash(&hashes[..1])),
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d"
+        );
+        assert_eq!(
+            hex::encode(root_hash(&hashes)),
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328"
+        );
+    }
+
+    #[test]
+    fn test_known_audit_path() {
+        let hashes: Vec<Vec<u8>> = test_leaves().iter().map(|l| leaf_hash(l)).collect();
+        let path: Vec<String> = inclusion_proof(&hashes, 0).iter().map(hex::encode).collect();
+        assert_eq!(
+            path,
+            vec![
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inclusion_proofs_verify_for_all_sizes() {
+        let hashes: Vec<Vec<u8>> = test_leaves().iter().map(|l| leaf_hash(l)).collect();
+        for size in 1..=hashes.len() {
+            let root = root_hash(&hashes[..size]);
+            for index in 0..size {
+                let path = inclusion_proof(&hashes[..size], index);
+                assert!(verify_inclusion(&hashes[index], index, size, &path, &root));
+
+                let other = (index + 1) % size;
+                if other != index {
+                    assert!(!verify_inclusion(&hashes[other], index, size, &path, &root));
+                }
+                assert!(!verify_inclusion(&hashes[index], size, size, &path, &root));
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Prefix-free encoding for leaves built from several fields: each field is written
+//! as its length (an unsigned LEB128 varint) followed by its bytes, so no two field
+//! lists encode to the same bytes and boundaries can't shift between fields
+
+use crate::crypto::hash::sha256;
+
+/// Append `n` as an unsigned LEB128 varint
+pub fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Concatenate `fields`, each prefixed with its varint length
+pub fn encode_fields(fields: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(fields.iter().map(|f| f.len() + 10).sum());
+    for field in fields {
+        write_varint(&mut out, field.len() as u64);
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+/// SHA-256 of the prefix-free encoding of `fields`
+pub fn hash_fields(fields: &[&[u8]]) -> Vec<u8> {
+    sha256(&[&encode_fields(fields)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_encoding() {
+        let varint = |n| {
+            let mut out = Vec::new();
+            write_varint(&mut out, n);
+            out
+        };
+        assert_eq!(varint(0), vec![0x00]);
+        assert_eq!(varint(127), vec![0x7f]);
+        assert_eq!(varint(128), vec![0x80, 0x01]);
+        assert_eq!(varint(300), vec![0xac, 0x02]);
+        assert_eq!(varint(u64::MAX).len(), 10);
+    }
+
+    #[test]
+    fn test_shifted_boundaries_stay_distinct() {
+        // Naive concatenation gives "abc" for all of these
+        let splits: [&[&[u8]]; 4] = [&[b"ab", b"c"], &[b"a", b"bc"], &[b"abc", b""], &[b"abc"]];
+        for (i, a) in splits.iter().enumerate() {
+            assert_eq!(sha256(a), sha256(&[b"abc"]));
+            for b in &splits[i + 1..] {
+                assert_ne!(encode_fields(a), encode_fields(b));
+                assert_ne!(hash_fields(a), hash_fields(b));
+            }
+        }
+    }
+}
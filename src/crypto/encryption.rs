@@ -0,0 +1,98 @@
+//! AES-256-GCM encryption of values at rest (enabled by the `encryption` feature)
+
+use crate::error::AppError;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::fmt;
+
+/// Length in bytes of the random nonce stored ahead of each ciphertext
+pub const NONCE_LEN: usize = 12;
+
+/// 256-bit key for encrypting values at rest
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Parse a key from 64 hex characters
+    pub fn from_hex(hex_key: &str) -> Result<Self, AppError> {
+        let bytes = hex::decode(hex_key.trim())
+            .map_err(|e| AppError::InvalidInput(format!("Invalid encryption key hex: {}", e)))?;
+        let key = <[u8; 32]>::try_from(bytes.as_slice())
+            .map_err(|_| AppError::InvalidInput("Encryption key must be 32 bytes".to_string()))?;
+        Ok(Self(key))
+    }
+
+    /// Read `MERKLE_ENCRYPTION_KEY` (hex); None when unset
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        std::env::var("MERKLE_ENCRYPTION_KEY")
+            .ok()
+            .map(|hex_key| Self::from_hex(&hex_key))
+            .transpose()
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&Key::<Aes256Gcm>::from(self.0))
+    }
+
+    /// Encrypt `plaintext` as `nonce || ciphertext` under a fresh random nonce
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt value: {}", e)))?;
+        Ok([&nonce[..], &ciphertext].concat())
+    }
+
+    /// Decrypt a `nonce || ciphertext` blob written by `encrypt`
+    pub fn decrypt(&self, stored: &[u8]) -> Result<Vec<u8>, AppError> {
+        let (nonce, ciphertext) = stored
+            .split_first_chunk::<NONCE_LEN>()
+            .ok_or(AppError::Internal("Encrypted value is truncated".to_string()))?;
+        self.cipher()
+            .decrypt(&Nonce::from(*nonce), ciphertext)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt value: {}", e)))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep key material out of logs
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = EncryptionKey::new([7; 32]);
+        let first = key.encrypt(b"secret").unwrap();
+        let second = key.encrypt(b"secret").unwrap();
+
+        // Fresh nonces give distinct ciphertexts for the same plaintext
+        assert_ne!(first, second);
+        assert_eq!(key.decrypt(&first).unwrap(), b"secret".to_vec());
+
+        let mut tampered = first.clone();
+        tampered[NONCE_LEN] ^= 0xff;
+        assert!(key.decrypt(&tampered).is_err());
+        assert!(EncryptionKey::new([8; 32]).decrypt(&first).is_err());
+        assert!(key.decrypt(&first[..4]).is_err());
+    }
+
+    #[test]
+    fn test_key_from_hex() {
+        let key = EncryptionKey::from_hex(&"ab".repeat(32)).unwrap();
+        assert_eq!(key.0, [0xab; 32]);
+        assert!(EncryptionKey::from_hex("abcd").is_err());
+        assert!(EncryptionKey::from_hex("zz").is_err());
+        assert_eq!(format!("{:?}", key), "EncryptionKey(..)");
+    }
+}
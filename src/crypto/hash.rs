@@ -0,0 +1,219 @@
+//! SHA-256 for leaf and node hashes. The `simd` feature swaps the pure-Rust
+//! implementation for ring's assembly one (SHA-NI/AVX where the CPU has them);
+//! the output is byte-identical either way. BLAKE3 is available through
+//! `Blake3Hasher` for interop with deployments built on it, and `Rfc6962Hasher`
+//! adds RFC 6962 leaf/node prefixes for second-preimage resistance.
+
+use crate::crypto::{ct, Hasher};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Hash function a tree or proof was built with. This server's storage hashes with
+/// SHA-256; proofs tagged with another algorithm verify with its `Hasher`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+    /// SHA-256 with RFC 6962 domain separation: 0x00 before leaf data, 0x01 before
+    /// a node's children
+    #[serde(rename = "sha256-rfc6962")]
+    Sha256Rfc6962,
+}
+
+impl HashAlgorithm {
+    /// True for the default algorithm
+    pub fn is_sha256(&self) -> bool {
+        *self == Self::Sha256
+    }
+
+    /// Digest length in bytes
+    pub fn output_len(self) -> usize {
+        match self {
+            Self::Sha256 => Sha256Hasher::OUTPUT_LEN,
+            Self::Blake3 => Blake3Hasher::OUTPUT_LEN,
+            Self::Sha256Rfc6962 => Rfc6962Hasher::OUTPUT_LEN,
+        }
+    }
+
+    /// Hash a left and right child into their parent
+    pub fn hash_nodes(self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256Hasher::hash_nodes(left, right),
+            Self::Blake3 => Blake3Hasher::hash_nodes(left, right),
+            Self::Sha256Rfc6962 => Rfc6962Hasher::hash_nodes(left, right),
+        }
+    }
+}
+
+/// SHA-256 over the raw leaf data and the concatenated children (the default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    const OUTPUT_LEN: usize = 32;
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha256;
+
+    fn hash_leaf(data: &[u8]) -> Vec<u8> {
+        sha256(&[data])
+    }
+
+    fn hash_nodes(left: &[u8], right: &[u8]) -> Vec<u8> {
+        sha256(&[left, right])
+    }
+}
+
+/// BLAKE3 in its default 32-byte mode, laid out like `Sha256Hasher`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+impl Hasher for Blake3Hasher {
+    const OUTPUT_LEN: usize = blake3::OUT_LEN;
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Blake3;
+
+    fn hash_leaf(data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+
+    fn hash_nodes(left: &[u8], right: &[u8]) -> Vec<u8> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().as_bytes().to_vec()
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+            Self::Sha256Rfc6962 => "sha256-rfc6962",
+        })
+    }
+}
+
+/// SHA-256 with RFC 6962 prefixes, so an interior node's preimage can never be
+/// presented as a leaf
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rfc6962Hasher;
+
+impl Hasher for Rfc6962Hasher {
+    const OUTPUT_LEN: usize = 32;
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha256Rfc6962;
+
+    fn hash_leaf(data: &[u8]) -> Vec<u8> {
+        ct::leaf_hash(data)
+    }
+
+    fn hash_nodes(left: &[u8], right: &[u8]) -> Vec<u8> {
+        ct::node_hash(left, right)
+    }
+}
+
+/// SHA-256 over the concatenation of `parts`, using the accelerated implementation
+/// when the `simd` feature is enabled
+pub fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    #[cfg(feature = "simd")]
+    return accelerated(parts);
+    #[cfg(not(feature = "simd"))]
+    return portable(parts);
+}
+
+/// Pure-Rust SHA-256 (the default)
+pub fn portable(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Incremental SHA-256 for input that arrives in pieces, on the same implementation
+/// as `sha256`
+pub struct Sha256Stream {
+    #[cfg(feature = "simd")]
+    context: ring::digest::Context,
+    #[cfg(not(feature = "simd"))]
+    hasher: Sha256,
+}
+
+impl Sha256Stream {
+    /// Start an empty hash
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "simd")]
+            context: ring::digest::Context::new(&ring::digest::SHA256),
+            #[cfg(not(feature = "simd"))]
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Feed the next piece of input
+    pub fn update(&mut self, data: &[u8]) {
+        #[cfg(feature = "simd")]
+        self.context.update(data);
+        #[cfg(not(feature = "simd"))]
+        self.hasher.update(data);
+    }
+
+    /// Get the digest of everything fed so far
+    pub fn finish(self) -> Vec<u8> {
+        #[cfg(feature = "simd")]
+        return self.context.finish().as_ref().to_vec();
+        #[cfg(not(feature = "simd"))]
+        return self.hasher.finalize().to_vec();
+    }
+}
+
+impl Default for Sha256Stream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Assembly-backed SHA-256 from ring
+#[cfg(feature = "simd")]
+pub fn accelerated(parts: &[&[u8]]) -> Vec<u8> {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    for part in parts {
+        context.update(part);
+    }
+    context.finish().as_ref().to_vec()
+}
+
+#[cfg(all(test, feature = "simd"))]
+mod tests {
+    use super::*;
+    use crate::models::merkle::{MerkleNode, MerkleTree};
+
+    /// Root over `leaves`, duplicating the last node of odd levels like `MerkleTree`
+    fn root_with(hash: fn(&[&[u8]]) -> Vec<u8>, values: &[Vec<u8>]) -> Vec<u8> {
+        let mut level: Vec<Vec<u8>> = values.iter().map(|v| hash(&[v])).collect();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| hash(&[&pair[0], pair.get(1).unwrap_or(&pair[0])]))
+                .collect();
+        }
+        level.pop().unwrap()
+    }
+
+    #[test]
+    fn test_accelerated_roots_match_portable() {
+        for count in [1, 2, 3, 7, 64, 1000] {
+            let values: Vec<Vec<u8>> = (0..count).map(|i| vec![i as u8; i % 200]).collect();
+            let portable_root = root_with(portable, &values);
+            assert_eq!(root_with(accelerated, &values), portable_root, "{}", count);
+
+            let tree = MerkleTree::from_leaves(values.iter().map(|v| MerkleNode::new_leaf(v)).collect());
+            assert_eq!(tree.root_hash().unwrap(), portable_root, "{}", count);
+        }
+
+        let large = vec![0xa5; 1 << 20];
+        assert_eq!(accelerated(&[&large, b"tail"]), portable(&[&large, b"tail"]));
+        assert_eq!(accelerated(&[]), portable(&[]));
+    }
+}
@@ -1 +1,23 @@
-pub mod proof;
\ No newline at end of file
+pub mod proof;
+pub mod ct;
+pub mod encoding;
+pub mod hash;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+
+pub use hash::{Blake3Hasher, Rfc6962Hasher, Sha256Hasher};
+
+/// Hash function for leaves and interior nodes, so the same tree logic can run over
+/// SHA-256, BLAKE3 or another digest
+pub trait Hasher {
+    /// Digest length in bytes
+    const OUTPUT_LEN: usize;
+    /// Tag recorded in proofs built with this hasher
+    const ALGORITHM: hash::HashAlgorithm;
+
+    /// Hash a leaf's data
+    fn hash_leaf(data: &[u8]) -> Vec<u8>;
+
+    /// Hash a left and right child into their parent
+    fn hash_nodes(left: &[u8], right: &[u8]) -> Vec<u8>;
+}
@@ -1,6 +1,38 @@
-use crate::models::merkle::MerkleNode;
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use crate::crypto::{
+    ct,
+    hash::HashAlgorithm,
+    Blake3Hasher, Rfc6962Hasher,
+};
+use crate::error::AppError;
+use crate::models::commitment::Commitment;
+use crate::models::merkle::{LeafHashMode, MerkleNode, EMPTY_LEAF_HASH};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Length in bytes of every hash in a proof (SHA-256)
+pub const HASH_LEN: usize = 32;
+
+/// Default cap on proof path elements; 64 levels cover 2^64 leaves
+pub const MAX_PROOF_PATH_LEN: usize = 64;
+
+/// Current proof wire format version. Version 2 added `prehashed`, `leaf_mode`,
+/// `algorithm` and `placeholder`, which change how the leaf and nodes are hashed;
+/// proofs leaving all four at their defaults are still written as version 1.
+pub const PROOF_VERSION: u8 = 2;
+
+fn default_version() -> u8 {
+    1
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let version = u8::deserialize(deserializer)?;
+    if !(1..=PROOF_VERSION).contains(&version) {
+        return Err(serde::de::Error::custom(format!(
+            "unsupported proof version {} (expected at most {})",
+            version, PROOF_VERSION
+        )));
+    }
+    Ok(version)
+}
 
 /// An element in a Merkle proof
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -12,8 +44,11 @@ pub struct ProofElement {
 }
 
 /// A complete Merkle proof for a specific commitment
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct MerkleProof {
+    /// Wire format version; proofs without one are treated as version 1
+    #[serde(default = "default_version", deserialize_with = "deserialize_version")]
+    pub version: u8,
     /// Index of the commitment being proved
     pub index: usize,
     /// The committed value
@@ -22,86 +57,1010 @@ pub struct MerkleProof {
     pub proof: Vec<ProofElement>,
     /// The Merkle root
     pub root: Vec<u8>,
+    /// Hops from the sub-tree root up through parent trees (empty for a single-level proof)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chain: Vec<ChainLink>,
+    /// True when `value` is already the leaf hash (hash-only storage), so it isn't hashed again
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prehashed: bool,
+    /// How `value` is hashed into the leaf (ignored when prehashed)
+    #[serde(default, skip_serializing_if = "LeafHashMode::is_plain")]
+    pub leaf_mode: LeafHashMode,
+    /// Hash function the proof was built with, picking the hasher `verify` uses
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_sha256")]
+    pub algorithm: HashAlgorithm,
+    /// True when the leaf is a null slot, whose hash is `EMPTY_LEAF_HASH`; its
+    /// `value` must be empty
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub placeholder: bool,
+}
+
+/// One hop of a chained proof: the sub-tree root is committed as a leaf of a parent tree
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChainLink {
+    /// Index of the sub-tree root within the parent tree
+    pub index: usize,
+    /// The proof path within the parent tree
+    pub proof: Vec<ProofElement>,
 }
 
 impl MerkleProof {
     /// Create a new Merkle proof
     pub fn new(index: usize, value: Vec<u8>, proof: Vec<ProofElement>, root: Vec<u8>) -> Self {
         Self {
+            version: 1,
             index,
             value,
             proof,
             root,
+            chain: Vec::new(),
+            prehashed: false,
+            leaf_mode: LeafHashMode::Plain,
+            algorithm: HashAlgorithm::Sha256,
+            placeholder: false,
         }
     }
 
-    /// Verify this proof is valid
-    pub fn verify(&self) -> bool {
-        let mut current_hash = {
-            let mut hasher = Sha256::new();
-            hasher.update(&self.value);
-            hasher.finalize().to_vec()
+    /// Proof for the slot `commitment` holds, along `path` to `root`. In hash-only
+    /// storage the value is already the leaf hash.
+    pub fn for_commitment(
+        commitment: &Commitment,
+        path: Vec<ProofElement>,
+        root: Vec<u8>,
+        hash_only: bool,
+        leaf_mode: LeafHashMode,
+    ) -> Self {
+        Self::new(commitment.index, commitment.value.to_vec(), path, root)
+            .with_prehashed(hash_only && !commitment.placeholder)
+            .with_leaf_mode(leaf_mode)
+            .with_placeholder(commitment.placeholder)
+    }
+
+    /// Mark whether `value` is already the leaf hash
+    pub fn with_prehashed(mut self, prehashed: bool) -> Self {
+        self.prehashed = prehashed;
+        self.with_required_version()
+    }
+
+    /// Set how `value` is hashed into the leaf
+    pub fn with_leaf_mode(mut self, leaf_mode: LeafHashMode) -> Self {
+        self.leaf_mode = leaf_mode;
+        self.with_required_version()
+    }
+
+    /// Set the hash function the proof was built with
+    pub fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self.with_required_version()
+    }
+
+    /// Mark whether the leaf is a null slot
+    pub fn with_placeholder(mut self, placeholder: bool) -> Self {
+        self.placeholder = placeholder;
+        self.with_required_version()
+    }
+
+    /// Whether `prehashed`, `leaf_mode`, `algorithm` and `placeholder` are all at
+    /// their defaults, the only proofs a version 1 reader hashes correctly
+    fn is_v1(&self) -> bool {
+        !self.prehashed && self.leaf_mode.is_plain() && self.algorithm.is_sha256() && !self.placeholder
+    }
+
+    /// Set the lowest version that can express the proof
+    fn with_required_version(mut self) -> Self {
+        self.version = if self.is_v1() { 1 } else { PROOF_VERSION };
+        self
+    }
+
+    /// Whether `version` is known and can express the proof
+    fn version_supported(&self) -> bool {
+        match self.version {
+            1 => self.is_v1(),
+            version => version == PROOF_VERSION,
+        }
+    }
+
+    /// Compose this sub-tree proof with a proof from a parent tree whose committed
+    /// value is this proof's root, yielding a proof from the sub-leaf to the parent root
+    pub fn chain(self, parent_proof: MerkleProof) -> Result<MerkleProof, AppError> {
+        if parent_proof.value != self.root {
+            return Err(AppError::InvalidInput(
+                "Parent proof value must be the sub-tree root".to_string(),
+            ));
+        }
+
+        let mut chain = self.chain;
+        chain.push(ChainLink {
+            index: parent_proof.index,
+            proof: parent_proof.proof,
+        });
+        chain.extend(parent_proof.chain);
+
+        Ok(MerkleProof {
+            version: self.version,
+            index: self.index,
+            value: self.value,
+            proof: self.proof,
+            root: parent_proof.root,
+            chain,
+            prehashed: self.prehashed,
+            leaf_mode: self.leaf_mode,
+            algorithm: self.algorithm,
+            placeholder: self.placeholder,
+        })
+    }
+
+    /// Side of each sibling along the path, leaf level first (true when the sibling
+    /// is on the left)
+    pub fn is_left_sequence(&self) -> Vec<bool> {
+        self.proof.iter().map(|element| element.is_left).collect()
+    }
+
+    /// Leaf index implied by the path: a left sibling at level `i` means the path
+    /// went right there, setting bit `i`. Chained hops don't contribute.
+    pub fn position(&self) -> usize {
+        self.is_left_sequence()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, is_left)| is_left)
+            .fold(0, |position, (level, _)| position | (1 << level))
+    }
+
+    /// Hash of the leaf the path starts from: `EMPTY_LEAF_HASH` for a null slot, the
+    /// value itself when prehashed, otherwise the value hashed per the leaf mode
+    pub fn leaf_hash(&self) -> Vec<u8> {
+        self.start_hash(&|index, value| self.hash_leaf(index, value))
+    }
+
+    fn start_hash(&self, hash_leaf: &LeafHasher<'_>) -> Vec<u8> {
+        if self.placeholder {
+            EMPTY_LEAF_HASH.to_vec()
+        } else if self.prehashed {
+            self.value.clone()
+        } else {
+            hash_leaf(self.index, &self.value)
+        }
+    }
+
+    /// Hash a value into the leaf for `index` per the leaf mode and algorithm
+    fn hash_leaf(&self, index: usize, value: &[u8]) -> Vec<u8> {
+        match self.algorithm {
+            HashAlgorithm::Sha256 => self.leaf_mode.hash(index, value),
+            HashAlgorithm::Blake3 => self.leaf_mode.hash_using::<Blake3Hasher>(index, value),
+            HashAlgorithm::Sha256Rfc6962 => self.leaf_mode.hash_using::<Rfc6962Hasher>(index, value),
+        }
+    }
+
+    /// Total number of sibling hashes, including those in chained hops
+    pub fn path_len(&self) -> usize {
+        self.proof.len() + self.chain.iter().map(|link| link.proof.len()).sum::<usize>()
+    }
+
+    /// Smallest and largest leaf count of a tree this path could come from, for
+    /// checking a proof against a claimed tree size. The path length fixes the depth
+    /// and the index sets a floor. A right-hand sibling equal to the running hash is
+    /// odd-level padding, so the path ran along the last node of that level, capping
+    /// the size; a distinct one means more leaves follow. Only the innermost tree of
+    /// a chained proof is described, and the root isn't checked (see `verify`).
+    /// Returns (0, 0) when no tree fits, e.g. sides that disagree with the index.
+    pub fn implied_tree_size_range(&self) -> (usize, usize) {
+        let pow2 = |exp: usize| u32::try_from(exp).ok().and_then(|e| 1usize.checked_shl(e)).unwrap_or(usize::MAX);
+        let depth = self.proof.len();
+        // Depth d holds 2^(d-1) + 1 through 2^d leaves; depth 1 also fits a lone
+        // leaf duplicated to a parent
+        let mut min = match depth {
+            0 | 1 => 1,
+            d => pow2(d - 1).saturating_add(1),
         };
+        let mut max = pow2(depth);
+        min = min.max(self.index.saturating_add(1));
 
-        for element in &self.proof {
-            let mut hasher = Sha256::new();
+        let mut current = self.leaf_hash();
+        for (level, element) in self.proof.iter().enumerate() {
+            let position = u32::try_from(level)
+                .ok()
+                .and_then(|l| self.index.checked_shr(l))
+                .unwrap_or(0);
+            if element.is_left != (position & 1 == 1) {
+                return (0, 0);
+            }
             if element.is_left {
-                hasher.update(&element.hash);
-                hasher.update(&current_hash);
+                current = self.algorithm.hash_nodes(&element.hash, &current);
+                continue;
+            }
+
+            // Leaves up to and including the subtree the path is in at this level
+            let covered = position.saturating_add(1).saturating_mul(pow2(level));
+            if element.hash == current {
+                max = max.min(covered);
             } else {
-                hasher.update(&current_hash);
-                hasher.update(&element.hash);
+                min = min.max(covered.saturating_add(1));
             }
-            current_hash = hasher.finalize().to_vec();
+            current = self.algorithm.hash_nodes(&current, &element.hash);
         }
 
-        current_hash == self.root
+        if min > max { (0, 0) } else { (min, max) }
     }
+
+    /// Verify this proof is valid
+    pub fn verify(&self) -> bool {
+        self.verify_with_max_path(MAX_PROOF_PATH_LEN)
+    }
+
+    /// Verify this proof and require its root to be one of `trusted`, so a valid proof
+    /// against a root that was never published is still rejected
+    pub fn verify_against_roots(&self, trusted: &[Vec<u8>]) -> bool {
+        trusted.contains(&self.root) && self.verify()
+    }
+
+    /// Verify this proof, rejecting paths longer than `max_path_len` before hashing
+    pub fn verify_with_max_path(&self, max_path_len: usize) -> bool {
+        if self.path_len() > max_path_len {
+            return false;
+        }
+
+        // Reject unknown formats and malformed hashes up front rather than hashing garbage
+        let hash_len = self.algorithm.output_len();
+        let well_formed = self.version_supported()
+            && self.root.len() == hash_len
+            && (!self.prehashed || self.value.len() == hash_len)
+            && (!self.placeholder || self.value.is_empty())
+            && self
+                .proof
+                .iter()
+                .chain(self.chain.iter().flat_map(|link| link.proof.iter()))
+                .all(|element| element.hash.len() == hash_len);
+        if !well_formed {
+            return false;
+        }
+
+        self.compute_root() == self.root
+    }
+
+    /// Verify with caller-supplied hashing: `hash_leaf` turns the value (and each
+    /// chained sub-tree root) into a leaf and `hash_pair` joins a left and right node.
+    /// Neither hash lengths nor the path length are checked here; `verify` does both.
+    pub fn verify_with<L, P>(&self, hash_leaf: L, hash_pair: P) -> bool
+    where
+        L: Fn(&[u8]) -> Vec<u8>,
+        P: Fn(&[u8], &[u8]) -> Vec<u8>,
+    {
+        self.version_supported()
+            && (!self.placeholder || self.value.is_empty())
+            && self.compute_root_with(&|_, value| hash_leaf(value), &hash_pair) == self.root
+    }
+
+    /// Run the hashing chain and return the root this proof computes to,
+    /// without comparing it to `self.root`
+    pub fn compute_root(&self) -> Vec<u8> {
+        let algorithm = self.algorithm;
+        self.compute_root_with(
+            &|index, value| self.hash_leaf(index, value),
+            &|left, right| algorithm.hash_nodes(left, right),
+        )
+    }
+
+    fn compute_root_with(
+        &self,
+        hash_leaf: &LeafHasher<'_>,
+        hash_pair: &PairHasher<'_>,
+    ) -> Vec<u8> {
+        let mut current_hash = fold_path(self.start_hash(hash_leaf), &self.proof, hash_pair);
+
+        // Each hop commits the sub-tree root as a leaf of the parent tree
+        for link in &self.chain {
+            current_hash = fold_path(hash_leaf(link.index, &current_hash), &link.proof, hash_pair);
+        }
+
+        current_hash
+    }
+
+    /// Shareable JSON with hex strings in place of byte arrays:
+    /// `{ index, value_hex, path: [{ hash_hex, is_left }], root_hex }`
+    pub fn to_json_compact(&self) -> String {
+        let path = |proof: &[ProofElement]| {
+            proof
+                .iter()
+                .map(|element| CompactElement {
+                    hash_hex: hex::encode(&element.hash),
+                    is_left: element.is_left,
+                })
+                .collect()
+        };
+        let compact = CompactProof {
+            index: self.index,
+            value_hex: hex::encode(&self.value),
+            path: path(&self.proof),
+            root_hex: hex::encode(&self.root),
+            chain: self
+                .chain
+                .iter()
+                .map(|link| CompactLink {
+                    index: link.index,
+                    path: path(&link.proof),
+                })
+                .collect(),
+            prehashed: self.prehashed,
+            leaf_mode: self.leaf_mode,
+            algorithm: self.algorithm,
+            placeholder: self.placeholder,
+        };
+        serde_json::to_string(&compact).expect("compact proof is always serializable")
+    }
+
+    /// Parse a proof written by `to_json_compact`
+    pub fn from_json_compact(json: &str) -> Result<Self, AppError> {
+        let compact: CompactProof = serde_json::from_str(json)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid compact proof: {}", e)))?;
+        let bytes = |field: &str, text: &str| {
+            hex::decode(text)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid compact proof {}: {}", field, e)))
+        };
+        let path = |path: Vec<CompactElement>| {
+            path.into_iter()
+                .map(|element| {
+                    Ok(ProofElement {
+                        hash: bytes("hash_hex", &element.hash_hex)?,
+                        is_left: element.is_left,
+                    })
+                })
+                .collect::<Result<Vec<_>, AppError>>()
+        };
+
+        Ok(MerkleProof {
+            chain: compact
+                .chain
+                .into_iter()
+                .map(|link| {
+                    Ok(ChainLink {
+                        index: link.index,
+                        proof: path(link.path)?,
+                    })
+                })
+                .collect::<Result<_, AppError>>()?,
+            prehashed: compact.prehashed,
+            leaf_mode: compact.leaf_mode,
+            algorithm: compact.algorithm,
+            placeholder: compact.placeholder,
+            ..MerkleProof::new(
+                compact.index,
+                bytes("value_hex", &compact.value_hex)?,
+                path(compact.path)?,
+                bytes("root_hex", &compact.root_hex)?,
+            )
+        }
+        .with_required_version())
+    }
+}
+
+/// Wire shape of `MerkleProof::to_json_compact`
+#[derive(Serialize, Deserialize)]
+struct CompactProof {
+    index: usize,
+    value_hex: String,
+    path: Vec<CompactElement>,
+    root_hex: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    chain: Vec<CompactLink>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    prehashed: bool,
+    #[serde(default, skip_serializing_if = "LeafHashMode::is_plain")]
+    leaf_mode: LeafHashMode,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_sha256")]
+    algorithm: HashAlgorithm,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    placeholder: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactElement {
+    hash_hex: String,
+    is_left: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactLink {
+    index: usize,
+    path: Vec<CompactElement>,
+}
+
+/// Turns a value into the leaf hash for its index
+type LeafHasher<'a> = dyn Fn(usize, &[u8]) -> Vec<u8> + 'a;
+
+/// Joins a left and right node into their parent's hash
+type PairHasher<'a> = dyn Fn(&[u8], &[u8]) -> Vec<u8> + 'a;
+
+
+/// Hash a starting node up through a proof path
+fn fold_path(
+    mut current_hash: Vec<u8>,
+    path: &[ProofElement],
+    hash_pair: &PairHasher<'_>,
+) -> Vec<u8> {
+    for element in path {
+        current_hash = if element.is_left {
+            hash_pair(&element.hash, &current_hash)
+        } else {
+            hash_pair(&current_hash, &element.hash)
+        };
+    }
+    current_hash
 }
 
 /// Generate a Merkle proof for a specific index
 pub fn generate_proof(tree: &MerkleNode, target_index: usize, total_leaves: usize) -> Vec<ProofElement> {
-    fn helper(node: &MerkleNode, idx: usize, begin: usize, leaves_count: usize) -> Vec<ProofElement> {
-        if node.left.is_none() && node.right.is_none() {
-            // Leaf node, end of proof path
-            return Vec::new();
+    // Nothing is recomputed from a complete tree, so the algorithm goes unused
+    generate_pruned_proof(tree, target_index, total_leaves, &[], HashAlgorithm::Sha256)
+        .expect("a complete tree holds every sibling")
+}
+
+/// Generate a proof from a tree that may have had interior nodes pruned, recomputing
+/// any missing sibling from `leaf_hashes` with the tree's `algorithm`. None when a
+/// sibling is missing and the leaves under it weren't supplied.
+pub fn generate_pruned_proof(
+    tree: &MerkleNode,
+    target_index: usize,
+    total_leaves: usize,
+    leaf_hashes: &[Vec<u8>],
+    algorithm: HashAlgorithm,
+) -> Option<Vec<ProofElement>> {
+    fn helper(
+        node: Option<&MerkleNode>,
+        idx: usize,
+        begin: usize,
+        width: usize,
+        total_leaves: usize,
+        leaf_hashes: &[Vec<u8>],
+        algorithm: HashAlgorithm,
+    ) -> Option<Vec<ProofElement>> {
+        if width <= 1 {
+            // Leaf slot, end of proof path
+            return Some(Vec::new());
         }
-        // Figure out the size of the left subtree
-        let left_count = (leaves_count + 1) / 2;
-        let right_count = leaves_count - left_count;
-        let right_begin = begin + left_count;
+        // Each child covers half of this node's leaf slots
+        let half = width / 2;
+        let right_begin = begin + half;
+        let left = node.and_then(|n| n.left.as_deref());
+        let right = node.and_then(|n| n.right.as_deref());
+        let left_hash = || match left {
+            Some(left) => Some(left.hash.clone()),
+            None => range_hash(leaf_hashes, begin, half, total_leaves, algorithm),
+        };
 
         // Depending on which subtree target is in, recurse accordingly
-        if idx < begin + left_count {
-            // Target is in left subtree
-            let left = node.left.as_ref().unwrap();
-            let right = node.right.as_ref().unwrap_or(left); // duplicate left if missing
-            let mut proof = helper(left, idx, begin, left_count);
+        if idx < right_begin {
+            // Right slots past the last leaf duplicate the left subtree
+            let sibling = match right {
+                _ if right_begin >= total_leaves => left_hash()?,
+                Some(right) => right.hash.clone(),
+                None => range_hash(leaf_hashes, right_begin, half, total_leaves, algorithm)?,
+            };
+            let mut proof = helper(left, idx, begin, half, total_leaves, leaf_hashes, algorithm)?;
             proof.push(ProofElement {
-                hash: right.hash.clone(),
+                hash: sibling,
                 is_left: false,
             });
-            proof
+            Some(proof)
         } else {
-            // Target is in right subtree
-            let left = node.left.as_ref().unwrap();
-            let right = node.right.as_ref().unwrap_or(left); // duplicate left if missing (should never happen here, but for symmetry)
-            let mut proof = helper(right, idx, right_begin, right_count);
+            let mut proof = helper(right, idx, right_begin, half, total_leaves, leaf_hashes, algorithm)?;
             proof.push(ProofElement {
-                hash: left.hash.clone(),
+                hash: left_hash()?,
                 is_left: true,
             });
-            proof
+            Some(proof)
+        }
+    }
+    // A single leaf duplicated under a parent still spans two slots
+    let width = if total_leaves <= 1 && tree.is_leaf() { 1 } else { slot_width(total_leaves)?.max(2) };
+    helper(Some(tree), target_index, 0, width, total_leaves, leaf_hashes, algorithm)
+}
+
+/// Hash of the subtree over leaf slots [begin, begin + width), duplicating odd nodes
+/// as tree building does. None if a needed leaf isn't in `leaf_hashes`.
+fn range_hash(
+    leaf_hashes: &[Vec<u8>],
+    begin: usize,
+    width: usize,
+    total_leaves: usize,
+    algorithm: HashAlgorithm,
+) -> Option<Vec<u8>> {
+    if width <= 1 {
+        return leaf_hashes.get(begin).cloned();
+    }
+    let half = width / 2;
+    let left = range_hash(leaf_hashes, begin, half, total_leaves, algorithm)?;
+    if begin + half >= total_leaves {
+        return Some(algorithm.hash_nodes(&left, &left));
+    }
+    let right = range_hash(leaf_hashes, begin + half, half, total_leaves, algorithm)?;
+    Some(algorithm.hash_nodes(&left, &right))
+}
+
+/// The delta needed to upgrade a proof from an older tree to the current root
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofExtension {
+    /// Index of the commitment being proved
+    pub index: usize,
+    /// Tree size the old proof was generated against
+    pub old_size: usize,
+    /// Current tree size
+    pub new_size: usize,
+    /// Number of leading elements of the old path that remain valid
+    pub retained: usize,
+    /// Sibling hashes to append after the retained elements
+    pub siblings: Vec<ProofElement>,
+    /// The current Merkle root
+    pub root: Vec<u8>,
+}
+
+impl ProofExtension {
+    /// Apply this extension to a proof generated against the old tree
+    pub fn apply(&self, old: &MerkleProof) -> MerkleProof {
+        let mut path = old.proof[..self.retained.min(old.proof.len())].to_vec();
+        path.extend(self.siblings.iter().cloned());
+        MerkleProof {
+            proof: path,
+            root: self.root.clone(),
+            ..old.clone()
         }
     }
-    helper(tree, target_index, 0, total_leaves)
 }
 
+/// Compute the extension turning a proof for `index` in the old tree into one for the new tree.
+/// When the old size isn't a power of two some of its duplicated siblings become real
+/// nodes, so only the common prefix of the two paths is retained.
+pub fn generate_proof_extension(
+    old_tree: &MerkleNode,
+    old_size: usize,
+    new_tree: &MerkleNode,
+    new_size: usize,
+    index: usize,
+) -> ProofExtension {
+    let old_path = generate_proof(old_tree, index, old_size);
+    let new_path = generate_proof(new_tree, index, new_size);
+    let retained = old_path
+        .iter()
+        .zip(&new_path)
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    ProofExtension {
+        index,
+        old_size,
+        new_size,
+        retained,
+        siblings: new_path[retained..].to_vec(),
+        root: new_tree.hash.clone(),
+    }
+}
+
+/// Proof that the tree of the first `old_size` leaves is a prefix of the tree of
+/// `new_size` leaves. `proof` holds the largest subtrees lying wholly before
+/// `old_size` (`is_left`) or wholly after it, in the order the new tree visits them;
+/// the left ones alone rebuild the old root.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub old_size: usize,
+    pub new_size: usize,
+    pub proof: Vec<ProofElement>,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_sha256")]
+    pub algorithm: HashAlgorithm,
+}
+
+impl ConsistencyProof {
+    pub fn new(old_size: usize, new_size: usize, proof: Vec<ProofElement>) -> Self {
+        Self {
+            old_size,
+            new_size,
+            proof,
+            algorithm: HashAlgorithm::Sha256,
+        }
+    }
+
+    /// Set the hash function the trees were built with
+    pub fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Check the proof rebuilds both `old_root` and `new_root`, so the old tree's
+    /// leaves are exactly the new tree's first `old_size`
+    pub fn verify(&self, old_root: &[u8], new_root: &[u8]) -> bool {
+        let hash_len = self.algorithm.output_len();
+        let well_formed = 0 < self.old_size
+            && self.old_size <= self.new_size
+            && self.proof.len() <= 2 * MAX_PROOF_PATH_LEN
+            && self.proof.iter().all(|element| element.hash.len() == hash_len);
+        if !well_formed {
+            return false;
+        }
+
+        let old_nodes: Vec<_> = self.proof.iter().filter(|element| element.is_left).cloned().collect();
+        consistency_root(self.old_size, self.old_size, &old_nodes, self.algorithm)
+            .is_some_and(|root| root == old_root)
+            && consistency_root(self.old_size, self.new_size, &self.proof, self.algorithm)
+                .is_some_and(|root| root == new_root)
+    }
+}
+
+/// Generate a consistency proof between the first `old_size` leaves of `tree` and the
+/// whole tree of `new_size` leaves. `old_size` must be in 1..=`new_size`.
+pub fn generate_consistency_proof(tree: &MerkleNode, old_size: usize, new_size: usize) -> Vec<ProofElement> {
+    fn helper(
+        node: &MerkleNode,
+        begin: usize,
+        width: usize,
+        old_size: usize,
+        new_size: usize,
+        proof: &mut Vec<ProofElement>,
+    ) {
+        if begin + width <= old_size || begin >= old_size {
+            // Wholly on one side of the old tree's end
+            proof.push(ProofElement {
+                hash: node.hash.clone(),
+                is_left: begin < old_size,
+            });
+            return;
+        }
+
+        let half = width / 2;
+        let left = node.left.as_deref().expect("a node over several slots has children");
+        helper(left, begin, half, old_size, new_size, proof);
+        // Right children past the last leaf are duplicates the verifier recomputes
+        if begin + half < new_size {
+            let right = node.right.as_deref().unwrap_or(left);
+            helper(right, begin + half, half, old_size, new_size, proof);
+        }
+    }
+
+    let mut proof = Vec::new();
+    if let Some(width) = slot_width(new_size) {
+        helper(tree, 0, width, old_size, new_size, &mut proof);
+    }
+    proof
+}
+
+/// Root of a `total_leaves` tree whose subtrees either side of `split` are taken from
+/// `elements` in order. None when an element is on the wrong side, or too few or too
+/// many are given.
+fn consistency_root(
+    split: usize,
+    total_leaves: usize,
+    elements: &[ProofElement],
+    algorithm: HashAlgorithm,
+) -> Option<Vec<u8>> {
+    fn compute(
+        elements: &mut std::slice::Iter<'_, ProofElement>,
+        begin: usize,
+        width: usize,
+        split: usize,
+        total_leaves: usize,
+        algorithm: HashAlgorithm,
+    ) -> Option<Vec<u8>> {
+        if begin + width <= split || begin >= split {
+            let element = elements.next()?;
+            return (element.is_left == (begin < split)).then(|| element.hash.clone());
+        }
+
+        let half = width / 2;
+        let left = compute(elements, begin, half, split, total_leaves, algorithm)?;
+        let right = if begin + half < total_leaves {
+            compute(elements, begin + half, half, split, total_leaves, algorithm)?
+        } else {
+            left.clone()
+        };
+        Some(algorithm.hash_nodes(&left, &right))
+    }
+
+    let mut elements = elements.iter();
+    let root = compute(&mut elements, 0, slot_width(total_leaves)?, split, total_leaves, algorithm)?;
+    elements.next().is_none().then_some(root)
+}
+
+/// Number of leaf slots under the root. Odd nodes are duplicated level by level,
+/// so the tree is always a perfect binary tree over the next power of two. None
+/// for sizes no tree can reach, which only an untrusted proof can claim.
+fn slot_width(total_leaves: usize) -> Option<usize> {
+    total_leaves.checked_next_power_of_two()
+}
+
+/// A proof that a contiguous range of leaves is included in the tree
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RangeProof {
+    /// Index of the first leaf in the range
+    pub start: usize,
+    /// Total number of leaves in the tree
+    pub total_leaves: usize,
+    /// The committed values in the range, in order
+    pub values: Vec<Vec<u8>>,
+    /// Hashes of the subtrees outside the range, left to right
+    pub boundary: Vec<Vec<u8>>,
+    /// The Merkle root
+    pub root: Vec<u8>,
+    /// Indices in the range that are null slots, whose leaf is `EMPTY_LEAF_HASH` and
+    /// whose value must be empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub placeholders: Vec<usize>,
+}
+
+impl RangeProof {
+    /// Create a new range proof
+    pub fn new(
+        start: usize,
+        total_leaves: usize,
+        values: Vec<Vec<u8>>,
+        boundary: Vec<Vec<u8>>,
+        root: Vec<u8>,
+    ) -> Self {
+        Self {
+            start,
+            total_leaves,
+            values,
+            boundary,
+            root,
+            placeholders: Vec::new(),
+        }
+    }
+
+    /// Mark the indices in the range that are null slots
+    pub fn with_placeholders(mut self, placeholders: Vec<usize>) -> Self {
+        self.placeholders = placeholders;
+        self
+    }
+
+    /// Index one past the last leaf in the range, None if that overflows
+    pub fn end(&self) -> Option<usize> {
+        self.start.checked_add(self.values.len())
+    }
+}
+
+/// Generate the boundary hashes proving the leaf range [start, end)
+pub fn generate_range_proof(
+    tree: &MerkleNode,
+    start: usize,
+    end: usize,
+    total_leaves: usize,
+) -> Vec<Vec<u8>> {
+    fn helper(
+        node: &MerkleNode,
+        begin: usize,
+        width: usize,
+        start: usize,
+        end: usize,
+        total_leaves: usize,
+        boundary: &mut Vec<Vec<u8>>,
+    ) {
+        if begin + width <= start || begin >= end {
+            // Entirely outside the range, the verifier needs its hash
+            boundary.push(node.hash.clone());
+            return;
+        }
+        if node.is_leaf() {
+            return;
+        }
+
+        let half = width / 2;
+        let left = node.left.as_ref().unwrap();
+        helper(left, begin, half, start, end, total_leaves, boundary);
+        // Right children past the last leaf are duplicates the verifier recomputes
+        if begin + half < total_leaves {
+            let right = node.right.as_ref().unwrap_or(left);
+            helper(right, begin + half, half, start, end, total_leaves, boundary);
+        }
+    }
+
+    let mut boundary = Vec::new();
+    if let Some(width) = slot_width(total_leaves) {
+        helper(tree, 0, width, start, end, total_leaves, &mut boundary);
+    }
+    boundary
+}
+
+/// Verify a range proof by recomputing the root from the range and its boundary
+pub fn verify_range_proof(proof: &RangeProof) -> bool {
+    let well_formed = !proof.values.is_empty()
+        && proof.end().is_some_and(|end| end <= proof.total_leaves)
+        && proof.root.len() == HASH_LEN
+        && proof.boundary.iter().all(|hash| hash.len() == HASH_LEN)
+        // Null slots hold no value
+        && proof.placeholders.iter().all(|&index| {
+            index
+                .checked_sub(proof.start)
+                .and_then(|offset| proof.values.get(offset))
+                .is_some_and(|value| value.is_empty())
+        });
+    if !well_formed {
+        return false;
+    }
+
+    let leaf_hashes: Vec<Vec<u8>> = proof
+        .values
+        .iter()
+        .enumerate()
+        .map(|(offset, value)| {
+            let index = proof.start + offset;
+            if proof.placeholders.contains(&index) {
+                EMPTY_LEAF_HASH.to_vec()
+            } else {
+                MerkleNode::committed_leaf(index, value, false, LeafHashMode::Plain).hash
+            }
+        })
+        .collect();
+    range_root(proof.start, proof.total_leaves, &leaf_hashes, &proof.boundary, HashAlgorithm::Sha256)
+        .is_some_and(|root| root == proof.root)
+}
+
+/// Root of a `total_leaves` tree from the leaf hashes at `start..` and the boundary
+/// hashes around them. None when the boundary is short, or has hashes left over.
+fn range_root(
+    start: usize,
+    total_leaves: usize,
+    leaf_hashes: &[Vec<u8>],
+    boundary: &[Vec<u8>],
+    algorithm: HashAlgorithm,
+) -> Option<Vec<u8>> {
+    struct Ctx<'a> {
+        start: usize,
+        end: usize,
+        total_leaves: usize,
+        leaf_hashes: &'a [Vec<u8>],
+        boundary: std::slice::Iter<'a, Vec<u8>>,
+        algorithm: HashAlgorithm,
+    }
+
+    fn compute(ctx: &mut Ctx, begin: usize, width: usize) -> Option<Vec<u8>> {
+        if begin + width <= ctx.start || begin >= ctx.end {
+            return ctx.boundary.next().cloned();
+        }
+        if width == 1 {
+            return Some(ctx.leaf_hashes[begin - ctx.start].clone());
+        }
+
+        let half = width / 2;
+        let left = compute(ctx, begin, half)?;
+        let right = if begin + half < ctx.total_leaves {
+            compute(ctx, begin + half, half)?
+        } else {
+            left.clone()
+        };
+
+        Some(ctx.algorithm.hash_nodes(&left, &right))
+    }
+
+    let mut ctx = Ctx {
+        start,
+        end: start + leaf_hashes.len(),
+        total_leaves,
+        leaf_hashes,
+        boundary: boundary.iter(),
+        algorithm,
+    };
+    let computed = compute(&mut ctx, 0, slot_width(total_leaves)?)?;
+
+    // Every boundary hash must be consumed
+    ctx.boundary.next().is_none().then_some(computed)
+}
+
+/// Proof that a block of consecutive leaves is in the tree: the block's own root
+/// (of a tree over just its leaves) plus a range proof of its leaf hashes, so one
+/// proof covers the whole block
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockProof {
+    /// Index of the block's first leaf
+    pub block_start: usize,
+    /// Total number of leaves in the tree
+    pub total_leaves: usize,
+    /// Leaf hashes of the block, in order
+    pub leaf_hashes: Vec<Vec<u8>>,
+    /// Root of a tree built over the block's leaves alone
+    pub block_root: Vec<u8>,
+    /// Hashes of the subtrees outside the block, left to right
+    pub boundary: Vec<Vec<u8>>,
+    /// The Merkle root
+    pub root: Vec<u8>,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_sha256")]
+    pub algorithm: HashAlgorithm,
+}
+
+impl BlockProof {
+    /// Number of leaves in the block
+    pub fn block_len(&self) -> usize {
+        self.leaf_hashes.len()
+    }
+
+    /// Root of a tree over just `leaf_hashes`, as `block_root` records it
+    pub fn compute_block_root(leaf_hashes: &[Vec<u8>], algorithm: HashAlgorithm) -> Option<Vec<u8>> {
+        range_root(0, leaf_hashes.len(), leaf_hashes, &[], algorithm)
+    }
+
+    /// Check the leaf hashes reproduce `block_root` and, with the boundary, `root`
+    pub fn verify(&self) -> bool {
+        let hash_len = self.algorithm.output_len();
+        let well_formed = !self.leaf_hashes.is_empty()
+            && self.block_start.checked_add(self.block_len()).is_some_and(|end| end <= self.total_leaves)
+            && self.root.len() == hash_len
+            && self.leaf_hashes.iter().chain(&self.boundary).all(|hash| hash.len() == hash_len);
+        if !well_formed {
+            return false;
+        }
+
+        Self::compute_block_root(&self.leaf_hashes, self.algorithm).is_some_and(|root| root == self.block_root)
+            && range_root(self.block_start, self.total_leaves, &self.leaf_hashes, &self.boundary, self.algorithm)
+                .is_some_and(|root| root == self.root)
+    }
+}
+
+/// An RFC 6962 inclusion proof, possibly generated by another CT log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CtInclusionProof {
+    pub leaf_index: usize,
+    pub tree_size: usize,
+    /// Sibling hashes from the leaf up to the root
+    pub audit_path: Vec<Vec<u8>>,
+    /// SHA-256(0x00 || leaf data)
+    pub leaf_hash: Vec<u8>,
+    /// Merkle Tree Hash the path should reach
+    pub root_hash: Vec<u8>,
+}
+
+impl CtInclusionProof {
+    /// Verify under CT hashing rules; malformed hashes and over-long paths fail
+    pub fn verify(&self) -> bool {
+        let well_formed = self.audit_path.len() <= MAX_PROOF_PATH_LEN
+            && self.leaf_hash.len() == HASH_LEN
+            && self.root_hash.len() == HASH_LEN
+            && self.audit_path.iter().all(|hash| hash.len() == HASH_LEN);
+
+        well_formed
+            && ct::verify_inclusion(
+                &self.leaf_hash,
+                self.leaf_index,
+                self.tree_size,
+                &self.audit_path,
+                &self.root_hash,
+            )
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::merkle::MerkleTree;
+    use crate::crypto::{hash::sha256, Hasher, Sha256Hasher};
+    use crate::models::merkle::{MerkleTree, SingleLeafMode};
+
+    /// Leaf 0 of the eight-leaf RFC 6962 reference tree
+    fn ct_vector() -> CtInclusionProof {
+        let hash = |s: &str| hex::decode(s).unwrap();
+        CtInclusionProof {
+            leaf_index: 0,
+            tree_size: 8,
+            audit_path: vec![
+                hash("96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7"),
+                hash("5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e"),
+                hash("6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4"),
+            ],
+            leaf_hash: ct::leaf_hash(b""),
+            root_hash: hash("5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328"),
+        }
+    }
+
+    #[test]
+    fn test_ct_inclusion_proof_vector() {
+        assert!(ct_vector().verify());
+
+        let mut tampered = ct_vector();
+        tampered.audit_path[1][0] ^= 0x01;
+        assert!(!tampered.verify());
+
+        let wrong_index = CtInclusionProof { leaf_index: 1, ..ct_vector() };
+        assert!(!wrong_index.verify());
+        let wrong_size = CtInclusionProof { tree_size: 9, ..ct_vector() };
+        assert!(!wrong_size.verify());
+
+        let mut truncated = ct_vector();
+        truncated.root_hash.pop();
+        assert!(!truncated.verify());
+    }
 
     #[test]
     fn test_proof_generation_and_verification() {
@@ -128,6 +1087,75 @@ mod tests {
         assert!(proof.verify());
     }
 
+    #[test]
+    fn test_position_matches_index() {
+        for leaf_count in [1, 2, 3, 5, 8, 13] {
+            let tree = MerkleTree::from_leaves((0..leaf_count).map(|i| MerkleNode::new_leaf(&[i])).collect());
+            let root = tree.root().unwrap();
+            for index in 0..leaf_count as usize {
+                let proof = MerkleProof::new(
+                    index,
+                    vec![index as u8],
+                    generate_proof(root, index, leaf_count as usize),
+                    root.hash.clone(),
+                );
+                assert_eq!(proof.is_left_sequence().len(), proof.proof.len());
+                assert_eq!(proof.position(), index, "leaf {} of {}", index, leaf_count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_with_custom_hasher() {
+        use std::cell::Cell;
+
+        // "Hash" leaves by inverting their bits and pairs by XOR-ing them
+        let calls = Cell::new(0);
+        let hash_leaf = |value: &[u8]| value.iter().map(|b| !b).collect::<Vec<u8>>();
+        let hash_pair = |left: &[u8], right: &[u8]| {
+            calls.set(calls.get() + 1);
+            left.iter().zip(right).map(|(l, r)| l ^ r).collect::<Vec<u8>>()
+        };
+
+        let path = vec![
+            ProofElement { hash: vec![0x0f, 0x00], is_left: true },
+            ProofElement { hash: vec![0x00, 0xf0], is_left: false },
+        ];
+        let proof = MerkleProof::new(1, vec![0xff, 0xff], path, vec![0x0f, 0xf0]);
+        assert!(proof.verify_with(hash_leaf, hash_pair));
+        assert_eq!(calls.get(), 2);
+        assert!(!proof.verify());
+
+        let wrong_root = MerkleProof {
+            root: vec![0x00, 0x00],
+            ..proof
+        };
+        assert!(!wrong_root.verify_with(hash_leaf, hash_pair));
+    }
+
+    #[test]
+    fn test_compute_root() {
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data0"),
+            MerkleNode::new_leaf(b"data1"),
+            MerkleNode::new_leaf(b"data2"),
+        ]);
+        let root = tree.root().unwrap();
+
+        let proof = MerkleProof::new(
+            1,
+            b"data1".to_vec(),
+            generate_proof(root, 1, 3),
+            root.hash.clone(),
+        );
+        assert_eq!(proof.compute_root(), root.hash);
+
+        // A wrong value still computes a root, just not the expected one
+        let wrong = MerkleProof::new(1, b"wrong".to_vec(), proof.proof.clone(), root.hash.clone());
+        assert_ne!(wrong.compute_root(), root.hash);
+        assert_eq!(wrong.compute_root().len(), HASH_LEN);
+    }
+
     #[test]
     fn test_invalid_proof() {
         let leaves = vec![
@@ -184,4 +1212,624 @@ mod tests {
         );
         assert!(proof2.verify());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_chained_proof_two_levels() {
+        // Sub-tree over raw values
+        let sub_tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"sub0"),
+            MerkleNode::new_leaf(b"sub1"),
+            MerkleNode::new_leaf(b"sub2"),
+        ]);
+        let sub_root = sub_tree.root().unwrap();
+        let sub_proof = MerkleProof::new(
+            1,
+            b"sub1".to_vec(),
+            generate_proof(sub_root, 1, 3),
+            sub_root.hash.clone(),
+        );
+        assert!(sub_proof.verify());
+
+        // Parent tree commits the sub-tree root as its second leaf
+        let parent_tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"other"),
+            MerkleNode::new_leaf(&sub_root.hash),
+        ]);
+        let parent_root = parent_tree.root().unwrap();
+        let parent_proof = MerkleProof::new(
+            1,
+            sub_root.hash.clone(),
+            generate_proof(parent_root, 1, 2),
+            parent_root.hash.clone(),
+        );
+        assert!(parent_proof.verify());
+
+        let chained = sub_proof.clone().chain(parent_proof).unwrap();
+        assert_eq!(chained.root, parent_root.hash);
+        assert_eq!(chained.value, b"sub1".to_vec());
+        assert!(chained.verify());
+
+        // A proof whose value isn't the sub-tree root can't be chained
+        let unrelated = MerkleProof::new(
+            0,
+            b"other".to_vec(),
+            generate_proof(parent_root, 0, 2),
+            parent_root.hash.clone(),
+        );
+        assert!(sub_proof.clone().chain(unrelated).is_err());
+
+        // Tampering with the sub-leaf breaks the chained proof
+        let mut tampered = chained.clone();
+        tampered.value = b"forged".to_vec();
+        assert!(!tampered.verify());
+    }
+
+    #[test]
+    fn test_verify_rejects_malformed_hash_lengths() {
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data0"),
+            MerkleNode::new_leaf(b"data1"),
+        ]);
+        let root = tree.root().unwrap();
+        let valid = MerkleProof::new(
+            0,
+            b"data0".to_vec(),
+            generate_proof(root, 0, 2),
+            root.hash.clone(),
+        );
+        assert!(valid.verify());
+
+        let mut short_root = valid.clone();
+        short_root.root.truncate(16);
+        assert!(!short_root.verify());
+
+        let mut long_sibling = valid.clone();
+        long_sibling.proof[0].hash.push(0);
+        assert!(!long_sibling.verify());
+
+        let mut empty = valid;
+        empty.root.clear();
+        empty.proof.clear();
+        assert!(!empty.verify());
+    }
+
+    #[test]
+    fn test_single_leaf_proof_in_both_modes() {
+        for mode in [SingleLeafMode::LeafIsRoot, SingleLeafMode::DuplicateToParent] {
+            let tree = MerkleTree::from_leaves_with_mode(vec![MerkleNode::new_leaf(b"only")], mode);
+            let root = tree.root().unwrap();
+            let path = generate_proof(root, 0, 1);
+            assert_eq!(path.len(), usize::from(mode == SingleLeafMode::DuplicateToParent));
+
+            let proof = MerkleProof::new(0, b"only".to_vec(), path, root.hash.clone());
+            assert!(proof.verify(), "{:?}", mode);
+
+            let mut wrong = proof.clone();
+            wrong.value = b"other".to_vec();
+            assert!(!wrong.verify(), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_proof_equality() {
+        let leaves = || (0..5).map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes())).collect();
+        let (first, second) = (MerkleTree::from_leaves(leaves()), MerkleTree::from_leaves(leaves()));
+        let proof_for = |tree: &MerkleTree, index: usize| {
+            let root = tree.root().unwrap();
+            MerkleProof::new(
+                index,
+                format!("data{}", index).into_bytes(),
+                generate_proof(root, index, 5),
+                root.hash.clone(),
+            )
+        };
+
+        assert_eq!(proof_for(&first, 3), proof_for(&second, 3));
+        assert_ne!(proof_for(&first, 3), proof_for(&second, 2));
+    }
+
+    #[test]
+    fn test_pruned_proof_recomputes_missing_siblings() {
+        for count in [2, 5, 7, 8] {
+            let leaves: Vec<MerkleNode> = (0..count)
+                .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+                .collect();
+            let leaf_hashes: Vec<Vec<u8>> = leaves.iter().map(|l| l.hash.clone()).collect();
+            let tree = MerkleTree::from_leaves(leaves);
+            let full = tree.root().unwrap();
+
+            // Drop the left subtree's right child and everything under the root's right
+            let mut pruned = full.clone();
+            if let Some(left) = pruned.left.as_deref_mut() {
+                left.right = None;
+            }
+            if let Some(right) = pruned.right.as_deref_mut() {
+                right.left = None;
+                right.right = None;
+            }
+
+            for index in 0..count {
+                let path = generate_pruned_proof(&pruned, index, count, &leaf_hashes, HashAlgorithm::Sha256).unwrap();
+                assert_eq!(path, generate_proof(full, index, count), "{} of {}", index, count);
+                let proof = MerkleProof::new(index, format!("data{}", index).into_bytes(), path, full.hash.clone());
+                assert!(proof.verify());
+            }
+        }
+
+        // Without the leaves a dropped sibling can't be recomputed
+        let leaves: Vec<MerkleNode> = (0..4).map(|i| MerkleNode::new_leaf(&[i])).collect();
+        let mut pruned = MerkleTree::from_leaves(leaves).root().unwrap().clone();
+        pruned.right = None;
+        assert_eq!(generate_pruned_proof(&pruned, 0, 4, &[], HashAlgorithm::Sha256), None);
+
+        // Missing siblings are rehashed with the tree's own algorithm
+        let leaves: Vec<MerkleNode> = (0..5).map(|i| MerkleNode::new_leaf_using::<Blake3Hasher>(&[i])).collect();
+        let leaf_hashes: Vec<Vec<u8>> = leaves.iter().map(|l| l.hash.clone()).collect();
+        let tree = MerkleTree::from_leaves_using::<Blake3Hasher>(leaves, SingleLeafMode::default());
+        let full = tree.root().unwrap();
+        let mut pruned = full.clone();
+        pruned.right = None;
+        let path = generate_pruned_proof(&pruned, 0, 5, &leaf_hashes, HashAlgorithm::Blake3).unwrap();
+        assert_eq!(path, generate_proof(full, 0, 5));
+    }
+
+    #[test]
+    fn test_verify_against_roots() {
+        let leaves: Vec<MerkleNode> = (0..4).map(|i| MerkleNode::new_leaf(&[i])).collect();
+        let tree = MerkleTree::from_leaves(leaves);
+        let root = tree.root().unwrap();
+        let proof = MerkleProof::new(2, vec![2], generate_proof(root, 2, 4), root.hash.clone());
+        let other = vec![0xab; 32];
+
+        assert!(proof.verify_against_roots(&[other.clone(), root.hash.clone()]));
+        assert!(!proof.verify_against_roots(&[other]));
+        assert!(!proof.verify_against_roots(&[]));
+
+        // A trusted root doesn't rescue a proof that doesn't reach it
+        let tampered = MerkleProof { value: vec![9], ..proof };
+        assert!(!tampered.verify_against_roots(std::slice::from_ref(&root.hash)));
+    }
+
+    #[test]
+    fn test_compact_json_round_trip() {
+        let leaves: Vec<MerkleNode> = (0..5)
+            .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+            .collect();
+        let tree = MerkleTree::from_leaves(leaves);
+        let root = tree.root().unwrap();
+        let proof = MerkleProof::new(3, b"data3".to_vec(), generate_proof(root, 3, 5), root.hash.clone());
+
+        let json = proof.to_json_compact();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["index"], 3);
+        assert_eq!(value["value_hex"], hex::encode(b"data3"));
+        assert_eq!(value["root_hex"], hex::encode(&root.hash));
+        assert_eq!(value["path"][0]["hash_hex"], hex::encode(&proof.proof[0].hash));
+        assert_eq!(value["path"][0]["is_left"], proof.proof[0].is_left);
+
+        let parsed = MerkleProof::from_json_compact(&json).unwrap();
+        assert_eq!(parsed, proof);
+        assert!(parsed.verify());
+
+        let bad = json.replace(&hex::encode(&root.hash), "zz");
+        assert!(matches!(MerkleProof::from_json_compact(&bad), Err(AppError::InvalidInput(_))));
+        assert!(MerkleProof::from_json_compact("{}").is_err());
+    }
+
+    #[test]
+    fn test_prehashed_proof() {
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data0"),
+            MerkleNode::new_leaf(b"data1"),
+        ]);
+        let root = tree.root().unwrap();
+        let leaf_hash = MerkleNode::new_leaf(b"data1").hash;
+        let proof = MerkleProof::new(1, leaf_hash.clone(), generate_proof(root, 1, 2), root.hash.clone())
+            .with_prehashed(true);
+        assert!(proof.verify());
+
+        // The flag round-trips, and the same value read as raw data doesn't verify
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<MerkleProof>(&json).unwrap(), proof);
+        assert!(!proof.clone().with_prehashed(false).verify());
+
+        // A prehashed value must be a full hash
+        let mut short = proof;
+        short.value.truncate(HASH_LEN - 1);
+        assert!(!short.verify());
+    }
+
+    #[test]
+    fn test_verify_rejects_over_length_path() {
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data0"),
+            MerkleNode::new_leaf(b"data1"),
+        ]);
+        let root = tree.root().unwrap();
+        let valid = MerkleProof::new(
+            0,
+            b"data0".to_vec(),
+            generate_proof(root, 0, 2),
+            root.hash.clone(),
+        );
+        assert!(valid.verify_with_max_path(1));
+        assert!(!valid.verify_with_max_path(0));
+
+        // A million elements is rejected without hashing any of them
+        let mut huge = valid;
+        huge.proof = vec![
+            ProofElement {
+                hash: vec![0; HASH_LEN],
+                is_left: false,
+            };
+            1_000_000
+        ];
+        let started = std::time::Instant::now();
+        assert!(!huge.verify());
+        assert!(started.elapsed() < std::time::Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_proof_all_indices_across_leaf_counts() {
+        for count in 1..=16usize {
+            let values: Vec<Vec<u8>> = (0..count).map(|i| format!("v{}", i).into_bytes()).collect();
+            let tree = MerkleTree::from_leaves(values.iter().map(|v| MerkleNode::new_leaf(v)).collect());
+            let root = tree.root().unwrap();
+
+            for (index, value) in values.iter().enumerate() {
+                let proof = MerkleProof::new(
+                    index,
+                    value.clone(),
+                    generate_proof(root, index, count),
+                    root.hash.clone(),
+                );
+                assert!(proof.verify(), "count={} index={}", count, index);
+            }
+        }
+    }
+
+    fn range_proof_for(count: usize, start: usize, end: usize) -> RangeProof {
+        let values: Vec<Vec<u8>> = (0..count).map(|i| format!("v{}", i).into_bytes()).collect();
+        let tree = MerkleTree::from_leaves(values.iter().map(|v| MerkleNode::new_leaf(v)).collect());
+        let root = tree.root().unwrap();
+        RangeProof::new(
+            start,
+            count,
+            values[start..end].to_vec(),
+            generate_range_proof(root, start, end, count),
+            root.hash.clone(),
+        )
+    }
+
+    #[test]
+    fn test_range_proof_aligned() {
+        let proof = range_proof_for(8, 4, 8);
+        // The whole right half is covered, only the left half's hash is needed
+        assert_eq!(proof.boundary.len(), 1);
+        assert!(verify_range_proof(&proof));
+
+        let proof = range_proof_for(8, 0, 8);
+        assert!(proof.boundary.is_empty());
+        assert!(verify_range_proof(&proof));
+    }
+
+    #[test]
+    fn test_range_proof_unaligned() {
+        for count in 1..=11usize {
+            for start in 0..count {
+                for end in start + 1..=count {
+                    let proof = range_proof_for(count, start, end);
+                    assert!(
+                        verify_range_proof(&proof),
+                        "count={} range={}..{}",
+                        count,
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_proof_tampered() {
+        let proof = range_proof_for(7, 1, 6);
+
+        let mut wrong_value = proof.clone();
+        wrong_value.values[2] = b"forged".to_vec();
+        assert!(!verify_range_proof(&wrong_value));
+
+        let mut shifted = proof.clone();
+        shifted.start = 2;
+        assert!(!verify_range_proof(&shifted));
+
+        let mut extra_boundary = proof.clone();
+        extra_boundary.boundary.push(vec![0; HASH_LEN]);
+        assert!(!verify_range_proof(&extra_boundary));
+
+        let mut overflowing = proof.clone();
+        overflowing.start = usize::MAX;
+        overflowing.total_leaves = usize::MAX;
+        assert_eq!(overflowing.end(), None);
+        assert!(!verify_range_proof(&overflowing));
+
+        let mut empty = proof;
+        empty.values.clear();
+        assert!(!verify_range_proof(&empty));
+    }
+
+    #[test]
+    fn test_proof_version() {
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data0"),
+            MerkleNode::new_leaf(b"data1"),
+        ]);
+        let root = tree.root().unwrap();
+        let proof = MerkleProof::new(
+            0,
+            b"data0".to_vec(),
+            generate_proof(root, 0, 2),
+            root.hash.clone(),
+        );
+        // Defaults for every field version 2 added: still written as version 1
+        assert_eq!(proof.version, 1);
+
+        // Version-less JSON from older clients is treated as version 1
+        let mut json = serde_json::to_value(&proof).unwrap();
+        json.as_object_mut().unwrap().remove("version");
+        let legacy: MerkleProof = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(legacy.version, 1);
+        assert!(legacy.verify());
+
+        // Setting any of them bumps the version, and a version 1 proof using one is refused
+        let indexed = MerkleProof::new(0, b"data0".to_vec(), Vec::new(), LeafHashMode::IndexBound.hash(0, b"data0"))
+            .with_leaf_mode(LeafHashMode::IndexBound);
+        assert_eq!(indexed.version, PROOF_VERSION);
+        assert!(indexed.verify());
+        assert!(!MerkleProof { version: 1, ..indexed.clone() }.verify());
+        let null = MerkleProof::new(0, Vec::new(), Vec::new(), EMPTY_LEAF_HASH.to_vec()).with_placeholder(true);
+        assert_eq!(null.version, PROOF_VERSION);
+        assert!(!MerkleProof { version: 1, ..null }.verify());
+
+        // Unknown versions are rejected when parsing
+        json["version"] = 3.into();
+        let err = serde_json::from_value::<MerkleProof>(json).unwrap_err();
+        assert!(err.to_string().contains("unsupported proof version 3"));
+
+        // ...and by verify for proofs built in code
+        let mut future = proof;
+        future.version = 3;
+        assert!(!future.verify());
+    }
+
+    #[test]
+    fn test_extend_proof_from_four_to_eight() {
+        let values: Vec<Vec<u8>> = (0..8).map(|i| format!("v{}", i).into_bytes()).collect();
+        let leaves = |n: usize| values[..n].iter().map(|v| MerkleNode::new_leaf(v)).collect();
+        let old_tree = MerkleTree::from_leaves(leaves(4));
+        let new_tree = MerkleTree::from_leaves(leaves(8));
+        let (old_root, new_root) = (old_tree.root().unwrap(), new_tree.root().unwrap());
+
+        let old_proof = MerkleProof::new(
+            1,
+            values[1].clone(),
+            generate_proof(old_root, 1, 4),
+            old_root.hash.clone(),
+        );
+        assert!(old_proof.verify());
+
+        let extension = generate_proof_extension(old_root, 4, new_root, 8, 1);
+        assert_eq!(extension.retained, 2);
+        assert_eq!(extension.siblings.len(), 1);
+
+        let extended = extension.apply(&old_proof);
+        assert_eq!(extended.root, new_root.hash);
+        assert!(extended.verify());
+    }
+
+    #[test]
+    fn test_extend_placeholder_proof() {
+        let leaf = |i: usize| match i {
+            1 => MerkleNode::empty_leaf(),
+            _ => MerkleNode::new_leaf(format!("v{}", i).as_bytes()),
+        };
+        let old_tree = MerkleTree::from_leaves((0..3).map(leaf).collect());
+        let new_tree = MerkleTree::from_leaves((0..6).map(leaf).collect());
+        let (old_root, new_root) = (old_tree.root().unwrap(), new_tree.root().unwrap());
+
+        let old_proof = MerkleProof::new(1, Vec::new(), generate_proof(old_root, 1, 3), old_root.hash.clone())
+            .with_placeholder(true);
+        assert!(old_proof.verify());
+
+        let extended = generate_proof_extension(old_root, 3, new_root, 6, 1).apply(&old_proof);
+        assert!(extended.placeholder);
+        assert_eq!(extended.root, new_root.hash);
+        assert!(extended.verify());
+    }
+
+    #[test]
+    fn test_extend_proof_unaligned_sizes() {
+        let values: Vec<Vec<u8>> = (0..13).map(|i| format!("v{}", i).into_bytes()).collect();
+        let leaves = |n: usize| values[..n].iter().map(|v| MerkleNode::new_leaf(v)).collect();
+
+        for old_size in 1..13 {
+            for new_size in old_size..=13 {
+                let old_tree = MerkleTree::from_leaves(leaves(old_size));
+                let new_tree = MerkleTree::from_leaves(leaves(new_size));
+                let (old_root, new_root) = (old_tree.root().unwrap(), new_tree.root().unwrap());
+
+                for (index, value) in values.iter().enumerate().take(old_size) {
+                    let old_proof = MerkleProof::new(
+                        index,
+                        value.clone(),
+                        generate_proof(old_root, index, old_size),
+                        old_root.hash.clone(),
+                    );
+                    let extension =
+                        generate_proof_extension(old_root, old_size, new_root, new_size, index);
+                    assert!(extension.apply(&old_proof).verify());
+                }
+            }
+        }
+    }
+
+    fn proofs_round_trip<H: Hasher>(values: &[&[u8]]) -> Vec<u8> {
+        let leaves = values.iter().map(|v| MerkleNode::new_leaf_using::<H>(v)).collect();
+        let tree = MerkleTree::from_leaves_using::<H>(leaves, SingleLeafMode::default());
+        assert_eq!(tree.algorithm(), H::ALGORITHM);
+        let root = tree.root().unwrap();
+        assert!(root.verify_subtree_using::<H>());
+
+        for (index, value) in values.iter().enumerate() {
+            let path = generate_proof(root, index, values.len());
+            let proof = MerkleProof::new(index, value.to_vec(), path, root.hash.clone())
+                .with_algorithm(H::ALGORITHM);
+            assert!(proof.verify(), "{} {}", H::ALGORITHM, index);
+
+            // The same path tagged with another algorithm doesn't verify
+            for other in [HashAlgorithm::Sha256, HashAlgorithm::Blake3, HashAlgorithm::Sha256Rfc6962] {
+                let tagged = proof.clone().with_algorithm(other);
+                assert_eq!(tagged.verify(), other == H::ALGORITHM, "{} as {}", H::ALGORITHM, other);
+            }
+        }
+        root.hash.clone()
+    }
+
+    #[test]
+    fn test_trees_under_each_hasher() {
+        let values: [&[u8]; 5] = [b"a", b"b", b"c", b"d", b"e"];
+        let sha256_root = proofs_round_trip::<Sha256Hasher>(&values);
+        let blake3_root = proofs_round_trip::<Blake3Hasher>(&values);
+        let rfc6962_root = proofs_round_trip::<Rfc6962Hasher>(&values);
+        assert_ne!(sha256_root, blake3_root);
+        assert_ne!(sha256_root, rfc6962_root);
+
+        // The default constructors are the SHA-256 ones
+        let tree = MerkleTree::from_leaves(values.iter().map(|v| MerkleNode::new_leaf(v)).collect());
+        assert_eq!(tree.root_hash().unwrap(), sha256_root);
+
+        // BLAKE3 trees keep the same shape, duplicating the last node of odd levels
+        let (leaf, pair) = (Blake3Hasher::hash_leaf, Blake3Hasher::hash_nodes);
+        let ab = pair(&leaf(b"a"), &leaf(b"b"));
+        let cd = pair(&leaf(b"c"), &leaf(b"d"));
+        let ee = pair(&leaf(b"e"), &leaf(b"e"));
+        assert_eq!(blake3_root, pair(&pair(&ab, &cd), &pair(&ee, &ee)));
+    }
+
+    #[test]
+    fn test_rfc6962_prefixes_stop_interior_nodes_posing_as_leaves() {
+        let values: [&[u8]; 4] = [b"a", b"b", b"c", b"d"];
+
+        // Forge a leaf whose value is the preimage of the interior node over a and b:
+        // one level up the tree, it hashes to that node and reaches the real root
+        let forge = |leaf: &dyn Fn(&[u8]) -> Vec<u8>, root: &MerkleNode, algorithm| {
+            let preimage = [leaf(b"a"), leaf(b"b")].concat();
+            let cd = root.right.as_deref().unwrap();
+            let path = vec![ProofElement { hash: cd.hash.clone(), is_left: false }];
+            MerkleProof::new(0, preimage, path, root.hash.clone()).with_algorithm(algorithm)
+        };
+
+        let plain = MerkleTree::from_leaves(values.iter().map(|v| MerkleNode::new_leaf(v)).collect());
+        let forged = forge(&|v| sha256(&[v]), plain.root().unwrap(), HashAlgorithm::Sha256);
+        assert!(forged.verify());
+
+        let prefixed = MerkleTree::from_leaves_rfc6962(&values);
+        assert_eq!(prefixed.algorithm(), HashAlgorithm::Sha256Rfc6962);
+        let forged = forge(&ct::leaf_hash, prefixed.root().unwrap(), HashAlgorithm::Sha256Rfc6962);
+        assert!(!forged.verify());
+
+        // Honest proofs against the prefixed tree still verify
+        let root = prefixed.root().unwrap();
+        let proof = MerkleProof::new(2, b"c".to_vec(), generate_proof(root, 2, 4), root.hash.clone())
+            .with_algorithm(HashAlgorithm::Sha256Rfc6962);
+        assert!(proof.verify());
+    }
+
+    fn tree_of(leaf_count: usize) -> MerkleTree {
+        MerkleTree::from_leaves((0..leaf_count).map(|i| MerkleNode::new_leaf(&[i as u8])).collect())
+    }
+
+    fn size_range(tree: &MerkleTree, index: usize) -> (usize, usize) {
+        let root = tree.root().unwrap();
+        let path = generate_proof(root, index, tree.leaf_count());
+        MerkleProof::new(index, vec![index as u8], path, root.hash.clone()).implied_tree_size_range()
+    }
+
+    #[test]
+    fn test_implied_tree_size_range() {
+        // Power-of-two trees have no padding; real right siblings raise the floor
+        let eight = tree_of(8);
+        assert_eq!(size_range(&eight, 0), (5, 8));
+        assert_eq!(size_range(&eight, 4), (7, 8));
+        assert_eq!(size_range(&eight, 7), (8, 8));
+        assert_eq!(size_range(&tree_of(1), 0), (1, 1));
+        assert_eq!(size_range(&tree_of(2), 0), (2, 2));
+
+        // Paths along the padded right edge pin the size down
+        let five = tree_of(5);
+        assert_eq!(size_range(&five, 0), (5, 8));
+        assert_eq!(size_range(&five, 4), (5, 5));
+        assert_eq!(size_range(&tree_of(6), 5), (6, 6));
+        assert_eq!(size_range(&tree_of(6), 4), (6, 6));
+        assert_eq!(size_range(&tree_of(7), 3), (5, 8));
+
+        for leaf_count in 1..=17 {
+            let tree = tree_of(leaf_count);
+            for index in 0..leaf_count {
+                let (min, max) = size_range(&tree, index);
+                assert!((min..=max).contains(&leaf_count), "{} of {}: {}..={}", index, leaf_count, min, max);
+            }
+        }
+
+        // Sides that disagree with the index fit no tree
+        let root = five.root().unwrap();
+        let proof = MerkleProof::new(1, vec![1], generate_proof(root, 0, 5), root.hash.clone());
+        assert_eq!(proof.implied_tree_size_range(), (0, 0));
+    }
+
+    #[test]
+    fn test_consistency_proof() {
+        let leaves: Vec<_> = (0..11).map(|i| MerkleNode::new_leaf(format!("leaf{}", i).as_bytes())).collect();
+        let tree = |size: usize| MerkleTree::from_leaves(leaves[..size].to_vec());
+
+        for new_size in 1..=leaves.len() {
+            let new_tree = tree(new_size);
+            let new_root = new_tree.root_hash().unwrap();
+            for old_size in 1..=new_size {
+                let old_root = tree(old_size).root_hash().unwrap();
+                let path = generate_consistency_proof(new_tree.root().unwrap(), old_size, new_size);
+                let proof = ConsistencyProof::new(old_size, new_size, path);
+                assert!(proof.verify(&old_root, &new_root), "{} -> {}", old_size, new_size);
+                if old_size < new_size {
+                    assert!(!proof.verify(&new_root, &old_root), "{} -> {}", old_size, new_size);
+                }
+            }
+        }
+
+        // Rewriting a leaf the old tree covered breaks consistency with its root
+        let old_root = tree(6).root_hash().unwrap();
+        let mut rewritten = leaves.clone();
+        rewritten[3] = MerkleNode::new_leaf(b"rewritten");
+        let rewritten = MerkleTree::from_leaves(rewritten);
+        let path = generate_consistency_proof(rewritten.root().unwrap(), 6, 11);
+        let proof = ConsistencyProof::new(6, 11, path);
+        assert!(!proof.verify(&old_root, &rewritten.root_hash().unwrap()));
+
+        // An honest proof can't be bent to other sizes or sides
+        let new_tree = tree(11);
+        let new_root = new_tree.root_hash().unwrap();
+        let honest = ConsistencyProof::new(6, 11, generate_consistency_proof(new_tree.root().unwrap(), 6, 11));
+        assert!(honest.verify(&old_root, &new_root));
+        assert!(!ConsistencyProof { old_size: 5, ..honest.clone() }.verify(&old_root, &new_root));
+        let mut flipped = honest.clone();
+        flipped.proof.last_mut().unwrap().is_left ^= true;
+        assert!(!flipped.verify(&old_root, &new_root));
+        let mut truncated = honest.clone();
+        truncated.proof.pop();
+        assert!(!truncated.verify(&old_root, &new_root));
+
+        // Sizes past the largest power of two fail instead of overflowing
+        let oversized = ConsistencyProof { new_size: usize::MAX, ..honest };
+        assert!(!oversized.verify(&old_root, &new_root));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::error::AppError;
+use base64::Engine;
+use std::str::FromStr;
+
+/// Text encoding for hashes in responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base58,
+    Base64,
+    /// Multibase, using the base58btc (`z`) prefix
+    Multibase,
+}
+
+impl Encoding {
+    /// Encode bytes as text
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::Base58 => bs58::encode(bytes).into_string(),
+            Encoding::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+            Encoding::Multibase => format!("z{}", bs58::encode(bytes).into_string()),
+        }
+    }
+
+    /// Decode text produced by `encode`
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, AppError> {
+        let decoded = match self {
+            Encoding::Hex => hex::decode(text).map_err(|e| e.to_string()),
+            Encoding::Base58 => bs58::decode(text).into_vec().map_err(|e| e.to_string()),
+            Encoding::Base64 => base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|e| e.to_string()),
+            Encoding::Multibase => match text.strip_prefix('z') {
+                Some(digits) => bs58::decode(digits).into_vec().map_err(|e| e.to_string()),
+                None => Err("missing 'z' prefix".to_string()),
+            },
+        };
+        decoded.map_err(|e| AppError::InvalidInput(format!("Invalid {:?} value: {}", self, e)))
+    }
+
+    /// Parse an optional query value, defaulting to hex
+    pub fn from_query(value: Option<&str>) -> Result<Self, AppError> {
+        value.map_or(Ok(Encoding::default()), str::parse)
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Encoding::Hex),
+            "base58" => Ok(Encoding::Base58),
+            "base64" => Ok(Encoding::Base64),
+            "multibase" => Ok(Encoding::Multibase),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown encoding '{}' (expected hex, base58, base64 or multibase)",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings() {
+        let bytes = [0x00, 0x01, 0xfe, 0xff];
+
+        assert_eq!(Encoding::Hex.encode(&bytes), "0001feff");
+        assert_eq!(Encoding::Base64.encode(&bytes), "AAH+/w==");
+        assert_eq!(Encoding::Base58.encode(&bytes), "1ftS");
+        assert_eq!(Encoding::Multibase.encode(&bytes), "z1ftS");
+
+        let root = [0xab; 32];
+        let base58 = Encoding::Base58.encode(&root);
+        assert_eq!(bs58::decode(&base58).into_vec().unwrap(), root.to_vec());
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let bytes = [0x00, 0x01, 0xfe, 0xff];
+        for encoding in [Encoding::Hex, Encoding::Base58, Encoding::Base64, Encoding::Multibase] {
+            assert_eq!(encoding.decode(&encoding.encode(&bytes)).unwrap(), bytes.to_vec());
+        }
+        assert!(matches!(Encoding::Base64.decode("not base64!"), Err(AppError::InvalidInput(_))));
+        assert!(Encoding::Multibase.decode("1ftS").is_err());
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!(Encoding::from_query(None).unwrap(), Encoding::Hex);
+        assert_eq!(Encoding::from_query(Some("base58")).unwrap(), Encoding::Base58);
+        assert_eq!(Encoding::from_query(Some("multibase")).unwrap(), Encoding::Multibase);
+
+        let result = Encoding::from_query(Some("base32"));
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod encoding;
+
 pub mod request;
 
 pub mod response;
\ No newline at end of file
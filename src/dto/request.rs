@@ -1,21 +1,402 @@
-use serde::Deserialize;
+use crate::crypto::proof::{CtInclusionProof, MerkleProof};
+use crate::dto::encoding::Encoding;
+use crate::error::AppError;
+use crate::models::commitment::{unix_now, CommitmentMetadata};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Largest value a client may commit when no other limit is configured
+pub const DEFAULT_MAX_VALUE_SIZE: usize = 1_000_000;
+
+/// Limits a commitment request is checked against, resolved per request from the
+/// API config and the caller's auth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationContext {
+    /// Largest value accepted, in bytes
+    pub max_value_size: usize,
+    /// Exact length every value must have, for fixed-size record logs
+    pub record_size: Option<usize>,
+}
+
+impl Default for ValidationContext {
+    fn default() -> Self {
+        Self {
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            record_size: None,
+        }
+    }
+}
 
 /// Request to add a new commitment
 #[derive(Debug, Deserialize)]
 pub struct AddCommitmentRequest {
-    /// The data to commit (as byte array)
+    /// The data to commit, as a byte array or a hex string
+    #[serde(deserialize_with = "deserialize_bytes_or_hex")]
     pub value: Vec<u8>,
+    /// MIME type to serve the raw value with (does not affect the leaf hash)
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Seconds until the commitment expires and is swept from the tree
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
 }
 
 impl AddCommitmentRequest {
-    pub fn validate(&self) -> Result<(), String> {
-        if self.value.is_empty() {
-            return Err("Value cannot be empty".to_string());
+    /// Check the value and content type against the limits in `context`
+    pub fn validate(&self, context: &ValidationContext) -> Result<(), String> {
+        validate_value(&self.value, context)?;
+        if let Some(content_type) = &self.content_type {
+            let printable = content_type.bytes().all(|b| b.is_ascii_graphic() || b == b' ');
+            if content_type.is_empty() || content_type.len() > 255 || !printable {
+                return Err("Invalid content type".to_string());
+            }
         }
-        if self.value.len() > 1_000_000 {
-            // 1MB limit
-            return Err("Value too large (max 1MB)".to_string());
+        Ok(())
+    }
+
+    /// Content type and expiry to store with the value
+    pub fn metadata(&self) -> CommitmentMetadata {
+        CommitmentMetadata {
+            content_type: self.content_type.clone(),
+            expires_at: self
+                .ttl_seconds
+                .map(|ttl| unix_now().saturating_add(ttl)),
+            reveal: None,
+        }
+    }
+}
+
+/// Check a value to commit is non-empty and within the limits in `context`
+fn validate_value(value: &[u8], context: &ValidationContext) -> Result<(), String> {
+    if value.is_empty() {
+        return Err("Value cannot be empty".to_string());
+    }
+    if value.len() > context.max_value_size {
+        return Err(format!(
+            "Value too large ({} bytes, max {})",
+            value.len(),
+            context.max_value_size
+        ));
+    }
+    if let Some(size) = context.record_size.filter(|&size| size != value.len()) {
+        return Err(format!(
+            "Value must be exactly {} bytes (got {})",
+            size,
+            value.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Check a batch is non-empty and each value in it is valid
+fn validate_values(values: &[Vec<u8>], context: &ValidationContext) -> Result<(), String> {
+    if values.is_empty() {
+        return Err("At least one value is required".to_string());
+    }
+    for (offset, value) in values.iter().enumerate() {
+        validate_value(value, context).map_err(|e| format!("Value {}: {}", offset, e))?;
+    }
+    Ok(())
+}
+
+/// Request to add many commitments with one tree rebuild
+#[derive(Debug, Deserialize)]
+pub struct BatchCommitRequest {
+    pub values: Vec<Vec<u8>>,
+}
+
+impl BatchCommitRequest {
+    /// Check each value against the limits in `context`
+    pub fn validate(&self, context: &ValidationContext) -> Result<(), String> {
+        validate_values(&self.values, context)
+    }
+}
+
+/// Request to commit a pre-sorted block of values into consecutive slots
+#[derive(Debug, Deserialize)]
+pub struct CommitBlockRequest {
+    /// The values, in ascending byte order
+    pub values: Vec<Vec<u8>>,
+}
+
+impl CommitBlockRequest {
+    /// Check each value against the limits in `context`, and that the block is sorted
+    pub fn validate(&self, context: &ValidationContext) -> Result<(), String> {
+        validate_values(&self.values, context)?;
+        if let Some(offset) = self.values.windows(2).position(|pair| pair[0] > pair[1]) {
+            return Err(format!("Block is not sorted at value {}", offset + 1));
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Accept bytes as either a JSON array of numbers or a hex string (optionally `0x`-prefixed)
+pub fn deserialize_bytes_or_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesOrHex;
+
+    impl<'de> Visitor<'de> for BytesOrHex {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a byte array or a hex string")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+            let digits = value.strip_prefix("0x").unwrap_or(value);
+            hex::decode(digits).map_err(|e| E::custom(format!("invalid hex value: {}", e)))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    deserializer.deserialize_any(BytesOrHex)
+}
+
+/// Default page size for paged list endpoints
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Largest page a client may request
+pub const MAX_PAGE_LIMIT: usize = 500;
+
+/// Offset/limit pagination for list endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    /// Number of items to skip (defaults to 0)
+    pub offset: Option<usize>,
+    /// Page size (defaults to 50, at most 500)
+    pub limit: Option<usize>,
+}
+
+impl PageQuery {
+    /// Resolve to `(offset, limit)`, rejecting a zero or oversized limit
+    pub fn resolve(&self) -> Result<(usize, usize), String> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if limit == 0 || limit > MAX_PAGE_LIMIT {
+            return Err(format!("limit must be between 1 and {}", MAX_PAGE_LIMIT));
+        }
+        Ok((self.offset.unwrap_or(0), limit))
+    }
+}
+
+/// Cursor for pulling the commitment stream
+#[derive(Debug, Deserialize)]
+pub struct ReplicationQuery {
+    /// Last index the follower already has (omit to start from the beginning)
+    pub after: Option<usize>,
+}
+
+/// Query selecting how hashes are encoded in the response
+#[derive(Debug, Default, Deserialize)]
+pub struct EncodingQuery {
+    /// One of hex, base58, base64, multibase (defaults to hex)
+    pub encoding: Option<String>,
+}
+
+/// Query selecting the JSON field naming of the response
+#[derive(Debug, Default, Deserialize)]
+pub struct NamingQuery {
+    /// snake_case or camelCase (defaults to the server's configured naming)
+    pub naming: Option<String>,
+}
+
+/// Request for proofs of several indices at once
+#[derive(Debug, Deserialize)]
+pub struct BatchProofRequest {
+    pub indices: Vec<usize>,
+}
+
+/// Query asking for a lossy UTF-8 preview of each value
+#[derive(Debug, Default, Deserialize)]
+pub struct PreviewQuery {
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Query choosing how a commitment is deleted
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteQuery {
+    /// Remove the slot and shift later indices down instead of leaving a placeholder
+    #[serde(default)]
+    pub compact: bool,
+}
+
+/// An RFC 6962 inclusion proof to verify, hashes as base64
+#[derive(Debug, Deserialize)]
+pub struct CtVerifyRequest {
+    pub leaf_index: usize,
+    pub tree_size: usize,
+    /// Sibling hashes from the leaf up to the root
+    pub audit_path: Vec<String>,
+    /// SHA-256(0x00 || leaf data)
+    pub leaf_hash: String,
+    /// Merkle Tree Hash of the log
+    pub root_hash: String,
+}
+
+impl CtVerifyRequest {
+    /// Decode the base64 hashes into a proof
+    pub fn decode(&self) -> Result<CtInclusionProof, AppError> {
+        let base64 = Encoding::Base64;
+        Ok(CtInclusionProof {
+            leaf_index: self.leaf_index,
+            tree_size: self.tree_size,
+            audit_path: self
+                .audit_path
+                .iter()
+                .map(|hash| base64.decode(hash))
+                .collect::<Result<_, _>>()?,
+            leaf_hash: base64.decode(&self.leaf_hash)?,
+            root_hash: base64.decode(&self.root_hash)?,
+        })
+    }
+}
+
+/// First phase of commit-reveal: the sealed hash SHA-256(value || nonce)
+#[derive(Debug, Deserialize)]
+pub struct SealedCommitRequest {
+    /// The sealed hash, as a byte array or a hex string
+    #[serde(deserialize_with = "deserialize_bytes_or_hex")]
+    pub hash: Vec<u8>,
+}
+
+/// Second phase of commit-reveal: the value and nonce behind a sealed hash
+#[derive(Debug, Deserialize)]
+pub struct RevealRequest {
+    #[serde(deserialize_with = "deserialize_bytes_or_hex")]
+    pub value: Vec<u8>,
+    #[serde(deserialize_with = "deserialize_bytes_or_hex")]
+    pub nonce: Vec<u8>,
+}
+
+/// One entry of a map to commit
+#[derive(Debug, Deserialize)]
+pub struct MapEntryRequest {
+    /// Key, as a byte array or a hex string
+    #[serde(deserialize_with = "deserialize_bytes_or_hex")]
+    pub key: Vec<u8>,
+    /// Value, as a byte array or a hex string
+    #[serde(deserialize_with = "deserialize_bytes_or_hex")]
+    pub value: Vec<u8>,
+}
+
+/// Request to commit a batch of key→value entries as one sorted map
+#[derive(Debug, Deserialize)]
+pub struct CommitMapRequest {
+    pub entries: Vec<MapEntryRequest>,
+}
+
+/// Query naming the key to prove
+#[derive(Debug, Deserialize)]
+pub struct KeyQuery {
+    /// Key as hex
+    pub key: String,
+}
+
+/// Backends the admin migrate endpoint can move commitments into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Memory,
+}
+
+/// Request to copy every commitment into a fresh backend and switch over to it
+#[derive(Debug, Deserialize)]
+pub struct MigrateRequest {
+    pub backend: StorageBackend,
+}
+
+/// A proof to verify against a set of roots the caller trusts, e.g. published checkpoints
+#[derive(Debug, Deserialize)]
+pub struct VerifyTrustedRequest {
+    pub proof: MerkleProof,
+    /// Trusted roots as raw bytes
+    pub trusted_roots: Vec<Vec<u8>>,
+}
+
+/// Query naming the root an existing proof was generated against
+#[derive(Debug, Deserialize)]
+pub struct ExtendQuery {
+    /// Old root as hex
+    pub from_root: String,
+}
+
+/// Query selecting the two tree sizes of a consistency proof
+#[derive(Debug, Deserialize)]
+pub struct ConsistencyQuery {
+    /// Size of the older tree
+    pub from: usize,
+    /// Size of the newer tree
+    pub to: usize,
+}
+
+/// Query selecting the leaf range [start, end)
+#[derive(Debug, Deserialize)]
+pub struct RangeQuery {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<AddCommitmentRequest, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn test_value_as_array_or_hex() {
+        let array = parse(r#"{"value": [222, 173, 190, 239]}"#).unwrap();
+        let hex = parse(r#"{"value": "deadbeef"}"#).unwrap();
+        let prefixed = parse(r#"{"value": "0xDEADBEEF"}"#).unwrap();
+
+        assert_eq!(array.value, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(hex.value, array.value);
+        assert_eq!(prefixed.value, array.value);
+    }
+
+    #[test]
+    fn test_fixed_record_size() {
+        let request = |len: usize| AddCommitmentRequest {
+            value: vec![7; len],
+            content_type: None,
+            ttl_seconds: None,
+        };
+
+        let fixed = ValidationContext {
+            record_size: Some(64),
+            ..Default::default()
+        };
+
+        assert!(request(64).validate(&fixed).is_ok());
+        assert_eq!(
+            request(63).validate(&fixed).unwrap_err(),
+            "Value must be exactly 64 bytes (got 63)"
+        );
+        assert!(request(65).validate(&fixed).is_err());
+        assert!(request(65).validate(&ValidationContext::default()).is_ok());
+    }
+
+    #[test]
+    fn test_value_rejects_other_types() {
+        for json in [
+            r#"{"value": {"bytes": [1, 2]}}"#,
+            r#"{"value": 42}"#,
+            r#"{"value": true}"#,
+            r#"{"value": "not hex"}"#,
+            r#"{"value": [1, 256]}"#,
+        ] {
+            assert!(parse(json).is_err(), "{}", json);
+        }
+
+        let err = parse(r#"{"value": 42}"#).unwrap_err().to_string();
+        assert!(err.contains("a byte array or a hex string"), "{}", err);
+    }
+}
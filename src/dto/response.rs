@@ -1,6 +1,19 @@
-use crate::crypto::proof::MerkleProof;
-use crate::models::commitment::Commitment;
-use serde::Serialize;
+use crate::crypto::proof::{BlockProof, ConsistencyProof, MerkleProof, ProofElement, ProofExtension, RangeProof};
+use crate::dto::encoding::Encoding;
+use crate::dto::request::StorageBackend;
+use crate::error::AppError;
+use crate::models::commitment::{Commitment, IndexRemap};
+use crate::models::keyed_map::KeyProof;
+use crate::models::merkle::SiblingFanout;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
 
 /// Response after adding a commitment
 #[derive(Debug, Serialize)]
@@ -20,14 +33,114 @@ impl AddCommitmentResponse {
     }
 }
 
+/// Response after adding a batch of commitments
+#[derive(Debug, Serialize)]
+pub struct BatchCommitResponse {
+    /// The assigned indices, in request order
+    pub indices: Vec<usize>,
+    /// The Merkle root after the whole batch (raw bytes)
+    pub merkle_root: Vec<u8>,
+}
+
+/// Response after deleting a commitment
+#[derive(Debug, Serialize)]
+pub struct DeleteResponse {
+    /// The deleted index
+    pub index: usize,
+    /// The Merkle root hash after the delete (raw bytes)
+    pub merkle_root: Vec<u8>,
+    /// Commitments that moved to a new index; empty unless compacted
+    pub remapped: Vec<IndexRemap>,
+}
+
+/// `201 Created` with a `Location` header pointing at the new resource
+#[derive(Debug)]
+pub struct Created<T> {
+    pub location: String,
+    pub body: T,
+}
+
+impl<T> Created<T> {
+    /// A created commitment, located at `/api/v1/commitments/{index}`
+    pub fn commitment(index: usize, body: T) -> Self {
+        Self {
+            location: format!("/api/v1/commitments/{}", index),
+            body,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Created<T> {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::CREATED,
+            [(header::LOCATION, self.location)],
+            Json(self.body),
+        )
+            .into_response()
+    }
+}
+
+/// A body tagged with an `ETag`; `body` is None when the client's copy is still
+/// current, which is sent as `304 Not Modified`
+#[derive(Debug)]
+pub struct Conditional<T> {
+    pub etag: String,
+    pub body: Option<T>,
+}
+
+impl<T> Conditional<T> {
+    /// Send `body` unless `if_none_match` (an `If-None-Match` value) already names `etag`
+    pub fn new(etag: String, if_none_match: Option<&str>, body: T) -> Self {
+        let current = if_none_match.is_some_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+        Self {
+            body: (!current).then_some(body),
+            etag,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Conditional<T> {
+    fn into_response(self) -> Response {
+        let etag = [(header::ETAG, self.etag)];
+        match self.body {
+            Some(body) => (etag, Json(body)).into_response(),
+            None => (StatusCode::NOT_MODIFIED, etag).into_response(),
+        }
+    }
+}
+
+/// Characters kept in a value preview before it is truncated
+pub const VALUE_PREVIEW_CHARS: usize = 64;
+
+/// Lossy UTF-8 rendering of `value`, cut to `max_chars` characters with a trailing
+/// ellipsis. Invalid bytes show up as U+FFFD; this is a display aid only.
+pub fn value_preview(value: &[u8], max_chars: usize) -> String {
+    let text = String::from_utf8_lossy(value);
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.into_owned(),
+    }
+}
+
 /// Response containing a commitment
 #[derive(Debug, Serialize)]
 pub struct CommitmentResponse {
     pub commitment: Commitment,
-    /// Value as bytes
-    pub value: Vec<u8>,
+    /// Value as bytes, sharing the commitment's buffer
+    pub value: Bytes,
     /// Root as bytes
     pub root: Vec<u8>,
+    /// MIME type of the value, if one was supplied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Lossy UTF-8 preview of the value, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_preview: Option<String>,
 }
 
 impl From<Commitment> for CommitmentResponse {
@@ -35,41 +148,546 @@ impl From<Commitment> for CommitmentResponse {
         Self {
             value: commitment.value.clone(),
             root: commitment.merkle_root.clone(),
+            content_type: commitment.content_type.clone(),
+            value_preview: None,
             commitment,
         }
     }
 }
 
+impl CommitmentResponse {
+    /// Attach a value preview when `preview` is set
+    pub fn with_preview(mut self, preview: bool) -> Self {
+        self.value_preview = preview.then(|| value_preview(&self.value, VALUE_PREVIEW_CHARS));
+        self
+    }
+}
+
 /// Response containing a Merkle proof
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ProofResponse {
-    pub proof: MerkleProof,
+    /// Shared with the storage proof cache, so responses don't copy the value
+    pub proof: Arc<MerkleProof>,
     /// Root as bytes
     pub root: Vec<u8>,
+    /// Root in the requested text encoding (hex by default)
+    #[serde(default)]
+    pub root_encoded: String,
+    /// Leaf index implied by the path's sibling sides
+    #[serde(default)]
+    pub derived_index: usize,
+    /// Whether `derived_index` matches the proof's index; false points at a
+    /// generation bug or a tampered path
+    #[serde(default)]
+    pub index_consistent: bool,
 }
 
 impl From<MerkleProof> for ProofResponse {
     fn from(proof: MerkleProof) -> Self {
+        Arc::new(proof).into()
+    }
+}
+
+impl From<Arc<MerkleProof>> for ProofResponse {
+    fn from(proof: Arc<MerkleProof>) -> Self {
+        let derived_index = proof.position();
         Self {
             root: proof.root.clone(),
+            root_encoded: Encoding::default().encode(&proof.root),
+            derived_index,
+            index_consistent: derived_index == proof.index,
             proof,
         }
     }
 }
 
+impl ProofResponse {
+    /// Re-encode the root with the given encoding
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.root_encoded = encoding.encode(&self.root);
+        self
+    }
+
+    /// Build a proof response from hex-encoded value, sibling hashes and root
+    pub fn from_hex(
+        index: usize,
+        value_hex: &str,
+        path: &[(String, bool)],
+        root_hex: &str,
+    ) -> Result<Self, AppError> {
+        let value = decode_hex("value", value_hex)?;
+        let root = decode_hex("root", root_hex)?;
+        let proof = path
+            .iter()
+            .map(|(hash_hex, is_left)| {
+                Ok(ProofElement {
+                    hash: decode_hex("proof hash", hash_hex)?,
+                    is_left: *is_left,
+                })
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        Ok(MerkleProof::new(index, value, proof, root).into())
+    }
+}
+
+fn decode_hex(field: &str, input: &str) -> Result<Vec<u8>, AppError> {
+    hex::decode(input).map_err(|e| AppError::InvalidInput(format!("Invalid {} hex: {}", field, e)))
+}
+
+/// Response containing the delta to upgrade an old proof
+#[derive(Debug, Serialize)]
+pub struct ProofExtensionResponse {
+    pub extension: ProofExtension,
+    /// Root as bytes
+    pub root: Vec<u8>,
+}
+
+impl From<ProofExtension> for ProofExtensionResponse {
+    fn from(extension: ProofExtension) -> Self {
+        Self {
+            root: extension.root.clone(),
+            extension,
+        }
+    }
+}
+
+/// Response containing a consistency proof and the two roots it links
+#[derive(Debug, Serialize)]
+pub struct ConsistencyProofResponse {
+    pub proof: ConsistencyProof,
+    /// Root of the first `from` commitments
+    pub old_root: Vec<u8>,
+    /// Root of the first `to` commitments
+    pub new_root: Vec<u8>,
+}
+
+/// Response to committing a block: where it landed and the proof covering it
+#[derive(Debug, Serialize)]
+pub struct CommitBlockResponse {
+    pub block_start: usize,
+    pub block_len: usize,
+    pub proof: BlockProof,
+}
+
+/// Response containing a range proof
+#[derive(Debug, Serialize)]
+pub struct RangeProofResponse {
+    pub proof: RangeProof,
+    /// Root as bytes
+    pub root: Vec<u8>,
+}
+
+impl From<RangeProof> for RangeProofResponse {
+    fn from(proof: RangeProof) -> Self {
+        Self {
+            root: proof.root.clone(),
+            proof,
+        }
+    }
+}
+
+/// One page of a list endpoint with navigation metadata
+#[derive(Debug, Serialize)]
+pub struct PagedResponse<T> {
+    pub items: Vec<T>,
+    /// Total number of items across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub total_pages: usize,
+    pub has_next: bool,
+    pub has_prev: bool,
+}
+
+impl<T> PagedResponse<T> {
+    /// Wrap the items at `offset` of a list of `total` items
+    pub fn new(items: Vec<T>, total: usize, offset: usize, limit: usize) -> Self {
+        Self {
+            items,
+            total,
+            offset,
+            limit,
+            total_pages: if limit == 0 { 0 } else { total.div_ceil(limit) },
+            has_next: offset.saturating_add(limit) < total,
+            has_prev: offset > 0,
+        }
+    }
+}
+
+/// Leaf hash of a single commitment
+#[derive(Debug, Serialize)]
+pub struct LeafHashResponse {
+    pub index: usize,
+    /// SHA-256 of the committed value (raw bytes)
+    pub leaf_hash: Vec<u8>,
+}
+
+/// Node hashes from the root down to a commitment's leaf
+#[derive(Debug, Serialize)]
+pub struct NodePathResponse {
+    pub index: usize,
+    /// Root hash first, leaf hash last (raw bytes)
+    pub node_hashes: Vec<Vec<u8>>,
+}
+
+/// Result of verifying a Merkle proof
+#[derive(Debug, Serialize)]
+pub struct VerifyResponse {
+    pub valid: bool,
+    /// The root the proof actually computes to (raw bytes)
+    pub computed_root: Vec<u8>,
+}
+
+/// Result of verifying an RFC 6962 inclusion proof
+#[derive(Debug, Serialize)]
+pub struct CtVerifyResponse {
+    pub valid: bool,
+}
+
+/// Inclusion proof in the RFC 6962 wire format, hashes as base64
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CtInclusionProofResponse {
+    pub leaf_index: usize,
+    pub tree_size: usize,
+    /// Sibling hashes from the leaf up to the root
+    pub audit_path: Vec<String>,
+    /// RFC 6962 Merkle Tree Hash of the whole log
+    pub root_hash: String,
+}
+
+/// JSON-LD contexts of a `MerkleProof2019`-style proof
+pub const JSONLD_PROOF_CONTEXT: [&str; 2] = [
+    "https://w3id.org/security/v2",
+    "https://w3id.org/blockcerts/v3",
+];
+
+/// An inclusion proof as a JSON-LD document in the style of `MerkleProof2019`,
+/// hashes as base58, for verifiable-credentials tooling
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLdProofResponse {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    #[serde(rename = "type")]
+    pub proof_type: String,
+    pub hash_algorithm: String,
+    pub tree_size: usize,
+    pub leaf_index: usize,
+    /// Leaf hash the path starts from
+    pub target_hash: String,
+    pub merkle_root: String,
+    /// Siblings from the leaf up to the root, each keyed by its side
+    pub path: Vec<JsonLdPathStep>,
+}
+
+/// One sibling on a JSON-LD proof path: `{ "left": hash }` or `{ "right": hash }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonLdPathStep {
+    Left(String),
+    Right(String),
+}
+
+impl JsonLdProofResponse {
+    pub fn new(proof: &MerkleProof, tree_size: usize) -> Self {
+        let base58 = Encoding::Base58;
+        Self {
+            context: JSONLD_PROOF_CONTEXT.map(String::from).to_vec(),
+            proof_type: "MerkleProof2019".to_string(),
+            hash_algorithm: proof.algorithm.to_string(),
+            tree_size,
+            leaf_index: proof.index,
+            target_hash: base58.encode(&proof.leaf_hash()),
+            merkle_root: base58.encode(&proof.root),
+            path: proof
+                .proof
+                .iter()
+                .map(|element| match element.is_left {
+                    true => JsonLdPathStep::Left(base58.encode(&element.hash)),
+                    false => JsonLdPathStep::Right(base58.encode(&element.hash)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Decode back into a proof over the target hash, ready to verify
+    pub fn to_proof(&self) -> Result<MerkleProof, AppError> {
+        let base58 = Encoding::Base58;
+        let path = self
+            .path
+            .iter()
+            .map(|step| {
+                let (hash, is_left) = match step {
+                    JsonLdPathStep::Left(hash) => (hash, true),
+                    JsonLdPathStep::Right(hash) => (hash, false),
+                };
+                Ok(ProofElement {
+                    hash: base58.decode(hash)?,
+                    is_left,
+                })
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        let proof = MerkleProof::new(
+            self.leaf_index,
+            base58.decode(&self.target_hash)?,
+            path,
+            base58.decode(&self.merkle_root)?,
+        );
+        Ok(proof.with_prehashed(true))
+    }
+}
+
+/// Replication checkpoint of the leader
+#[derive(Debug, Serialize)]
+pub struct CheckpointResponse {
+    /// Index of the last commitment (None when empty)
+    pub last_index: Option<usize>,
+    /// Root after the last commitment (raw bytes)
+    pub root: Vec<u8>,
+}
+
+/// Result of switching to a new storage backend
+#[derive(Debug, Serialize)]
+pub struct MigrateResponse {
+    /// The backend now serving requests
+    pub backend: StorageBackend,
+    /// Slots copied, including placeholders
+    pub commitment_count: usize,
+    /// Root shared by the old and new backends (raw bytes)
+    pub merkle_root: Vec<u8>,
+}
+
+/// Result of a storage integrity self-check
+#[derive(Debug, Serialize)]
+pub struct IntegrityResponse {
+    pub valid: bool,
+    pub commitment_count: usize,
+}
+
 /// Response for root hash query
 #[derive(Debug, Serialize)]
 pub struct RootResponse {
     /// Root hash (raw bytes)
     pub root: Vec<u8>,
+    /// Root hash in the requested text encoding (hex by default)
+    pub root_encoded: String,
     /// Number of commitments in the tree
     pub commitment_count: usize,
 }
 
+/// `RootResponse` with camelCase field names
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootResponseCamel {
+    pub root: Vec<u8>,
+    pub root_encoded: String,
+    pub commitment_count: usize,
+}
+
+impl From<RootResponse> for RootResponseCamel {
+    fn from(response: RootResponse) -> Self {
+        Self {
+            root: response.root,
+            root_encoded: response.root_encoded,
+            commitment_count: response.commitment_count,
+        }
+    }
+}
+
+/// Field naming of JSON response bodies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonNaming {
+    #[default]
+    SnakeCase,
+    CamelCase,
+}
+
+impl JsonNaming {
+    /// Parse an optional query value, falling back to `default` when absent
+    pub fn from_query(value: Option<&str>, default: JsonNaming) -> Result<Self, AppError> {
+        value.map_or(Ok(default), str::parse)
+    }
+}
+
+impl FromStr for JsonNaming {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snake" | "snake_case" => Ok(JsonNaming::SnakeCase),
+            "camel" | "camelCase" => Ok(JsonNaming::CamelCase),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown naming '{}' (expected snake_case or camelCase)",
+                other
+            ))),
+        }
+    }
+}
+
+/// A body serialized under the snake_case names of `S` or the camelCase names of `C`
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Named<S, C> {
+    Snake(S),
+    Camel(C),
+}
+
+impl<S: Into<C>, C> Named<S, C> {
+    pub fn new(body: S, naming: JsonNaming) -> Self {
+        match naming {
+            JsonNaming::SnakeCase => Named::Snake(body),
+            JsonNaming::CamelCase => Named::Camel(body.into()),
+        }
+    }
+}
+
+/// Sibling fan-out of every node below the root
+#[derive(Debug, Serialize)]
+pub struct SiblingFanoutResponse {
+    pub leaf_count: usize,
+    pub nodes: Vec<SiblingFanout>,
+}
+
+/// Hash of one node, addressed by its level-order index
+#[derive(Debug, Serialize)]
+pub struct TreeNodeResponse {
+    pub index: usize,
+    pub hash: Vec<u8>,
+}
+
+/// A committed key→value map
+#[derive(Debug, Serialize)]
+pub struct CommitMapResponse {
+    pub map_id: usize,
+    pub root: Vec<u8>,
+    /// Number of entries; verifiers need it alongside the root
+    pub leaf_count: usize,
+}
+
+/// Proof of a key's value in a map, or of its absence
+#[derive(Debug, Serialize)]
+pub struct KeyProofResponse {
+    pub map_id: usize,
+    pub root: Vec<u8>,
+    pub leaf_count: usize,
+    pub proof: KeyProof,
+}
+
+/// Roots a range of commitments was written under, oldest first
+#[derive(Debug, Serialize)]
+pub struct RootCoverResponse {
+    pub start: usize,
+    pub end: usize,
+    /// Distinct roots recorded by the commitments in [start, end)
+    pub roots: Vec<Vec<u8>>,
+}
+
+/// Aggregate statistics for dashboards
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    /// Number of commitments in the tree
+    pub commitment_count: usize,
+    /// Current root hash as hex (None when the tree is empty)
+    pub root_hex: Option<String>,
+    /// Number of levels in the tree, including the leaves
+    pub tree_height: usize,
+    /// Average committed value size in bytes
+    pub average_leaf_size: f64,
+    /// Total committed value bytes
+    pub total_bytes: usize,
+    /// Seconds since the server started
+    pub uptime_seconds: u64,
+}
+
+/// Envelope wrapping a successful response body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub data: T,
+    pub meta: EnvelopeMeta,
+}
+
+/// Metadata attached to enveloped responses
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvelopeMeta {
+    pub request_id: String,
+    /// Server root as hex at the time of the response
+    pub root: String,
+}
+
 /// Health check response
 #[derive(Debug, Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
     pub commitment_count: usize,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paged_response_flags() {
+        let first = PagedResponse::new(vec![0; 10], 25, 0, 10);
+        assert!(first.has_next && !first.has_prev);
+        assert_eq!(first.total_pages, 3);
+
+        let middle = PagedResponse::new(vec![0; 10], 25, 10, 10);
+        assert!(middle.has_next && middle.has_prev);
+
+        let last = PagedResponse::new(vec![0; 5], 25, 20, 10);
+        assert!(!last.has_next && last.has_prev);
+
+        let only = PagedResponse::new(vec![0; 10], 10, 0, 10);
+        assert!(!only.has_next && !only.has_prev);
+        assert_eq!(only.total_pages, 1);
+    }
+
+    #[test]
+    fn test_value_preview() {
+        assert_eq!(value_preview("héllo".as_bytes(), 10), "héllo");
+        assert_eq!(value_preview(b"ab\xffcd", 10), "ab\u{FFFD}cd");
+        assert_eq!(value_preview("héllo world".as_bytes(), 5), "héllo…");
+        assert_eq!(value_preview(b"exact", 5), "exact");
+        assert_eq!(value_preview(b"", 5), "");
+
+        let response = CommitmentResponse::from(Commitment::new(0, b"data".to_vec(), vec![0; 32]));
+        assert_eq!(response.value_preview, None);
+        assert_eq!(response.with_preview(true).value_preview.as_deref(), Some("data"));
+    }
+    use crate::crypto::proof::generate_proof;
+    use crate::models::merkle::{MerkleNode, MerkleTree};
+
+    #[test]
+    fn test_proof_response_from_hex() {
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data0"),
+            MerkleNode::new_leaf(b"data1"),
+            MerkleNode::new_leaf(b"data2"),
+        ]);
+        let root = tree.root().unwrap();
+        let path: Vec<(String, bool)> = generate_proof(root, 2, 3)
+            .into_iter()
+            .map(|e| (hex::encode(e.hash), e.is_left))
+            .collect();
+
+        let response =
+            ProofResponse::from_hex(2, &hex::encode(b"data2"), &path, &hex::encode(&root.hash))
+                .unwrap();
+
+        assert_eq!(response.root, root.hash);
+        assert_eq!(response.proof.value, b"data2".to_vec());
+        assert!(response.proof.verify());
+    }
+
+    #[test]
+    fn test_proof_response_from_bad_hex() {
+        let result = ProofResponse::from_hex(0, "zz", &[], "00");
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+
+        let path = vec![("abc".to_string(), false)];
+        let result = ProofResponse::from_hex(0, "00", &path, "00");
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+}
@@ -7,11 +7,17 @@ use serde::Serialize;
 use std::fmt;
 
 /// Application error types
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AppError {
     NotFound(String),
     TreeBuildError(String),
     InvalidInput(String),
+    Conflict(String),
+    Unauthorized(String),
+    Forbidden(String),
+    PayloadTooLarge(String),
+    Timeout(String),
+    Overloaded(String),
     Internal(String),
 }
 
@@ -21,6 +27,12 @@ impl fmt::Display for AppError {
             AppError::NotFound(s) => write!(f, "Not found: {}", s),
             AppError::TreeBuildError(s) => write!(f, "Tree build error: {}", s),
             AppError::InvalidInput(s) => write!(f, "Invalid input: {}", s),
+            AppError::Conflict(s) => write!(f, "Conflict: {}", s),
+            AppError::Unauthorized(s) => write!(f, "Unauthorized: {}", s),
+            AppError::Forbidden(s) => write!(f, "Forbidden: {}", s),
+            AppError::PayloadTooLarge(s) => write!(f, "Payload too large: {}", s),
+            AppError::Timeout(s) => write!(f, "Timed out: {}", s),
+            AppError::Overloaded(s) => write!(f, "Overloaded: {}", s),
             AppError::Internal(s) => write!(f, "Internal error: {}", s),
         }
     }
@@ -40,6 +52,12 @@ impl IntoResponse for AppError {
         let (status, error_type) = match &self {
             AppError::NotFound(_) => (StatusCode::NOT_FOUND, "NOT_FOUND"),
             AppError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "INVALID_INPUT"),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, "CONFLICT"),
+            AppError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED"),
+            AppError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+            AppError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "PAYLOAD_TOO_LARGE"),
+            AppError::Timeout(_) => (StatusCode::REQUEST_TIMEOUT, "REQUEST_TIMEOUT"),
+            AppError::Overloaded(_) => (StatusCode::SERVICE_UNAVAILABLE, "OVERLOADED"),
             AppError::TreeBuildError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "TREE_BUILD_ERROR")
             }
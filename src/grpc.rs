@@ -0,0 +1,265 @@
+//! gRPC service over the commitment storage (enabled by the `grpc` feature)
+
+use crate::crypto::hash::HashAlgorithm;
+use crate::crypto::proof::{ChainLink, MerkleProof, ProofElement, MAX_PROOF_PATH_LEN};
+use crate::dto::request::{AddCommitmentRequest, ValidationContext};
+use crate::error::AppError;
+use crate::models::merkle::LeafHashMode;
+use crate::storage::traits::CommitmentStorage;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::{transport::Server, Request, Response, Status};
+
+/// Generated protobuf types and service stubs for `proto/merkle.proto`
+pub mod pb {
+    tonic::include_proto!("merkle.v1");
+}
+
+use pb::merkle_service_server::{MerkleService, MerkleServiceServer};
+
+/// Address the gRPC server listens on when `MERKLE_GRPC_ADDR` is unset
+pub const DEFAULT_GRPC_ADDR: &str = "127.0.0.1:50051";
+
+/// gRPC listen address from `MERKLE_GRPC_ADDR`, falling back to the default
+pub fn grpc_addr_from_env() -> String {
+    std::env::var("MERKLE_GRPC_ADDR").unwrap_or_else(|_| DEFAULT_GRPC_ADDR.to_string())
+}
+
+/// `MerkleService` implementation sharing storage with the REST API
+pub struct GrpcService {
+    storage: Arc<dyn CommitmentStorage>,
+}
+
+impl GrpcService {
+    pub fn new(storage: Arc<dyn CommitmentStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// Wrap the service for use with a tonic `Server`
+    pub fn into_server(self) -> MerkleServiceServer<Self> {
+        MerkleServiceServer::new(self)
+    }
+}
+
+/// Serve the gRPC API on `addr` until the server fails
+pub async fn serve(storage: Arc<dyn CommitmentStorage>, addr: SocketAddr) -> Result<(), AppError> {
+    Server::builder()
+        .add_service(GrpcService::new(storage).into_server())
+        .serve(addr)
+        .await
+        .map_err(|e| AppError::Internal(format!("gRPC server failed: {}", e)))
+}
+
+#[tonic::async_trait]
+impl MerkleService for GrpcService {
+    async fn add_commitment(
+        &self,
+        request: Request<pb::AddCommitmentRequest>,
+    ) -> Result<Response<pb::AddCommitmentReply>, Status> {
+        let request = request.into_inner();
+        let req = AddCommitmentRequest {
+            value: request.value,
+            content_type: request.content_type,
+            ttl_seconds: request.ttl_seconds,
+        };
+        req.validate(&ValidationContext::default()).map_err(Status::invalid_argument)?;
+
+        let metadata = req.metadata();
+        let (index, merkle_root) = self
+            .storage
+            .store_commitment(None, req.value, metadata)
+            .await?;
+
+        Ok(Response::new(pb::AddCommitmentReply {
+            index: index as u64,
+            merkle_root,
+        }))
+    }
+
+    async fn get_proof(
+        &self,
+        request: Request<pb::GetProofRequest>,
+    ) -> Result<Response<pb::Proof>, Status> {
+        let index = usize::try_from(request.into_inner().index)
+            .map_err(|_| Status::invalid_argument("Index out of range"))?;
+        let proof = self.storage.get_proof(index).await?;
+
+        Ok(Response::new(proof.as_ref().into()))
+    }
+
+    async fn verify_proof(
+        &self,
+        request: Request<pb::Proof>,
+    ) -> Result<Response<pb::VerifyProofReply>, Status> {
+        let proof = MerkleProof::try_from(request.into_inner())?;
+        if proof.path_len() > MAX_PROOF_PATH_LEN {
+            return Err(Status::invalid_argument(format!(
+                "Proof path has {} elements (max {})",
+                proof.path_len(),
+                MAX_PROOF_PATH_LEN
+            )));
+        }
+
+        Ok(Response::new(pb::VerifyProofReply {
+            valid: proof.verify(),
+            computed_root: proof.compute_root(),
+        }))
+    }
+}
+
+impl From<AppError> for Status {
+    fn from(error: AppError) -> Self {
+        let message = error.to_string();
+        match error {
+            AppError::NotFound(_) => Status::not_found(message),
+            AppError::InvalidInput(_) => Status::invalid_argument(message),
+            AppError::Conflict(_) => Status::already_exists(message),
+            AppError::Unauthorized(_) => Status::unauthenticated(message),
+            AppError::Forbidden(_) => Status::permission_denied(message),
+            AppError::PayloadTooLarge(_) | AppError::Overloaded(_) => Status::resource_exhausted(message),
+            AppError::Timeout(_) => Status::deadline_exceeded(message),
+            AppError::TreeBuildError(_) | AppError::Internal(_) => Status::internal(message),
+        }
+    }
+}
+
+impl From<&ProofElement> for pb::ProofElement {
+    fn from(element: &ProofElement) -> Self {
+        Self {
+            hash: element.hash.clone(),
+            is_left: element.is_left,
+        }
+    }
+}
+
+impl From<pb::ProofElement> for ProofElement {
+    fn from(element: pb::ProofElement) -> Self {
+        Self {
+            hash: element.hash,
+            is_left: element.is_left,
+        }
+    }
+}
+
+impl From<&MerkleProof> for pb::Proof {
+    fn from(proof: &MerkleProof) -> Self {
+        Self {
+            version: proof.version.into(),
+            index: proof.index as u64,
+            value: proof.value.clone(),
+            proof: proof.proof.iter().map(Into::into).collect(),
+            root: proof.root.clone(),
+            chain: proof
+                .chain
+                .iter()
+                .map(|link| pb::ChainLink {
+                    index: link.index as u64,
+                    proof: link.proof.iter().map(Into::into).collect(),
+                })
+                .collect(),
+            prehashed: proof.prehashed,
+            length_prefixed: proof.leaf_mode == LeafHashMode::LengthPrefixed,
+            index_bound: proof.leaf_mode == LeafHashMode::IndexBound,
+            placeholder: proof.placeholder,
+        }
+    }
+}
+
+impl TryFrom<pb::Proof> for MerkleProof {
+    type Error = AppError;
+
+    fn try_from(proof: pb::Proof) -> Result<Self, Self::Error> {
+        let index = |index: u64| {
+            usize::try_from(index).map_err(|_| AppError::InvalidInput("Index out of range".to_string()))
+        };
+        let version = u8::try_from(proof.version)
+            .map_err(|_| AppError::InvalidInput(format!("Unsupported proof version {}", proof.version)))?;
+
+        let leaf_mode = match (proof.length_prefixed, proof.index_bound) {
+            (false, false) => LeafHashMode::Plain,
+            (true, false) => LeafHashMode::LengthPrefixed,
+            (false, true) => LeafHashMode::IndexBound,
+            (true, true) => {
+                return Err(AppError::InvalidInput(
+                    "A proof can't be both length-prefixed and index-bound".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            version,
+            index: index(proof.index)?,
+            value: proof.value,
+            proof: proof.proof.into_iter().map(Into::into).collect(),
+            root: proof.root,
+            chain: proof
+                .chain
+                .into_iter()
+                .map(|link| {
+                    Ok(ChainLink {
+                        index: index(link.index)?,
+                        proof: link.proof.into_iter().map(Into::into).collect(),
+                    })
+                })
+                .collect::<Result<Vec<_>, AppError>>()?,
+            prehashed: proof.prehashed,
+            leaf_mode,
+            placeholder: proof.placeholder,
+            // The protobuf schema predates algorithm tags; gRPC proofs are SHA-256
+            algorithm: HashAlgorithm::Sha256,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+    use pb::merkle_service_client::MerkleServiceClient;
+    use tonic::transport::server::TcpIncoming;
+
+    #[tokio::test]
+    async fn test_grpc_add_and_get_proof() {
+        let storage: Arc<dyn CommitmentStorage> = Arc::new(MemoryStorage::new());
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(GrpcService::new(storage.clone()).into_server())
+                .serve_with_incoming(incoming),
+        );
+
+        let mut client = MerkleServiceClient::connect(format!("http://{}", addr)).await.unwrap();
+        for i in 0..3 {
+            let reply = client
+                .add_commitment(pb::AddCommitmentRequest {
+                    value: format!("data{}", i).into_bytes(),
+                    content_type: None,
+                    ttl_seconds: None,
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(reply.index, i);
+        }
+
+        let proof = client
+            .get_proof(pb::GetProofRequest { index: 1 })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(proof.value, b"data1".to_vec());
+        assert_eq!(proof.root, storage.get_root_hash().await.unwrap());
+        assert!(MerkleProof::try_from(proof.clone()).unwrap().verify());
+
+        let reply = client.verify_proof(proof).await.unwrap().into_inner();
+        assert!(reply.valid);
+
+        let status = client
+            .get_proof(pb::GetProofRequest { index: 9 })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -5,6 +5,11 @@ pub mod dto;
 pub mod error;
 pub mod models;
 pub mod storage;
+pub mod test_vectors;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 
 // Re-exports for convenience
 pub use error::AppError;
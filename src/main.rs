@@ -1,13 +1,61 @@
-use merkle_tree::{api, storage::memory::MemoryStorage};
+use merkle_tree::{
+    api::{self, state::ApiConfig},
+    storage::{expiry, memory::{MemoryStorage, MemoryStorageConfig, RebuildLimiter}, self_test, traits::CommitmentStorage},
+};
 use std::sync::Arc;
 
 #[tokio::main]
 async fn main() {
+    // Export spans to an OTLP collector
+    #[cfg(feature = "otel")]
+    let _tracer_provider = merkle_tree::telemetry::init(&merkle_tree::telemetry::otlp_endpoint_from_env())
+        .expect("failed to initialize OpenTelemetry");
+
     // Create storage
-    let storage = Arc::new(MemoryStorage::new());
+    #[allow(unused_mut)]
+    let mut storage_config = MemoryStorageConfig {
+        rebuild_limiter: RebuildLimiter::from_env(),
+        max_leaves: MemoryStorageConfig::max_leaves_from_env(),
+        max_index_gap: MemoryStorageConfig::max_index_gap_from_env(),
+        batch_window: MemoryStorageConfig::batch_window_from_env(),
+        checkpoint_interval: MemoryStorageConfig::checkpoint_interval_from_env(),
+        ..Default::default()
+    };
+    #[cfg(feature = "encryption")]
+    {
+        storage_config.encryption_key = merkle_tree::crypto::encryption::EncryptionKey::from_env()
+            .expect("invalid MERKLE_ENCRYPTION_KEY");
+    }
+
+    // Prove the configured hashing round-trips before accepting any data
+    if self_test::self_test_enabled_from_env() {
+        self_test::self_test(storage_config.clone())
+            .await
+            .expect("startup self-test failed");
+        println!("✅ Startup self-test passed");
+    }
+    let storage = Arc::new(MemoryStorage::with_config(storage_config));
+
+    // Persistent backends recover their tree from stored commitments before serving
+    storage.rebuild().await.expect("failed to rebuild Merkle tree");
+
+    // Serve gRPC on its own port, sharing the same storage
+    #[cfg(feature = "grpc")]
+    {
+        let grpc_addr = merkle_tree::grpc::grpc_addr_from_env()
+            .parse()
+            .expect("invalid MERKLE_GRPC_ADDR");
+        println!("🔌 gRPC server running on {}", grpc_addr);
+        tokio::spawn(merkle_tree::grpc::serve(storage.clone(), grpc_addr));
+    }
+
+    // Tombstone expired commitments in the background
+    expiry::spawn_sweeper(storage.clone(), expiry::sweep_interval_from_env());
 
     // Create application state
-    let state = api::state::AppState::new(storage);
+    let state = api::state::AppState::builder(storage)
+        .config(ApiConfig::from_env().expect("invalid API configuration"))
+        .build();
 
     // Create router
     let app = api::route::create_router(state);
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// Kind of mutation recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOp {
+    /// A value was appended
+    Add,
+    /// A value was written at a chosen index
+    Insert,
+    /// A value was removed, leaving a placeholder
+    Delete,
+    /// A value was removed and every later index shifted down by one
+    Compact,
+}
+
+/// One mutation of the commitment log and the root it produced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub op: AuditOp,
+    /// Index of the affected commitment
+    pub index: usize,
+    /// Merkle root after the mutation
+    pub root: Vec<u8>,
+    /// Unix time (seconds) of the mutation
+    pub timestamp: u64,
+}
+
+/// A root kept as a stable anchor each time the tree grows past a multiple of the
+/// checkpoint interval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Number of leaves the root covers
+    pub tree_size: usize,
+    pub root: Vec<u8>,
+    /// Unix time (seconds) the checkpoint was taken
+    pub timestamp: u64,
+}
+
+impl AuditEntry {
+    /// Record `op` on `index`, timestamped now
+    pub fn now(op: AuditOp, index: usize, root: Vec<u8>) -> Self {
+        Self {
+            op,
+            index,
+            root,
+            timestamp: super::commitment::unix_now(),
+        }
+    }
+}
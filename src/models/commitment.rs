@@ -1,26 +1,132 @@
+use crate::crypto::hash::sha256;
+use crate::models::merkle::{LeafHashMode, MerkleNode};
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current time as seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The value committed in the first phase of commit-reveal: SHA-256(value || nonce)
+pub fn seal(value: &[u8], nonce: &[u8]) -> Vec<u8> {
+    sha256(&[value, nonce])
+}
+
+/// Phase of a commit-reveal commitment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevealState {
+    /// Only the sealed hash is known
+    Sealed,
+    /// The value and nonce were shown to match the sealed hash
+    Revealed,
+}
+
+/// Optional per-commitment attributes that are stored but never hashed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitmentMetadata {
+    /// MIME type of the value
+    pub content_type: Option<String>,
+    /// Unix time (seconds) after which the commitment is tombstoned
+    pub expires_at: Option<u64>,
+    /// Set for commit-reveal commitments, whose value is a sealed hash
+    pub reveal: Option<RevealState>,
+}
+
+/// Where a commitment moved when an earlier one was compacted away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexRemap {
+    pub old_index: usize,
+    pub new_index: usize,
+}
 
 /// A commitment represents a piece of data committed to the Merkle tree
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Commitment {
     /// Unique sequential index
     pub index: usize,
-    /// The committed data
-    pub value: Vec<u8>,
+    /// The committed data, reference-counted so responses can share it without copying
+    pub value: Bytes,
     /// The Merkle root at the time of commitment
     pub merkle_root: Vec<u8>,
+    /// The Merkle root just before this commitment was written, chaining writes together
+    #[serde(default)]
+    pub prev_root: Vec<u8>,
+    /// MIME type of the value, if the client supplied one (not part of the leaf hash)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Unix time (seconds) after which the commitment expires, if it has a TTL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Commit-reveal phase, for commitments made through the commit-reveal flow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reveal: Option<RevealState>,
+    /// True for a null slot (gap padding or a tombstone), which holds no value and
+    /// hashes to `EMPTY_LEAF_HASH` rather than as a committed empty value
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub placeholder: bool,
 }
 
 impl Commitment {
     /// Create a new commitment
-    pub fn new(index: usize, value: Vec<u8>, merkle_root: Vec<u8>) -> Self {
+    pub fn new(index: usize, value: impl Into<Bytes>, merkle_root: Vec<u8>) -> Self {
         Self {
             index,
-            value,
+            value: value.into(),
             merkle_root,
+            prev_root: Vec::new(),
+            content_type: None,
+            expires_at: None,
+            reveal: None,
+            placeholder: false,
+        }
+    }
+
+    /// Create a null slot, which has no value
+    pub fn new_placeholder(index: usize, merkle_root: Vec<u8>) -> Self {
+        Self {
+            placeholder: true,
+            ..Self::new(index, Vec::new(), merkle_root)
+        }
+    }
+
+    /// Record the root the tree had before this commitment was written
+    pub fn with_prev_root(mut self, prev_root: Vec<u8>) -> Self {
+        self.prev_root = prev_root;
+        self
+    }
+
+    /// Attach the content type, expiry and reveal state
+    pub fn with_metadata(mut self, metadata: CommitmentMetadata) -> Self {
+        self.content_type = metadata.content_type;
+        self.expires_at = metadata.expires_at;
+        self.reveal = metadata.reveal;
+        self
+    }
+
+    /// Get the content type, expiry and reveal state
+    pub fn metadata(&self) -> CommitmentMetadata {
+        CommitmentMetadata {
+            content_type: self.content_type.clone(),
+            expires_at: self.expires_at,
+            reveal: self.reveal,
         }
     }
 
+    /// Check whether the commitment had expired by `now` (Unix seconds)
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// Check whether the commitment has expired
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(unix_now())
+    }
+
     /// Get the commitment index
     pub fn index(&self) -> usize {
         self.index
@@ -31,10 +137,46 @@ impl Commitment {
         &self.value
     }
 
+    /// Leaf hash of the value under the default leaf hashing rule, matching the
+    /// leaf node the tree builds for it
+    pub fn leaf_hash(&self) -> Vec<u8> {
+        self.leaf_hash_with_mode(LeafHashMode::default())
+    }
+
+    /// Leaf hash of the value under `mode`; `EMPTY_LEAF_HASH` for a placeholder
+    pub fn leaf_hash_with_mode(&self, mode: LeafHashMode) -> Vec<u8> {
+        self.leaf(false, mode).hash
+    }
+
+    /// Leaf node of this slot: the null leaf for a placeholder, otherwise the value's
+    /// leaf (taken as-is when `hash_only` storage already holds the leaf hash)
+    pub fn leaf(&self, hash_only: bool, mode: LeafHashMode) -> MerkleNode {
+        if self.placeholder {
+            MerkleNode::empty_leaf()
+        } else {
+            MerkleNode::committed_leaf(self.index, &self.value, hash_only, mode)
+        }
+    }
+
+    /// Check whether this slot is a placeholder rather than a committed value
+    pub fn is_placeholder(&self) -> bool {
+        self.placeholder
+    }
+
     /// Get the Merkle root at commitment time
     pub fn merkle_root(&self) -> &[u8] {
         &self.merkle_root
     }
+
+    /// Get the Merkle root from just before this commitment
+    pub fn prev_root(&self) -> &[u8] {
+        &self.prev_root
+    }
+
+    /// Get the MIME type of the value, if known
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
 }
 
 #[cfg(test)]
@@ -61,4 +203,36 @@ mod tests {
         
         assert_eq!(commitment, deserialized);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_leaf_hash_matches_tree_leaf() {
+        use crate::models::merkle::{MerkleNode, MerkleTree};
+
+        let commitment = Commitment::new(1, b"data1".to_vec(), Vec::new());
+        let tree = MerkleTree::from_leaves(
+            ["data0", "data1", "data2"].map(|v| MerkleNode::new_leaf(v.as_bytes())).to_vec(),
+        );
+        let (_, leaf) = tree.enumerate_leaves().nth(1).unwrap();
+        assert_eq!(commitment.leaf_hash(), leaf);
+
+        let mode = LeafHashMode::LengthPrefixed;
+        assert_eq!(
+            commitment.leaf_hash_with_mode(mode),
+            MerkleNode::new_leaf_with_mode(1, b"data1", mode).hash
+        );
+        assert_ne!(commitment.leaf_hash_with_mode(mode), commitment.leaf_hash());
+    }
+
+    #[test]
+    fn test_commitment_expiry() {
+        let commitment = Commitment::new(0, vec![1], vec![2]);
+        assert!(!commitment.is_expired_at(u64::MAX));
+
+        let commitment = commitment.with_metadata(CommitmentMetadata {
+            expires_at: Some(100),
+            ..Default::default()
+        });
+        assert!(!commitment.is_expired_at(99));
+        assert!(commitment.is_expired_at(100));
+    }
+}
@@ -0,0 +1,250 @@
+//! Key→value maps committed as a tree of leaves sorted by key, so a key's value
+//! can be proved present and an absent key proved missing by its two neighbours
+
+use crate::crypto::encoding::hash_fields;
+use crate::crypto::proof::{generate_proof, MerkleProof};
+use crate::error::AppError;
+use crate::models::merkle::{MerkleNode, MerkleTree};
+use serde::{Deserialize, Serialize};
+
+/// One key and the value committed under it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyedEntry {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// Leaf hash of an entry: SHA-256 of the key and value in prefix-free encoding, so
+/// bytes can't shift between key and value
+pub fn keyed_leaf_hash(key: &[u8], value: &[u8]) -> Vec<u8> {
+    hash_fields(&[key, value])
+}
+
+/// Depth of every leaf in a tree of `leaf_count` leaves; odd levels duplicate their
+/// last node, so all leaves sit at the same depth
+fn leaf_depth(leaf_count: usize) -> usize {
+    match leaf_count {
+        0 | 1 => 0,
+        n => (n - 1).ilog2() as usize + 1,
+    }
+}
+
+/// A committed map, its entries kept sorted by key
+#[derive(Debug, Clone)]
+pub struct KeyedMap {
+    entries: Vec<KeyedEntry>,
+    tree: MerkleTree,
+}
+
+impl KeyedMap {
+    /// Sort `entries` by key and build the tree over them. Duplicate keys are a
+    /// `Conflict`; an empty map is rejected.
+    pub fn from_entries(mut entries: Vec<KeyedEntry>) -> Result<Self, AppError> {
+        if entries.is_empty() {
+            return Err(AppError::InvalidInput("Map must have at least one entry".to_string()));
+        }
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].key == pair[1].key) {
+            return Err(AppError::Conflict(format!(
+                "Duplicate key {}",
+                hex::encode(&pair[0].key)
+            )));
+        }
+
+        let leaves = entries
+            .iter()
+            .map(|e| MerkleNode::from_leaf_hash(keyed_leaf_hash(&e.key, &e.value)))
+            .collect();
+        Ok(Self {
+            tree: MerkleTree::from_leaves(leaves),
+            entries,
+        })
+    }
+
+    /// Get the root hash
+    pub fn root(&self) -> Vec<u8> {
+        self.tree.root_hash_or_empty()
+    }
+
+    /// Get the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the map has no entries (never true for a built map)
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Prove `key`'s value, or that `key` is absent by proving the entries either side
+    pub fn generate_proof_for_key(&self, key: &[u8]) -> KeyProof {
+        match self.entries.binary_search_by(|e| e.key.as_slice().cmp(key)) {
+            Ok(index) => KeyProof::Present(self.entry_proof(index)),
+            Err(insert_at) => KeyProof::Absent {
+                key: key.to_vec(),
+                left: insert_at.checked_sub(1).map(|index| self.entry_proof(index)),
+                right: (insert_at < self.len()).then(|| self.entry_proof(insert_at)),
+            },
+        }
+    }
+
+    fn entry_proof(&self, index: usize) -> EntryProof {
+        let entry = &self.entries[index];
+        let root = self.tree.root().expect("a built map is never empty");
+        let path = generate_proof(root, index, self.len());
+        EntryProof {
+            key: entry.key.clone(),
+            value: entry.value.clone(),
+            proof: MerkleProof::new(index, keyed_leaf_hash(&entry.key, &entry.value), path, self.root())
+                .with_prehashed(true),
+        }
+    }
+}
+
+/// An entry with a proof of its leaf; `proof.value` is the entry's leaf hash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryProof {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub proof: MerkleProof,
+}
+
+impl EntryProof {
+    /// Check the entry is the leaf at `proof.index` of the map with `root` and
+    /// `leaf_count`, returning that position. The position is read from the path's
+    /// sides rather than trusted, so neighbours can't be faked.
+    fn verify(&self, root: &[u8], leaf_count: usize) -> Option<usize> {
+        let position = self.proof.position();
+        let valid = self.proof.prehashed
+            && self.proof.chain.is_empty()
+            && self.proof.value == keyed_leaf_hash(&self.key, &self.value)
+            && self.proof.root == root
+            && self.proof.proof.len() == leaf_depth(leaf_count)
+            && position == self.proof.index
+            && position < leaf_count
+            && self.proof.verify();
+        valid.then_some(position)
+    }
+}
+
+/// Proof that a key maps to a value, or that it isn't in the map
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KeyProof {
+    Present(EntryProof),
+    /// The entries sorting immediately before and after `key`; one is missing when
+    /// `key` sorts before the first entry or after the last
+    Absent {
+        key: Vec<u8>,
+        left: Option<EntryProof>,
+        right: Option<EntryProof>,
+    },
+}
+
+impl KeyProof {
+    /// Verify against a map's published root and entry count
+    pub fn verify(&self, root: &[u8], leaf_count: usize) -> bool {
+        match self {
+            Self::Present(entry) => entry.verify(root, leaf_count).is_some(),
+            Self::Absent { key, left, right } => {
+                let left = left.as_ref().map(|e| (e.verify(root, leaf_count), &e.key));
+                let right = right.as_ref().map(|e| (e.verify(root, leaf_count), &e.key));
+                match (left, right) {
+                    (Some((Some(l), left_key)), Some((Some(r), right_key))) => {
+                        r == l + 1 && left_key < key && key < right_key
+                    }
+                    (Some((Some(l), left_key)), None) => l + 1 == leaf_count && left_key < key,
+                    (None, Some((Some(r), right_key))) => r == 0 && key < right_key,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(keys: &[&str]) -> KeyedMap {
+        KeyedMap::from_entries(
+            keys.iter()
+                .map(|k| KeyedEntry {
+                    key: k.as_bytes().to_vec(),
+                    value: format!("value-{}", k).into_bytes(),
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_present_key_proof() {
+        let map = map(&["carol", "alice", "erin", "bob", "dave"]);
+        let (root, count) = (map.root(), map.len());
+
+        let proof = map.generate_proof_for_key(b"carol");
+        let KeyProof::Present(entry) = &proof else {
+            panic!("expected a membership proof, got {:?}", proof);
+        };
+        assert_eq!(entry.value, b"value-carol".to_vec());
+        assert_eq!(entry.proof.index, 2);
+        assert!(proof.verify(&root, count));
+
+        // A different value under the same key doesn't verify
+        let mut forged = entry.clone();
+        forged.value = b"value-mallory".to_vec();
+        assert!(!KeyProof::Present(forged).verify(&root, count));
+        assert!(!proof.verify(&root, count - 2));
+    }
+
+    #[test]
+    fn test_absent_key_proof() {
+        let map = map(&["alice", "bob", "dave", "erin", "frank"]);
+        let (root, count) = (map.root(), map.len());
+
+        for key in ["carol", "aaron", "zoe"] {
+            let proof = map.generate_proof_for_key(key.as_bytes());
+            assert!(matches!(proof, KeyProof::Absent { .. }), "{}", key);
+            assert!(proof.verify(&root, count), "{}", key);
+        }
+
+        // Neighbours that aren't adjacent can't hide a present key
+        let KeyProof::Present(bob) = map.generate_proof_for_key(b"bob") else { unreachable!() };
+        let KeyProof::Present(erin) = map.generate_proof_for_key(b"erin") else { unreachable!() };
+        let gap = KeyProof::Absent {
+            key: b"dave".to_vec(),
+            left: Some(bob.clone()),
+            right: Some(erin),
+        };
+        assert!(!gap.verify(&root, count));
+
+        // Nor can a claimed index that disagrees with the path
+        let mut shifted = bob;
+        shifted.proof.index = 2;
+        let KeyProof::Present(dave) = map.generate_proof_for_key(b"dave") else { unreachable!() };
+        let moved = KeyProof::Absent {
+            key: b"carol".to_vec(),
+            left: Some(shifted),
+            right: Some(dave),
+        };
+        assert!(!moved.verify(&root, count));
+    }
+
+    #[test]
+    fn test_entry_boundaries_are_unambiguous() {
+        assert_ne!(keyed_leaf_hash(b"ab", b"c"), keyed_leaf_hash(b"a", b"bc"));
+        assert_ne!(keyed_leaf_hash(b"", b"abc"), keyed_leaf_hash(b"abc", b""));
+    }
+
+    #[test]
+    fn test_duplicate_keys_conflict() {
+        let entry = |value: &[u8]| KeyedEntry {
+            key: b"key".to_vec(),
+            value: value.to_vec(),
+        };
+        let result = KeyedMap::from_entries(vec![entry(b"a"), entry(b"b")]);
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+        assert!(matches!(KeyedMap::from_entries(Vec::new()), Err(AppError::InvalidInput(_))));
+    }
+}
@@ -1,5 +1,85 @@
+use crate::crypto::hash::{sha256, HashAlgorithm, Rfc6962Hasher, Sha256Hasher, Sha256Stream};
+use crate::crypto::proof::{generate_range_proof, BlockProof};
+use crate::crypto::Hasher;
+use crate::error::AppError;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+
+/// Canonical root of an empty tree: SHA-256 of the empty string
+pub const EMPTY_ROOT: [u8; 32] = [
+    0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9, 0x24,
+    0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+];
+
+/// Leaf hash of a null slot (a gap placeholder or a tombstone): SHA-256 of the
+/// domain tag `merkle_tree:empty-leaf`. Deliberately not SHA-256 of the empty
+/// string, which is what a committed empty value hashes to, so a null slot can't
+/// be passed off as a commitment to empty bytes or the other way round.
+pub const EMPTY_LEAF_HASH: [u8; 32] = [
+    0x03, 0xee, 0xd5, 0x27, 0xd5, 0x1a, 0xbf, 0x82, 0x4f, 0xc4, 0xf7, 0xaa, 0x62, 0xb3, 0x53, 0xc6,
+    0x1c, 0x2f, 0xd6, 0x65, 0xc9, 0x26, 0xe5, 0x07, 0x00, 0xfd, 0xae, 0xe7, 0x06, 0xde, 0xd1, 0x61,
+];
+
+/// How a tree with exactly one leaf forms its root
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SingleLeafMode {
+    /// The leaf is the root, so the root hash equals the leaf hash (the default)
+    #[default]
+    LeafIsRoot,
+    /// The leaf is paired with itself under a parent, giving a root distinct from the leaf
+    DuplicateToParent,
+}
+
+/// How a leaf hash is computed from its value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeafHashMode {
+    /// SHA-256(value) (the default)
+    #[default]
+    Plain,
+    /// SHA-256(len || value) with the length as 8 bytes big-endian, so values that
+    /// concatenate to the same bytes still hash differently
+    LengthPrefixed,
+    /// SHA-256(index || value) with the leaf's index as 8 bytes big-endian, so a
+    /// leaf can't be replayed at another position
+    IndexBound,
+}
+
+impl LeafHashMode {
+    /// True for the default mode
+    pub fn is_plain(&self) -> bool {
+        *self == Self::Plain
+    }
+
+    /// Hash `value` into the leaf hash for slot `index`
+    pub fn hash(self, index: usize, value: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Plain => sha256(&[value]),
+            Self::LengthPrefixed => sha256(&[&(value.len() as u64).to_be_bytes(), value]),
+            Self::IndexBound => sha256(&[&(index as u64).to_be_bytes(), value]),
+        }
+    }
+
+    /// Hash `value` into the leaf hash for slot `index` with `H` in place of SHA-256
+    pub fn hash_using<H: Hasher>(self, index: usize, value: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Plain => H::hash_leaf(value),
+            Self::LengthPrefixed => H::hash_leaf(&[&(value.len() as u64).to_be_bytes(), value].concat()),
+            Self::IndexBound => H::hash_leaf(&[&(index as u64).to_be_bytes(), value].concat()),
+        }
+    }
+
+    /// Incremental hasher for a value fed in pieces; None for length-prefixed leaves
+    /// when the total length isn't known up front, and always for index-bound leaves,
+    /// whose slot isn't known until the value is stored
+    pub fn hasher(self, len: Option<u64>) -> Option<Sha256Stream> {
+        let mut hasher = Sha256Stream::new();
+        match self {
+            Self::Plain => {}
+            Self::LengthPrefixed => hasher.update(&len?.to_be_bytes()),
+            Self::IndexBound => return None,
+        }
+        Some(hasher)
+    }
+}
 
 /// A node in the Merkle tree
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -12,22 +92,53 @@ pub struct MerkleNode {
 impl MerkleNode {
     /// Create a new leaf node from raw data
     pub fn new_leaf(data: &[u8]) -> Self {
-        let mut hasher = Sha256::new();
-        hasher.update(data);
+        Self::new_leaf_using::<Sha256Hasher>(data)
+    }
+
+    /// Create a new leaf node from raw data, hashed with `H`
+    pub fn new_leaf_using<H: Hasher>(data: &[u8]) -> Self {
+        Self::from_leaf_hash(H::hash_leaf(data))
+    }
+
+    /// Create the leaf for slot `index`, hashing `data` per `mode`
+    pub fn new_leaf_with_mode(index: usize, data: &[u8], mode: LeafHashMode) -> Self {
+        Self::from_leaf_hash(mode.hash(index, data))
+    }
+
+    /// Create a leaf node from an already-computed leaf hash
+    pub fn from_leaf_hash(hash: Vec<u8>) -> Self {
         Self {
-            hash: hasher.finalize().to_vec(),
+            hash,
             left: None,
             right: None,
         }
     }
 
+    /// Create the leaf for a null slot
+    pub fn empty_leaf() -> Self {
+        Self::from_leaf_hash(EMPTY_LEAF_HASH.to_vec())
+    }
+
+    /// Create the leaf for a value committed at `index`, taking it as the leaf hash
+    /// in hash-only storage and otherwise hashing it per `mode`. Null slots have no
+    /// value; see `Commitment::leaf`.
+    pub fn committed_leaf(index: usize, value: &[u8], hash_only: bool, mode: LeafHashMode) -> Self {
+        if hash_only {
+            Self::from_leaf_hash(value.to_vec())
+        } else {
+            Self::new_leaf_with_mode(index, value, mode)
+        }
+    }
+
     /// Create a new parent node from two children
     pub fn new_parent(left: MerkleNode, right: MerkleNode) -> Self {
-        let mut hasher = Sha256::new();
-        hasher.update(&left.hash);
-        hasher.update(&right.hash);
+        Self::new_parent_using::<Sha256Hasher>(left, right)
+    }
+
+    /// Create a new parent node from two children, hashed with `H`
+    pub fn new_parent_using<H: Hasher>(left: MerkleNode, right: MerkleNode) -> Self {
         Self {
-            hash: hasher.finalize().to_vec(),
+            hash: H::hash_nodes(&left.hash, &right.hash),
             left: Some(Box::new(left)),
             right: Some(Box::new(right)),
         }
@@ -42,6 +153,47 @@ impl MerkleNode {
     pub fn is_leaf(&self) -> bool {
         self.left.is_none() && self.right.is_none()
     }
+
+    /// Recompute every internal hash from its children and check it matches the
+    /// stored one. Leaf hashes are trusted; a missing right child is treated as a
+    /// duplicate of the left, as in proof generation.
+    pub fn verify_subtree(&self) -> bool {
+        self.verify_subtree_using::<Sha256Hasher>()
+    }
+
+    /// Check the subtree like `verify_subtree`, for a tree built with `H`
+    pub fn verify_subtree_using<H: Hasher>(&self) -> bool {
+        let Some(left) = self.left.as_deref() else {
+            // A right child without a left one can't come from tree building
+            return self.right.is_none();
+        };
+        let right = self.right.as_deref().unwrap_or(left);
+
+        if H::hash_nodes(&left.hash, &right.hash) != self.hash {
+            return false;
+        }
+
+        left.verify_subtree_using::<H>() && (std::ptr::eq(left, right) || right.verify_subtree_using::<H>())
+    }
+}
+
+/// Owned root metadata, without the subtree behind it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootSummary {
+    pub hash: Vec<u8>,
+    pub leaf_count: usize,
+    pub height: usize,
+}
+
+/// How many leaf proofs carry a node as a sibling
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiblingFanout {
+    /// Levels above the leaves (0 is the leaf level)
+    pub level: usize,
+    /// Position of the node within its level
+    pub position: usize,
+    pub hash: Vec<u8>,
+    pub fanout: usize,
 }
 
 /// Merkle tree structure
@@ -49,6 +201,7 @@ impl MerkleNode {
 pub struct MerkleTree {
     root: Option<MerkleNode>,
     leaf_count: usize,
+    algorithm: HashAlgorithm,
 }
 
 impl MerkleTree {
@@ -57,14 +210,134 @@ impl MerkleTree {
         Self {
             root: None,
             leaf_count: 0,
+            algorithm: HashAlgorithm::Sha256,
         }
     }
 
-    /// Build a Merkle tree from a list of leaf nodes
+    /// Build a Merkle tree from a list of leaf nodes. A single leaf is its own root;
+    /// see `from_leaves_with_mode` to hash it with itself instead.
     pub fn from_leaves(leaves: Vec<MerkleNode>) -> Self {
+        Self::from_leaves_with_mode(leaves, SingleLeafMode::default())
+    }
+
+    /// Build a Merkle tree from a list of leaf nodes, forming a one-leaf root per `mode`
+    pub fn from_leaves_with_mode(leaves: Vec<MerkleNode>, mode: SingleLeafMode) -> Self {
+        Self::from_leaves_using::<Sha256Hasher>(leaves, mode)
+    }
+
+    /// Build a tree over raw `values` with RFC 6962 domain separation: leaves hash
+    /// 0x00 || value and nodes 0x01 || left || right, so no interior node can pass as
+    /// a leaf. The shape still duplicates the last node of odd levels; see
+    /// `crypto::ct` for full RFC 6962 trees. Existing roots are unaffected.
+    pub fn from_leaves_rfc6962<V: AsRef<[u8]>>(values: &[V]) -> Self {
+        let leaves = values
+            .iter()
+            .map(|v| MerkleNode::new_leaf_using::<Rfc6962Hasher>(v.as_ref()))
+            .collect();
+        Self::from_leaves_using::<Rfc6962Hasher>(leaves, SingleLeafMode::default())
+    }
+
+    /// Build a Merkle tree like `from_leaves_with_mode`, joining nodes with `H`. The
+    /// leaves must already be hashed with `H` too (see `MerkleNode::new_leaf_using`).
+    pub fn from_leaves_using<H: Hasher>(leaves: Vec<MerkleNode>, mode: SingleLeafMode) -> Self {
         let leaf_count = leaves.len();
-        let root = Self::build_tree(leaves);
-        Self { root, leaf_count }
+        let mut root = Self::build_tree::<H>(leaves);
+        if leaf_count == 1 && mode == SingleLeafMode::DuplicateToParent {
+            root = root.map(|leaf| MerkleNode::new_parent_using::<H>(leaf.clone(), leaf));
+        }
+        Self {
+            root,
+            leaf_count,
+            algorithm: H::ALGORITHM,
+        }
+    }
+
+    /// Build a tree like `from_leaves_with_mode`, refusing more than `max_leaves`
+    /// leaves rather than allocating the levels above them
+    pub fn try_from_leaves(
+        leaves: Vec<MerkleNode>,
+        mode: SingleLeafMode,
+        max_leaves: usize,
+    ) -> Result<Self, AppError> {
+        if leaves.len() > max_leaves {
+            return Err(AppError::InvalidInput(format!(
+                "Tree would have {} leaves (max {})",
+                leaves.len(),
+                max_leaves
+            )));
+        }
+        Ok(Self::from_leaves_with_mode(leaves, mode))
+    }
+
+    /// Hash function the tree was built with
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Append a leaf, rehashing only the nodes on the path to it instead of
+    /// rebuilding the tree. The root matches `from_leaves` over the same leaves;
+    /// padding on the new path is left as a missing right child, which every
+    /// traversal already reads as a copy of the left.
+    pub fn push_leaf(&mut self, leaf: MerkleNode) {
+        let index = self.leaf_count;
+        let height = self.height();
+        self.leaf_count += 1;
+        let Some(root) = self.root.take() else {
+            self.root = Some(leaf);
+            return;
+        };
+
+        let width = 1usize << (height - 1);
+        self.root = Some(if index < width {
+            self.insert(root, index, width, leaf)
+        } else {
+            // Full: the old root becomes the left half of a tree twice as wide
+            let right = self.padded(leaf, width);
+            self.join(root, Some(right))
+        });
+    }
+
+    /// Put `leaf` in slot `index` of the empty tail of `node`, which spans `width` slots
+    fn insert(&self, mut node: MerkleNode, index: usize, width: usize, leaf: MerkleNode) -> MerkleNode {
+        if width == 1 {
+            return leaf;
+        }
+        let half = width / 2;
+        let left = *node.left.take().expect("interior nodes have a left child");
+        if index < half {
+            // Nothing lives right of the new leaf, so the right half stays padding
+            let left = self.insert(left, index, half, leaf);
+            return self.join(left, None);
+        }
+        let right = match node.right.take() {
+            Some(right) if index > half => self.insert(*right, index - half, half, leaf),
+            _ => self.padded(leaf, half),
+        };
+        self.join(left, Some(right))
+    }
+
+    /// Subtree spanning `width` slots with `leaf` first and padding after it
+    fn padded(&self, leaf: MerkleNode, width: usize) -> MerkleNode {
+        let mut node = leaf;
+        let mut span = 1;
+        while span < width {
+            node = self.join(node, None);
+            span *= 2;
+        }
+        node
+    }
+
+    /// Parent of `left` and `right`, hashed with the tree's algorithm; a missing
+    /// right child duplicates the left
+    fn join(&self, left: MerkleNode, right: Option<MerkleNode>) -> MerkleNode {
+        let hash = self
+            .algorithm
+            .hash_nodes(&left.hash, &right.as_ref().unwrap_or(&left).hash);
+        MerkleNode {
+            hash,
+            left: Some(Box::new(left)),
+            right: right.map(Box::new),
+        }
     }
 
     /// Get the root node
@@ -72,18 +345,273 @@ impl MerkleTree {
         self.root.as_ref()
     }
 
+    /// Get the root hash, leaf count and height, or None for an empty tree
+    pub fn root_summary(&self) -> Option<RootSummary> {
+        self.root.as_ref().map(|root| RootSummary {
+            hash: root.hash.clone(),
+            leaf_count: self.leaf_count,
+            height: self.height(),
+        })
+    }
+
     /// Get the root hash
     pub fn root_hash(&self) -> Option<Vec<u8>> {
         self.root.as_ref().map(|r| r.hash.clone())
     }
 
+    /// Get the root hash, or the canonical empty root for an empty tree
+    pub fn root_hash_or_empty(&self) -> Vec<u8> {
+        self.root_hash().unwrap_or_else(|| EMPTY_ROOT.to_vec())
+    }
+
     /// Get the number of leaves in the tree
     pub fn leaf_count(&self) -> usize {
         self.leaf_count
     }
 
+    /// Get the number of leaves in the tree (same as `leaf_count`)
+    pub fn len(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Check whether the tree has no leaves
+    pub fn is_empty(&self) -> bool {
+        self.leaf_count == 0
+    }
+
+    /// Get the leaf node at `index`, walking down from the root
+    pub fn leaf(&self, index: usize) -> Option<&MerkleNode> {
+        self.walk_to(index)?.pop()
+    }
+
+    /// Each real leaf's index and hash, left to right, for rebuilding hash-to-index maps
+    pub fn enumerate_leaves(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        // Copies made by odd-level duplication all sit right of the real leaves,
+        // so a left-to-right walk cut off at `leaf_count` skips them
+        let mut stack: Vec<&MerkleNode> = self.root.iter().collect();
+        std::iter::from_fn(move || {
+            while let Some(node) = stack.pop() {
+                if node.is_leaf() {
+                    return Some(node.hash.as_slice());
+                }
+                stack.extend(node.right.as_deref());
+                stack.extend(node.left.as_deref());
+            }
+            None
+        })
+        .take(self.leaf_count)
+        .enumerate()
+    }
+
+    /// Prove the `block_len` leaves from `block_start` are in the tree with one
+    /// proof, carrying the block's own root alongside its range proof
+    pub fn generate_block_proof(&self, block_start: usize, block_len: usize) -> Result<BlockProof, AppError> {
+        let end = block_start.checked_add(block_len).filter(|&end| block_len > 0 && end <= self.leaf_count);
+        let (Some(end), Some(root)) = (end, self.root.as_ref()) else {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid block of {} leaves from {} for {} leaves",
+                block_len, block_start, self.leaf_count
+            )));
+        };
+
+        let leaf_hashes: Vec<Vec<u8>> = self
+            .enumerate_leaves()
+            .skip(block_start)
+            .take(block_len)
+            .map(|(_, hash)| hash.to_vec())
+            .collect();
+        let block_root = BlockProof::compute_block_root(&leaf_hashes, self.algorithm)
+            .ok_or(AppError::TreeBuildError("Failed to hash block".to_string()))?;
+        Ok(BlockProof {
+            block_start,
+            total_leaves: self.leaf_count,
+            leaf_hashes,
+            block_root,
+            boundary: generate_range_proof(root, block_start, end, self.leaf_count),
+            root: root.hash.clone(),
+            algorithm: self.algorithm,
+        })
+    }
+
+    /// Hashes of the nodes on the path from the root down to the leaf at `index`,
+    /// root first and leaf hash last
+    pub fn node_path(&self, index: usize) -> Option<Vec<Vec<u8>>> {
+        let nodes = self.walk_to(index)?;
+        Some(nodes.into_iter().map(|node| node.hash.clone()).collect())
+    }
+
+    /// Hash of the node at `level_order_index` in the heap layout of the perfect tree
+    /// (0 is the root, node i's children are 2i+1 and 2i+2). Positions past the
+    /// depth, or covering only duplicated padding, are None.
+    pub fn node_at(&self, level_order_index: usize) -> Option<Vec<u8>> {
+        let slot = level_order_index.checked_add(1)?;
+        let level = slot.ilog2() as usize;
+        let position = slot - (1 << level);
+        let height = self.height();
+        if level >= height || position << (height - 1 - level) >= self.leaf_count {
+            return None;
+        }
+
+        // The position's bits, high first, pick the side to descend at each level
+        let mut node = self.root.as_ref()?;
+        for bit in (0..level).rev() {
+            let left = node.left.as_deref()?;
+            node = match (position >> bit) & 1 {
+                0 => left,
+                _ => node.right.as_deref().unwrap_or(left),
+            };
+        }
+        Some(node.hash.clone())
+    }
+
+    /// Nodes from the root down to the leaf at `index`
+    fn walk_to(&self, index: usize) -> Option<Vec<&MerkleNode>> {
+        if index >= self.leaf_count {
+            return None;
+        }
+
+        // The tree is perfect over 2^(height - 1) slots; halve the span at each level
+        let mut node = self.root.as_ref()?;
+        let mut path = vec![node];
+        let mut width = 1usize << (self.height() - 1);
+        let mut begin = 0;
+        while let Some(left) = node.left.as_deref() {
+            width /= 2;
+            if index < begin + width {
+                node = left;
+            } else {
+                begin += width;
+                node = node.right.as_deref().unwrap_or(left);
+            }
+            path.push(node);
+        }
+        Some(path)
+    }
+
+    /// Indices of leaves that differ between `self` and `other`, ascending. Subtrees
+    /// with equal hashes are skipped, so the walk only descends along changed paths.
+    /// Leaves present in just one tree count as changed.
+    pub fn diff(&self, other: &MerkleTree) -> Vec<usize> {
+        fn walk(a: &MerkleNode, b: &MerkleNode, begin: usize, width: usize, shared: usize, out: &mut Vec<usize>) {
+            if begin >= shared || a.hash == b.hash {
+                return;
+            }
+            match (a.left.as_deref(), b.left.as_deref()) {
+                (Some(a_left), Some(b_left)) => {
+                    let half = width / 2;
+                    walk(a_left, b_left, begin, half, shared, out);
+                    let a_right = a.right.as_deref().unwrap_or(a_left);
+                    let b_right = b.right.as_deref().unwrap_or(b_left);
+                    walk(a_right, b_right, begin + half, half, shared, out);
+                }
+                _ => out.push(begin),
+            }
+        }
+
+        let shared = self.leaf_count.min(other.leaf_count);
+        let mut changed = Vec::new();
+        if let (Some(mut a), Some(mut b)) = (self.root.as_ref(), other.root.as_ref()) {
+            // The taller tree's leftmost subtree at the shorter one's height spans the
+            // same slots, which hold every leaf the trees share
+            let (mut a_height, mut b_height) = (self.height(), other.height());
+            while a_height > b_height {
+                a = a.left.as_deref().expect("interior nodes have a left child");
+                a_height -= 1;
+            }
+            while b_height > a_height {
+                b = b.left.as_deref().expect("interior nodes have a left child");
+                b_height -= 1;
+            }
+            walk(a, b, 0, 1 << (a_height - 1), shared, &mut changed);
+        }
+        changed.extend(shared..self.leaf_count.max(other.leaf_count));
+        changed
+    }
+
+    /// For every node below the root, the number of leaf proofs that include it as a
+    /// sibling, bottom level first. A node is the sibling of every real leaf under its
+    /// pair; a last node duplicated on an odd level is its own sibling.
+    pub fn sibling_fanout(&self) -> Vec<SiblingFanout> {
+        // Real nodes per level, top down; duplicated copies lie past each level's end
+        let mut levels: Vec<Vec<&MerkleNode>> = Vec::new();
+        let mut level: Vec<&MerkleNode> = self.root.iter().collect();
+        while !level.is_empty() {
+            let next = level
+                .iter()
+                .filter_map(|n| n.left.as_deref().map(|left| [left, n.right.as_deref().unwrap_or(left)]))
+                .flatten()
+                .collect();
+            levels.push(level);
+            level = next;
+        }
+        levels.reverse();
+
+        let mut counts = Vec::with_capacity(levels.len());
+        let mut count = self.leaf_count;
+        for _ in &levels {
+            counts.push(count);
+            count = count.div_ceil(2);
+        }
+
+        let leaves_under = |level: usize, position: usize| {
+            let begin = position << level;
+            self.leaf_count.min(begin + (1 << level)).saturating_sub(begin)
+        };
+        let mut fanout = Vec::new();
+        for (level, nodes) in levels.iter().enumerate().take(levels.len().saturating_sub(1)) {
+            for (position, node) in nodes.iter().enumerate().take(counts[level]) {
+                let sibling = position ^ 1;
+                let paired = if sibling < counts[level] { sibling } else { position };
+                fanout.push(SiblingFanout {
+                    level,
+                    position,
+                    hash: node.hash.clone(),
+                    fanout: leaves_under(level, paired),
+                });
+            }
+        }
+        fanout
+    }
+
+    /// Get the number of levels in the tree, counting the leaf level (0 when empty)
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut node = self.root.as_ref();
+        while let Some(n) = node {
+            height += 1;
+            node = n.left.as_deref();
+        }
+        height
+    }
+
+    /// Draw the tree with box-drawing characters, one node per line labelled with the
+    /// first 8 hex characters of its hash
+    pub fn render_ascii(&self) -> String {
+        fn render(node: &MerkleNode, prefix: &str, out: &mut String) {
+            let children: Vec<&MerkleNode> = node.left.iter().chain(node.right.iter()).map(|c| &**c).collect();
+            for (i, child) in children.iter().enumerate() {
+                let last = i + 1 == children.len();
+                let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+                out.push_str(&format!("{}{}{}\n", prefix, branch, label(child)));
+                render(child, &format!("{}{}", prefix, indent), out);
+            }
+        }
+
+        fn label(node: &MerkleNode) -> String {
+            let hex = hex::encode(&node.hash);
+            hex[..hex.len().min(8)].to_string()
+        }
+
+        let Some(root) = self.root.as_ref() else {
+            return "(empty tree)\n".to_string();
+        };
+        let mut out = format!("{}\n", label(root));
+        render(root, "", &mut out);
+        out
+    }
+
     /// Build the tree from leaves (internal recursive function)
-    fn build_tree(leaves: Vec<MerkleNode>) -> Option<MerkleNode> {
+    fn build_tree<H: Hasher>(leaves: Vec<MerkleNode>) -> Option<MerkleNode> {
         if leaves.is_empty() {
             return None;
         }
@@ -95,13 +623,13 @@ impl MerkleTree {
 
             for chunk in current_level.chunks(2) {
                 if chunk.len() == 2 {
-                    next_level.push(MerkleNode::new_parent(
+                    next_level.push(MerkleNode::new_parent_using::<H>(
                         chunk[0].clone(),
                         chunk[1].clone(),
                     ));
                 } else {
                     // Odd number of nodes, duplicate the last one
-                    next_level.push(MerkleNode::new_parent(
+                    next_level.push(MerkleNode::new_parent_using::<H>(
                         chunk[0].clone(),
                         chunk[0].clone(),
                     ));
@@ -124,6 +652,9 @@ impl Default for MerkleTree {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::proof::generate_proof;
+    use crate::models::commitment::Commitment;
+    use sha2::{Digest, Sha256};
 
     #[test]
     fn test_leaf_creation() {
@@ -156,6 +687,7 @@ mod tests {
         let tree = MerkleTree::from_leaves(leaves);
         
         assert_eq!(tree.leaf_count(), 3);
+        assert_eq!(tree.height(), 3);
         assert!(tree.root().is_some());
         assert!(tree.root_hash().is_some());
     }
@@ -165,7 +697,329 @@ mod tests {
         let tree = MerkleTree::new();
         
         assert_eq!(tree.leaf_count(), 0);
+        assert_eq!(tree.height(), 0);
         assert!(tree.root().is_none());
         assert!(tree.root_hash().is_none());
+        assert_eq!(tree.root_hash_or_empty(), EMPTY_ROOT.to_vec());
+    }
+
+    #[test]
+    fn test_single_leaf_modes() {
+        let leaf = MerkleNode::new_leaf(b"only");
+
+        let tree = MerkleTree::from_leaves(vec![leaf.clone()]);
+        assert_eq!(tree.root_hash(), Some(leaf.hash.clone()));
+        assert_eq!(tree.height(), 1);
+
+        let tree = MerkleTree::from_leaves_with_mode(vec![leaf.clone()], SingleLeafMode::DuplicateToParent);
+        let expected = MerkleNode::new_parent(leaf.clone(), leaf.clone());
+        assert_eq!(tree.root_hash(), Some(expected.hash));
+        assert_eq!(tree.leaf_count(), 1);
+        assert_eq!(tree.height(), 2);
+
+        // Larger trees are unaffected by the mode
+        let leaves = vec![leaf.clone(), MerkleNode::new_leaf(b"other")];
+        assert_eq!(
+            MerkleTree::from_leaves_with_mode(leaves.clone(), SingleLeafMode::DuplicateToParent).root_hash(),
+            MerkleTree::from_leaves(leaves).root_hash()
+        );
+    }
+
+    #[test]
+    fn test_leaf_lookup() {
+        for count in 1..=9 {
+            let leaves: Vec<MerkleNode> = (0..count)
+                .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+                .collect();
+            let tree = MerkleTree::from_leaves(leaves.clone());
+            for (index, leaf) in leaves.iter().enumerate() {
+                assert_eq!(tree.leaf(index), Some(leaf), "count {} index {}", count, index);
+            }
+            assert!(tree.leaf(count).is_none());
+        }
+
+        let leaf = MerkleNode::new_leaf(b"only");
+        let tree = MerkleTree::from_leaves_with_mode(vec![leaf.clone()], SingleLeafMode::DuplicateToParent);
+        assert_eq!(tree.leaf(0), Some(&leaf));
+        assert!(MerkleTree::new().leaf(0).is_none());
+    }
+
+    #[test]
+    fn test_len_and_is_empty() {
+        let empty = MerkleTree::new();
+        assert_eq!(empty.len(), 0);
+        assert!(empty.is_empty());
+
+        let tree = MerkleTree::from_leaves(vec![
+            MerkleNode::new_leaf(b"data1"),
+            MerkleNode::new_leaf(b"data2"),
+        ]);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.len(), tree.leaf_count());
+        assert!(!tree.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_empty_root_is_sha256_of_empty_string() {
+        assert_eq!(EMPTY_ROOT.to_vec(), Sha256::digest(b"").to_vec());
+    }
+
+    #[test]
+    fn test_verify_subtree() {
+        let leaves: Vec<MerkleNode> = (0..5)
+            .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+            .collect();
+        let tree = MerkleTree::from_leaves(leaves);
+        let root = tree.root().unwrap();
+        assert!(root.verify_subtree());
+
+        // Tamper with an internal node two levels down
+        let mut tampered = root.clone();
+        tampered.left.as_mut().unwrap().right.as_mut().unwrap().hash[0] ^= 0xff;
+        assert!(!tampered.verify_subtree());
+
+        // A tampered leaf is caught by its parent
+        let mut tampered_leaf = root.clone();
+        let node = tampered_leaf.left.as_mut().unwrap().left.as_mut().unwrap();
+        node.left.as_mut().unwrap().hash = vec![0; 32];
+        assert!(!tampered_leaf.verify_subtree());
+
+        assert!(MerkleNode::new_leaf(b"single").verify_subtree());
+    }
+
+    #[test]
+    fn test_length_prefixed_leaves() {
+        let (left, right) = (MerkleNode::new_leaf(b"left"), MerkleNode::new_leaf(b"right"));
+        let parent = MerkleNode::new_parent(left.clone(), right.clone());
+        let spliced = [left.hash.clone(), right.hash.clone()].concat();
+
+        // A plain leaf over two concatenated hashes is indistinguishable from their parent
+        assert_eq!(MerkleNode::new_leaf(&spliced).hash, parent.hash);
+        let prefixed = MerkleNode::new_leaf_with_mode(0, &spliced, LeafHashMode::LengthPrefixed);
+        assert_ne!(prefixed.hash, parent.hash);
+
+        // The prefix is the length as 8 bytes big-endian
+        let mut hasher = Sha256::new();
+        hasher.update(8u64.to_be_bytes());
+        hasher.update(b"abcdefgh");
+        assert_eq!(LeafHashMode::LengthPrefixed.hash(0, b"abcdefgh"), hasher.finalize().to_vec());
+    }
+
+    #[test]
+    fn test_index_bound_leaves() {
+        let mode = LeafHashMode::IndexBound;
+        assert_ne!(mode.hash(0, b"value"), mode.hash(1, b"value"));
+
+        // The prefix is the index as 8 bytes big-endian
+        let mut hasher = Sha256::new();
+        hasher.update(3u64.to_be_bytes());
+        hasher.update(b"value");
+        assert_eq!(mode.hash(3, b"value"), hasher.finalize().to_vec());
+        assert!(mode.hasher(Some(5)).is_none());
+    }
+
+    #[test]
+    fn test_sibling_fanout() {
+        let tree = MerkleTree::from_leaves((0..5).map(|i| MerkleNode::new_leaf(&[i])).collect());
+        let fanout: Vec<(usize, usize, usize)> = tree
+            .sibling_fanout()
+            .iter()
+            .map(|f| (f.level, f.position, f.fanout))
+            .collect();
+        // Leaf 4 is duplicated at levels 0 and 1, so it pairs with itself there
+        assert_eq!(
+            fanout,
+            vec![
+                (0, 0, 1), (0, 1, 1), (0, 2, 1), (0, 3, 1), (0, 4, 1),
+                (1, 0, 2), (1, 1, 2), (1, 2, 1),
+                (2, 0, 1), (2, 1, 4),
+            ]
+        );
+        // Every proof element is counted once
+        let total: usize = tree.sibling_fanout().iter().map(|f| f.fanout).sum();
+        assert_eq!(total, 5 * 3);
+        assert_eq!(tree.sibling_fanout()[9].hash, tree.root().unwrap().right.as_ref().unwrap().hash);
+
+        assert!(MerkleTree::from_leaves(vec![MerkleNode::new_leaf(b"a")]).sibling_fanout().is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let tree = |values: &[&str]| {
+            MerkleTree::from_leaves(values.iter().map(|v| MerkleNode::new_leaf(v.as_bytes())).collect())
+        };
+        let base = tree(&["a", "b", "c", "d", "e"]);
+        assert!(base.diff(&base).is_empty());
+
+        assert_eq!(base.diff(&tree(&["a", "b", "X", "d", "e"])), vec![2]);
+        assert_eq!(base.diff(&tree(&["X", "b", "c", "d", "Y"])), vec![0, 4]);
+
+        // Extra leaves differ, whichever side has them
+        let longer = tree(&["a", "X", "c", "d", "e", "f", "g", "h", "i"]);
+        assert_eq!(base.diff(&longer), vec![1, 5, 6, 7, 8]);
+        assert_eq!(longer.diff(&base), vec![1, 5, 6, 7, 8]);
+        assert_eq!(tree(&["a"]).diff(&tree(&["a", "b"])), vec![1]);
+        assert_eq!(MerkleTree::new().diff(&tree(&["a", "b"])), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_try_from_leaves_limit() {
+        let leaves = || (0..4).map(|i| MerkleNode::new_leaf(&[i])).collect::<Vec<_>>();
+        let tree = MerkleTree::try_from_leaves(leaves(), SingleLeafMode::default(), 4).unwrap();
+        assert_eq!(tree.root_hash(), MerkleTree::from_leaves(leaves()).root_hash());
+
+        let result = MerkleTree::try_from_leaves(leaves(), SingleLeafMode::default(), 3);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_node_path() {
+        let leaves: Vec<MerkleNode> = (0..5)
+            .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+            .collect();
+        let tree = MerkleTree::from_leaves(leaves.clone());
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let path = tree.node_path(index).unwrap();
+            assert_eq!(path.len(), tree.height());
+            assert_eq!(path.first(), tree.root_hash().as_ref());
+            assert_eq!(path.last(), Some(&leaf.hash));
+        }
+
+        // Each node is the parent of the next, which the sibling proof doesn't show
+        let path = tree.node_path(2).unwrap();
+        let root = tree.root().unwrap();
+        assert_eq!(path[1], root.left.as_ref().unwrap().hash);
+        assert_eq!(path[2], root.left.as_ref().unwrap().right.as_ref().unwrap().hash);
+
+        assert_eq!(tree.node_path(5), None);
+        assert_eq!(MerkleTree::new().node_path(0), None);
+        let single = MerkleTree::from_leaves(vec![leaves[0].clone()]);
+        assert_eq!(single.node_path(0), Some(vec![leaves[0].hash.clone()]));
+    }
+
+    #[test]
+    fn test_node_at_level_order() {
+        let leaves: Vec<MerkleNode> = (0..5)
+            .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+            .collect();
+        let tree = MerkleTree::from_leaves(leaves.clone());
+        assert_eq!(tree.node_at(0), tree.root_hash());
+
+        // Every interior node hashes its children at 2i+1 and 2i+2; a missing right
+        // child is padding, duplicated from the left
+        for i in 0..7 {
+            let Some(hash) = tree.node_at(i) else { continue };
+            let left = tree.node_at(2 * i + 1).unwrap();
+            let right = tree.node_at(2 * i + 2).unwrap_or_else(|| left.clone());
+            assert_eq!(hash, sha256(&[&left, &right]), "{}", i);
+        }
+
+        // The leaf level starts at 2^(height - 1) - 1
+        for (index, leaf) in leaves.iter().enumerate() {
+            assert_eq!(tree.node_at(7 + index), Some(leaf.hash.clone()));
+        }
+        assert_eq!(tree.node_at(6), None);
+        assert_eq!(tree.node_at(12), None);
+        assert_eq!(tree.node_at(15), None);
+        assert_eq!(tree.node_at(usize::MAX), None);
+        assert_eq!(MerkleTree::new().node_at(0), None);
+    }
+
+    #[test]
+    fn test_enumerate_leaves() {
+        for count in [0, 1, 2, 3, 5, 6, 7, 9] {
+            let leaves: Vec<MerkleNode> = (0..count)
+                .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+                .collect();
+            let tree = MerkleTree::from_leaves(leaves.clone());
+
+            let enumerated: Vec<_> = tree.enumerate_leaves().collect();
+            assert_eq!(enumerated.len(), tree.leaf_count(), "{} leaves", count);
+            for ((index, hash), (expected, leaf)) in enumerated.into_iter().zip(leaves.iter().enumerate()) {
+                assert_eq!(index, expected);
+                assert_eq!(hash, leaf.hash.as_slice());
+            }
+        }
+
+        let leaf = MerkleNode::new_leaf(b"only");
+        let tree = MerkleTree::from_leaves_with_mode(vec![leaf.clone()], SingleLeafMode::DuplicateToParent);
+        assert_eq!(tree.enumerate_leaves().collect::<Vec<_>>(), vec![(0, leaf.hash.as_slice())]);
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let leaves: Vec<MerkleNode> = (0..4)
+            .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+            .collect();
+        let tree = MerkleTree::from_leaves(leaves.clone());
+        let rendered = tree.render_ascii();
+
+        // Root, two parents, four leaves
+        assert_eq!(rendered.lines().count(), 7);
+        let root_label = &hex::encode(tree.root_hash().unwrap())[..8];
+        assert_eq!(rendered.lines().next(), Some(root_label));
+        assert!(rendered.contains(&format!("│   ├── {}", &hex::encode(&leaves[0].hash)[..8])));
+        assert!(rendered.contains(&format!("    └── {}", &hex::encode(&leaves[3].hash)[..8])));
+
+        assert_eq!(MerkleTree::new().render_ascii(), "(empty tree)\n");
+    }
+
+    #[test]
+    fn test_root_summary() {
+        assert_eq!(MerkleTree::new().root_summary(), None);
+
+        let leaves: Vec<MerkleNode> = (0..5)
+            .map(|i| MerkleNode::new_leaf(format!("data{}", i).as_bytes()))
+            .collect();
+        let tree = MerkleTree::from_leaves(leaves);
+        assert_eq!(
+            tree.root_summary(),
+            Some(RootSummary {
+                hash: tree.root_hash().unwrap(),
+                leaf_count: 5,
+                height: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_null_slots_differ_from_committed_empty_values() {
+        assert_eq!(EMPTY_LEAF_HASH.to_vec(), sha256(&[b"merkle_tree:empty-leaf"]));
+        let placeholder = Commitment::new_placeholder(0, Vec::new());
+        let empty = Commitment::new(0, Vec::new(), Vec::new());
+        for mode in [LeafHashMode::Plain, LeafHashMode::LengthPrefixed, LeafHashMode::IndexBound] {
+            assert_eq!(placeholder.leaf(false, mode), MerkleNode::empty_leaf(), "{:?}", mode);
+            assert_eq!(empty.leaf(false, mode).hash, mode.hash(0, b""), "{:?}", mode);
+            assert_ne!(empty.leaf(false, mode).hash, EMPTY_LEAF_HASH.to_vec(), "{:?}", mode);
+        }
+        assert_ne!(EMPTY_LEAF_HASH, EMPTY_ROOT);
+        // Hash-only storage takes the stored hash, but a null slot still has no value
+        assert_eq!(placeholder.leaf(true, LeafHashMode::Plain), MerkleNode::empty_leaf());
+    }
+
+    #[test]
+    fn test_push_leaf_matches_from_leaves() {
+        let leaves: Vec<MerkleNode> = (0..1000u32).map(|i| MerkleNode::new_leaf(&i.to_be_bytes())).collect();
+        let mut tree = MerkleTree::new();
+        for (i, leaf) in leaves.iter().enumerate() {
+            tree.push_leaf(leaf.clone());
+            // Spot-check intermediate sizes, including every power-of-two boundary
+            if i < 40 || (i + 1).is_power_of_two() {
+                let expected = MerkleTree::from_leaves(leaves[..=i].to_vec());
+                assert_eq!(tree.root_hash(), expected.root_hash(), "{} leaves", i + 1);
+            }
+        }
+
+        let expected = MerkleTree::from_leaves(leaves.clone());
+        assert_eq!(tree.root_hash(), expected.root_hash());
+        assert_eq!(tree.leaf_count(), 1000);
+        assert_eq!(tree.height(), expected.height());
+        assert!(tree.root().unwrap().verify_subtree());
+        for index in [0, 511, 512, 999] {
+            assert_eq!(tree.leaf(index), expected.leaf(index));
+            let root = tree.root().unwrap();
+            assert_eq!(generate_proof(root, index, 1000), generate_proof(expected.root().unwrap(), index, 1000));
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod merkle;
-pub mod commitment;
\ No newline at end of file
+pub mod commitment;
+pub mod audit;
+pub mod keyed_map;
\ No newline at end of file
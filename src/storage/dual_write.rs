@@ -0,0 +1,280 @@
+use crate::crypto::{hash::HashAlgorithm, proof::MerkleProof};
+use crate::error::AppError;
+use crate::models::{
+    audit::{AuditEntry, Checkpoint},
+    commitment::{Commitment, CommitmentMetadata, IndexRemap},
+    merkle::{LeafHashMode, MerkleTree, SingleLeafMode},
+};
+use crate::storage::traits::{CommitmentStorage, StaleRoot, TreeSnapshot};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::Arc;
+
+/// Writes every mutation to a primary and a secondary backend and serves reads from
+/// the primary, for migrating between backends without downtime. Secondary failures
+/// and root divergence are logged rather than failing the write.
+#[derive(Debug, Clone)]
+pub struct DualWriteStorage<A, B> {
+    primary: A,
+    secondary: B,
+}
+
+impl<A: CommitmentStorage, B: CommitmentStorage> DualWriteStorage<A, B> {
+    pub fn new(primary: A, secondary: B) -> Self {
+        Self { primary, secondary }
+    }
+
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &B {
+        &self.secondary
+    }
+
+    /// Compare the secondary's outcome with the primary's, warning when they differ
+    fn check<T: PartialEq + std::fmt::Debug>(operation: &str, primary: &T, secondary: Result<T, AppError>) {
+        match secondary {
+            Ok(secondary) if secondary == *primary => {}
+            Ok(secondary) => tracing::warn!(
+                operation,
+                primary = ?primary,
+                secondary = ?secondary,
+                "dual-write backends diverged"
+            ),
+            Err(error) => tracing::warn!(operation, %error, "dual-write secondary failed"),
+        }
+    }
+}
+
+#[async_trait]
+impl<A: CommitmentStorage, B: CommitmentStorage> CommitmentStorage for DualWriteStorage<A, B> {
+    /// Writes the primary first; the secondary only sees writes the primary accepted
+    async fn store_commitment(
+        &self,
+        index: Option<usize>,
+        value: Vec<u8>,
+        metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        let result = self
+            .primary
+            .store_commitment(index, value.clone(), metadata.clone())
+            .await?;
+        let secondary = self.secondary.store_commitment(index, value, metadata).await;
+        Self::check("store_commitment", &result, secondary);
+        Ok(result)
+    }
+
+    async fn append_block(&self, values: Vec<Vec<u8>>) -> Result<(usize, Vec<u8>), AppError> {
+        let result = self.primary.append_block(values.clone()).await?;
+        let secondary = self.secondary.append_block(values).await;
+        Self::check("append_block", &result, secondary);
+        Ok(result)
+    }
+
+    async fn restore_slots(&self, slots: Vec<Commitment>) -> Result<Vec<u8>, AppError> {
+        let root = self.primary.restore_slots(slots.clone()).await?;
+        Self::check("restore_slots", &root, self.secondary.restore_slots(slots).await);
+        Ok(root)
+    }
+
+    async fn store_leaf_hash(
+        &self,
+        leaf_hash: Vec<u8>,
+        metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        let result = self
+            .primary
+            .store_leaf_hash(leaf_hash.clone(), metadata.clone())
+            .await?;
+        let secondary = self.secondary.store_leaf_hash(leaf_hash, metadata).await;
+        Self::check("store_leaf_hash", &result, secondary);
+        Ok(result)
+    }
+
+    async fn sweep_expired(&self, now: u64) -> Result<usize, AppError> {
+        let removed = self.primary.sweep_expired(now).await?;
+        let secondary = self.secondary.sweep_expired(now).await;
+        Self::check("sweep_expired", &removed, secondary);
+        let root = self.primary.get_root_hash().await?;
+        Self::check("sweep_expired", &root, self.secondary.get_root_hash().await);
+        Ok(removed)
+    }
+
+    async fn delete_commitment(&self, index: usize) -> Result<Vec<u8>, AppError> {
+        let root = self.primary.delete_commitment(index).await?;
+        Self::check("delete_commitment", &root, self.secondary.delete_commitment(index).await);
+        Ok(root)
+    }
+
+    async fn compact_delete(&self, index: usize) -> Result<(Vec<u8>, Vec<IndexRemap>), AppError> {
+        let result = self.primary.compact_delete(index).await?;
+        Self::check("compact_delete", &result, self.secondary.compact_delete(index).await);
+        Ok(result)
+    }
+
+    async fn mark_revealed(&self, index: usize) -> Result<(), AppError> {
+        self.primary.mark_revealed(index).await?;
+        Self::check("mark_revealed", &(), self.secondary.mark_revealed(index).await);
+        Ok(())
+    }
+
+    fn stores_hashes_only(&self) -> bool {
+        self.primary.stores_hashes_only()
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.primary.hash_algorithm()
+    }
+
+    fn leaf_hash_mode(&self) -> LeafHashMode {
+        self.primary.leaf_hash_mode()
+    }
+
+    fn single_leaf_mode(&self) -> SingleLeafMode {
+        self.primary.single_leaf_mode()
+    }
+
+    fn stale_root(&self) -> Option<StaleRoot> {
+        self.primary.stale_root()
+    }
+
+    async fn get_commitment(&self, index: usize) -> Result<Commitment, AppError> {
+        self.primary.get_commitment(index).await
+    }
+
+    async fn get_commitment_owned_value(&self, index: usize) -> Result<Bytes, AppError> {
+        self.primary.get_commitment_owned_value(index).await
+    }
+
+    async fn get_leaf_hash(&self, index: usize) -> Result<Vec<u8>, AppError> {
+        self.primary.get_leaf_hash(index).await
+    }
+
+    async fn get_all_commitments(&self) -> Result<Vec<Commitment>, AppError> {
+        self.primary.get_all_commitments().await
+    }
+
+    async fn get_commitment_by_root(&self, root: &[u8]) -> Result<Commitment, AppError> {
+        self.primary.get_commitment_by_root(root).await
+    }
+
+    async fn get_commitments_after(
+        &self,
+        cursor: Option<usize>,
+    ) -> Result<Vec<Commitment>, AppError> {
+        self.primary.get_commitments_after(cursor).await
+    }
+
+    async fn replica_checkpoint(&self) -> Result<Option<(usize, Vec<u8>)>, AppError> {
+        self.primary.replica_checkpoint().await
+    }
+
+    async fn get_proof(&self, index: usize) -> Result<Arc<MerkleProof>, AppError> {
+        self.primary.get_proof(index).await
+    }
+
+    async fn get_tree(&self) -> Result<MerkleTree, AppError> {
+        self.primary.get_tree().await
+    }
+
+    async fn snapshot(&self) -> Result<TreeSnapshot, AppError> {
+        self.primary.snapshot().await
+    }
+
+    async fn with_tree<'a>(
+        &'a self,
+        f: Box<dyn for<'t> FnOnce(&'t MerkleTree) + Send + 'a>,
+    ) -> Result<(), AppError> {
+        self.primary.with_tree(f).await
+    }
+
+    async fn get_root_hash(&self) -> Result<Vec<u8>, AppError> {
+        self.primary.get_root_hash().await
+    }
+
+    async fn try_get_root_hash(&self) -> Result<Option<Vec<u8>>, AppError> {
+        self.primary.try_get_root_hash().await
+    }
+
+    async fn commitment_count(&self) -> Result<usize, AppError> {
+        self.primary.commitment_count().await
+    }
+
+    async fn total_bytes(&self) -> Result<usize, AppError> {
+        self.primary.total_bytes().await
+    }
+
+    async fn verify_integrity(&self) -> Result<bool, AppError> {
+        self.primary.verify_integrity().await
+    }
+
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, AppError> {
+        self.primary.audit_log().await
+    }
+
+    async fn checkpoints(&self) -> Result<Vec<Checkpoint>, AppError> {
+        self.primary.checkpoints().await
+    }
+
+    async fn root_history(&self) -> Result<Vec<Vec<u8>>, AppError> {
+        self.primary.root_history().await
+    }
+
+    async fn rebuild(&self) -> Result<(), AppError> {
+        self.primary.rebuild().await?;
+        self.secondary.rebuild().await
+    }
+
+    async fn verify_chain(&self) -> Result<bool, AppError> {
+        self.primary.verify_chain().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::commitment::unix_now;
+    use crate::storage::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_dual_write_keeps_backends_in_sync() {
+        let storage = DualWriteStorage::new(MemoryStorage::new(), MemoryStorage::new());
+        for i in 0..5 {
+            let (index, root) = storage
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+            assert_eq!(index, i);
+            assert_eq!(root, storage.secondary().get_root_hash().await.unwrap());
+        }
+        storage.insert_at(8, b"gap".to_vec()).await.unwrap();
+        let expiring = CommitmentMetadata {
+            expires_at: Some(unix_now()),
+            ..Default::default()
+        };
+        storage
+            .store_commitment(None, b"short-lived".to_vec(), expiring)
+            .await
+            .unwrap();
+        assert_eq!(storage.sweep_expired(unix_now()).await.unwrap(), 1);
+
+        let (primary, secondary) = (storage.primary(), storage.secondary());
+        assert_eq!(
+            primary.get_root_hash().await.unwrap(),
+            secondary.get_root_hash().await.unwrap()
+        );
+        assert_eq!(
+            primary.get_all_commitments().await.unwrap(),
+            secondary.get_all_commitments().await.unwrap()
+        );
+        assert_eq!(storage.commitment_count().await.unwrap(), 10);
+
+        // A write the primary rejects never reaches the secondary
+        assert!(matches!(
+            storage.insert_at(8, b"again".to_vec()).await,
+            Err(AppError::Conflict(_))
+        ));
+        assert_eq!(secondary.commitment_count().await.unwrap(), 10);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::models::commitment::unix_now;
+use crate::storage::traits::CommitmentStorage;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// How often expired commitments are swept when `MERKLE_SWEEP_INTERVAL_SECS` is unset
+pub const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Sweep interval from `MERKLE_SWEEP_INTERVAL_SECS`, falling back to the default
+pub fn sweep_interval_from_env() -> Duration {
+    std::env::var("MERKLE_SWEEP_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map_or(DEFAULT_SWEEP_INTERVAL, Duration::from_secs)
+}
+
+/// Spawn a background task that tombstones expired commitments every `interval`
+pub fn spawn_sweeper(storage: Arc<dyn CommitmentStorage>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match storage.sweep_expired(unix_now()).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "swept expired commitments"),
+                Err(e) => tracing::warn!(error = %e, "expiry sweep failed"),
+            }
+        }
+    })
+}
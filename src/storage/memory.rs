@@ -1,70 +1,872 @@
+#[cfg(feature = "encryption")]
+use crate::crypto::encryption::EncryptionKey;
+use crate::crypto::proof::{generate_proof, MerkleProof, HASH_LEN};
 use crate::error::AppError;
 use crate::models::{
-    commitment::Commitment,
-    merkle::{MerkleNode, MerkleTree},
+    audit::{AuditEntry, AuditOp, Checkpoint},
+    commitment::{unix_now, Commitment, CommitmentMetadata, IndexRemap, RevealState},
+    merkle::{LeafHashMode, MerkleNode, MerkleTree, SingleLeafMode, EMPTY_ROOT},
 };
-use crate::storage::traits::CommitmentStorage;
+use crate::storage::traits::{CommitmentStorage, StaleRoot, TreeSnapshot};
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use bytes::Bytes;
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock, Semaphore};
+
+/// Number of leaves whose proofs are spot-checked by `verify_integrity`
+const INTEGRITY_SAMPLE_SIZE: usize = 16;
+
+/// Rebuilds slower than this are logged by default
+pub const DEFAULT_SLOW_REBUILD_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Placeholders a write at a chosen index may pad past the end by default
+pub const DEFAULT_MAX_INDEX_GAP: usize = 1024;
+
+/// Caps how many tree rebuilds run at once, queuing the rest. Clones share the cap,
+/// so one limiter can be handed to several storages.
+#[derive(Debug, Clone)]
+pub struct RebuildLimiter {
+    permits: Arc<Semaphore>,
+    active: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+impl RebuildLimiter {
+    /// Allow at most `max_concurrent` rebuilds at a time (at least one)
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            active: Arc::new(AtomicUsize::new(0)),
+            peak: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Read `MERKLE_MAX_CONCURRENT_REBUILDS`; None when unset
+    pub fn from_env() -> Option<Self> {
+        std::env::var("MERKLE_MAX_CONCURRENT_REBUILDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Self::new)
+    }
+
+    /// Most rebuilds that have run at once under this limiter
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Run `rebuild` on the blocking pool once a slot is free
+    async fn run<T: Send + 'static>(
+        &self,
+        rebuild: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+    ) -> Result<T, AppError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| AppError::Internal(format!("Rebuild limiter closed: {}", e)))?;
+        let _active = ActiveRebuild::enter(&self.active, &self.peak);
+        tokio::task::spawn_blocking(rebuild)
+            .await
+            .map_err(|e| AppError::Internal(format!("Tree rebuild task failed: {}", e)))?
+    }
+}
+
+/// Counts a rebuild as running until dropped, even if its caller is cancelled
+struct ActiveRebuild<'a>(&'a AtomicUsize);
+
+impl<'a> ActiveRebuild<'a> {
+    fn enter(active: &'a AtomicUsize, peak: &AtomicUsize) -> Self {
+        let running = active.fetch_add(1, Ordering::Relaxed) + 1;
+        peak.fetch_max(running, Ordering::Relaxed);
+        Self(active)
+    }
+}
+
+impl Drop for ActiveRebuild<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Configuration for in-memory storage
+#[derive(Debug, Clone)]
+pub struct MemoryStorageConfig {
+    /// zstd level used to compress values at rest (None stores them as-is)
+    #[cfg(feature = "compression")]
+    pub compression_level: Option<i32>,
+    /// AES-256-GCM key for values at rest (None stores them unencrypted). Leaves
+    /// are still hashed over the plaintext, so roots and proofs are unaffected.
+    #[cfg(feature = "encryption")]
+    pub encryption_key: Option<EncryptionKey>,
+    /// How a one-leaf tree forms its root (defaults to the leaf being the root)
+    pub single_leaf_mode: SingleLeafMode,
+    /// Log a warning when a tree rebuild takes longer than this (None disables it)
+    pub slow_rebuild_threshold: Option<Duration>,
+    /// Store SHA-256(value) in place of the value; originals are never kept
+    pub hash_only: bool,
+    /// How values are hashed into leaves
+    pub leaf_hash_mode: LeafHashMode,
+    /// Caps concurrent rebuilds (None leaves them unbounded). Writes to one storage
+    /// are already serialized, so this matters when storages share a limiter.
+    pub rebuild_limiter: Option<RebuildLimiter>,
+    /// Largest tree a write may build, counting placeholders (None leaves it unbounded)
+    pub max_leaves: Option<usize>,
+    /// Most placeholders a write at a chosen index may pad past the end (None leaves
+    /// it unbounded), so one request can't allocate slots up to an arbitrary index
+    pub max_index_gap: Option<usize>,
+    /// Coalesce appends arriving within this window into one rebuild (None writes
+    /// each on its own). Every append waits out the window, so this trades latency
+    /// for throughput.
+    pub batch_window: Option<Duration>,
+    /// Keep the root each time the tree grows past a multiple of this many leaves
+    /// (None takes no checkpoints)
+    pub checkpoint_interval: Option<usize>,
+}
+
+impl Default for MemoryStorageConfig {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "compression")]
+            compression_level: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            single_leaf_mode: SingleLeafMode::default(),
+            slow_rebuild_threshold: Some(DEFAULT_SLOW_REBUILD_THRESHOLD),
+            hash_only: false,
+            leaf_hash_mode: LeafHashMode::default(),
+            rebuild_limiter: None,
+            max_leaves: None,
+            max_index_gap: Some(DEFAULT_MAX_INDEX_GAP),
+            batch_window: None,
+            checkpoint_interval: None,
+        }
+    }
+}
+
+impl MemoryStorageConfig {
+    /// Warn when a rebuild over `leaf_count` leaves exceeded the slow threshold
+    fn report_rebuild(&self, elapsed: Duration, leaf_count: usize) {
+        if self.slow_rebuild_threshold.is_some_and(|threshold| elapsed > threshold) {
+            tracing::warn!(
+                leaf_count,
+                elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+                "slow Merkle tree rebuild"
+            );
+        }
+    }
+
+    /// Run a CPU-bound rebuild off the async runtime, within the rebuild limit if set
+    async fn run_rebuild<T: Send + 'static>(
+        &self,
+        rebuild: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+    ) -> Result<T, AppError> {
+        match &self.rebuild_limiter {
+            Some(limiter) => limiter.run(rebuild).await,
+            None => tokio::task::spawn_blocking(rebuild)
+                .await
+                .map_err(|e| AppError::Internal(format!("Tree rebuild task failed: {}", e)))?,
+        }
+    }
+
+    /// Read `MERKLE_BATCH_WINDOW_MS`; None when unset, unparsable or zero
+    pub fn batch_window_from_env() -> Option<Duration> {
+        std::env::var("MERKLE_BATCH_WINDOW_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&ms| ms > 0)
+            .map(Duration::from_millis)
+    }
+
+    /// Read `MERKLE_CHECKPOINT_INTERVAL`; None when unset, unparsable or zero
+    pub fn checkpoint_interval_from_env() -> Option<usize> {
+        std::env::var("MERKLE_CHECKPOINT_INTERVAL")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+    }
+
+    /// Read `MERKLE_MAX_LEAVES`; None when unset or unparsable
+    pub fn max_leaves_from_env() -> Option<usize> {
+        std::env::var("MERKLE_MAX_LEAVES").ok().and_then(|v| v.parse().ok())
+    }
+
+    /// Read `MERKLE_MAX_INDEX_GAP`; the default gap when unset or unparsable
+    pub fn max_index_gap_from_env() -> Option<usize> {
+        std::env::var("MERKLE_MAX_INDEX_GAP")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .or(Some(DEFAULT_MAX_INDEX_GAP))
+    }
+
+    /// Build a tree over `leaves` using the configured single-leaf mode and leaf limit
+    fn build_tree(&self, leaves: Vec<MerkleNode>) -> Result<MerkleTree, AppError> {
+        MerkleTree::try_from_leaves(leaves, self.single_leaf_mode, self.max_leaves.unwrap_or(usize::MAX))
+    }
+
+    /// The value actually committed: its hash in hash-only mode, otherwise unchanged
+    fn committed_value(&self, value: Vec<u8>) -> Result<Vec<u8>, AppError> {
+        if !self.hash_only {
+            return Ok(value);
+        }
+        self.check_hashable_in_advance()?;
+        // Only index-bound leaves use the index, and they were just refused
+        Ok(self.leaf_hash_mode.hash(0, &value))
+    }
+
+    /// Index-bound leaves hash their slot, which isn't known before the write lands,
+    /// so hash-only storage can't accept values hashed ahead of it
+    fn check_hashable_in_advance(&self) -> Result<(), AppError> {
+        if self.leaf_hash_mode == LeafHashMode::IndexBound {
+            return Err(AppError::InvalidInput(
+                "Hash-only storage can't commit index-bound leaves".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Leaf node for slot `index` holding an at-rest value, or for a null slot
+    fn leaf(&self, index: usize, stored: Option<&[u8]>) -> Result<MerkleNode, AppError> {
+        match stored {
+            Some(stored) => Ok(self.leaf_for(index, &self.decode_value(stored)?)),
+            None => Ok(MerkleNode::empty_leaf()),
+        }
+    }
+
+    /// Leaf node for a committed (decoded) value in slot `index`
+    fn leaf_for(&self, index: usize, value: &[u8]) -> MerkleNode {
+        MerkleNode::committed_leaf(index, value, self.hash_only, self.leaf_hash_mode)
+    }
+
+    /// Encode a value into its at-rest representation
+    #[allow(unused_mut)]
+    fn encode_value(&self, mut value: Vec<u8>) -> Result<Vec<u8>, AppError> {
+        #[cfg(feature = "compression")]
+        if let Some(level) = self.compression_level {
+            value = zstd::encode_all(value.as_slice(), level)
+                .map_err(|e| AppError::Internal(format!("Failed to compress value: {}", e)))?;
+        }
+        // Compress first; ciphertext doesn't compress
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            value = key.encrypt(&value)?;
+        }
+        Ok(value)
+    }
+
+    /// Decode an at-rest value back into the committed bytes
+    #[allow(unused_mut)]
+    fn decode_value<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>, AppError> {
+        let mut value = Cow::Borrowed(stored);
+        // Placeholders hold nothing at rest, and encoded values are never empty
+        if stored.is_empty() {
+            return Ok(value);
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            value = Cow::Owned(key.decrypt(&value)?);
+        }
+        #[cfg(feature = "compression")]
+        if self.compression_level.is_some() {
+            value = zstd::decode_all(&*value)
+                .map(Cow::Owned)
+                .map_err(|e| AppError::Internal(format!("Failed to decompress value: {}", e)))?;
+        }
+        Ok(value)
+    }
+
+    /// Decode an at-rest value, sharing the stored buffer when it isn't encoded
+    fn decode_bytes(&self, stored: &Bytes) -> Result<Bytes, AppError> {
+        Ok(match self.decode_value(stored)? {
+            Cow::Borrowed(_) => stored.clone(),
+            Cow::Owned(value) => Bytes::from(value),
+        })
+    }
+
+    /// Decode a stored commitment for callers
+    fn decode_commitment(&self, commitment: &Commitment) -> Result<Commitment, AppError> {
+        Ok(Commitment {
+            value: self.decode_bytes(&commitment.value)?,
+            ..commitment.clone()
+        })
+    }
+}
+
+/// The at-rest value of every slot, None for placeholders
+fn stored_slots(commitments: &[Commitment]) -> Vec<Option<Bytes>> {
+    commitments
+        .iter()
+        .map(|c| (!c.placeholder).then(|| c.value.clone()))
+        .collect()
+}
 
 /// In-memory storage implementation
 #[derive(Debug, Clone)]
 pub struct MemoryStorage {
     commitments: Arc<RwLock<Vec<Commitment>>>,
-    tree: Arc<RwLock<MerkleTree>>,
+    tree: Arc<RwLock<Arc<MerkleTree>>>,
+    /// Serializes writers so a rebuild doesn't have to hold the data locks
+    write_lock: Arc<Mutex<()>>,
+    /// Running total of committed value bytes
+    total_bytes: Arc<AtomicUsize>,
+    /// Proofs against the current root, cleared whenever the tree is swapped
+    proof_cache: Arc<std::sync::Mutex<HashMap<usize, Arc<MerkleProof>>>>,
+    /// Root after every write, in order, for checking the prev_root chain
+    root_history: Arc<RwLock<Vec<Vec<u8>>>>,
+    /// Append-only record of every mutation
+    audit_log: Arc<RwLock<Vec<AuditEntry>>>,
+    /// Roots kept every `checkpoint_interval` leaves
+    checkpoints: Arc<RwLock<Vec<Checkpoint>>>,
+    /// Tree rebuilds run by writes
+    rebuilds: Arc<AtomicUsize>,
+    /// Queue to the append batcher, when a batch window is configured
+    batcher: Arc<OnceLock<mpsc::UnboundedSender<PendingAppend>>>,
+    /// Root and commitment count of the last installed tree, readable without locks
+    published: Arc<ArcSwap<(Vec<u8>, usize)>>,
+    /// Writes accepted but not yet in the published root
+    pending_writes: Arc<AtomicUsize>,
+    config: MemoryStorageConfig,
+}
+
+/// Counts a write as pending until it lands or is abandoned
+struct PendingWrite(Arc<AtomicUsize>);
+
+impl PendingWrite {
+    fn new(pending: &Arc<AtomicUsize>) -> Self {
+        pending.fetch_add(1, Ordering::Relaxed);
+        Self(Arc::clone(pending))
+    }
+}
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// What `write_slots` puts in one slot
+enum SlotWrite {
+    /// An already-committed value, stamped with the root of the write
+    Value(Vec<u8>, CommitmentMetadata),
+    /// An exported slot replayed as it was, keeping the roots it was recorded with
+    Restored(Commitment),
+}
+
+impl SlotWrite {
+    fn placeholder(&self) -> bool {
+        matches!(self, SlotWrite::Restored(c) if c.placeholder)
+    }
+
+    fn value(&self) -> &[u8] {
+        match self {
+            SlotWrite::Value(value, _) => value,
+            SlotWrite::Restored(c) => &c.value,
+        }
+    }
+}
+
+/// An append waiting for the batcher's next flush
+#[derive(Debug)]
+struct PendingAppend {
+    value: Vec<u8>,
+    metadata: CommitmentMetadata,
+    reply: oneshot::Sender<Result<(usize, Vec<u8>), AppError>>,
 }
 
 impl MemoryStorage {
     /// Create a new in-memory storage
     pub fn new() -> Self {
+        Self::with_config(MemoryStorageConfig::default())
+    }
+
+    /// Create a new in-memory storage with the given configuration
+    pub fn with_config(config: MemoryStorageConfig) -> Self {
         Self {
             commitments: Arc::new(RwLock::new(Vec::new())),
-            tree: Arc::new(RwLock::new(MerkleTree::new())),
+            tree: Arc::new(RwLock::new(Arc::new(MerkleTree::new()))),
+            write_lock: Arc::new(Mutex::new(())),
+            total_bytes: Arc::new(AtomicUsize::new(0)),
+            proof_cache: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            root_history: Arc::new(RwLock::new(Vec::new())),
+            audit_log: Arc::new(RwLock::new(Vec::new())),
+            checkpoints: Arc::new(RwLock::new(Vec::new())),
+            rebuilds: Arc::new(AtomicUsize::new(0)),
+            batcher: Arc::new(OnceLock::new()),
+            published: Arc::new(ArcSwap::from_pointee((EMPTY_ROOT.to_vec(), 0))),
+            pending_writes: Arc::new(AtomicUsize::new(0)),
+            config,
         }
     }
 }
 
-impl Default for MemoryStorage {
-    fn default() -> Self {
-        Self::new()
+impl MemoryStorage {
+    fn proof_cache(&self) -> std::sync::MutexGuard<'_, HashMap<usize, Arc<MerkleProof>>> {
+        self.proof_cache.lock().unwrap_or_else(|e| e.into_inner())
     }
-}
 
-#[async_trait]
-impl CommitmentStorage for MemoryStorage {
-    async fn add_commitment(&self, value: Vec<u8>) -> Result<(usize, Vec<u8>), AppError> {
+    /// Swap in a rebuilt tree covering `commitment_count` slots, publishing its root
+    /// for lock-free reads and dropping proofs built against the old one
+    async fn install_tree(&self, tree: MerkleTree, commitment_count: usize) {
+        let mut current = self.tree.write().await;
+        self.published.store(Arc::new((tree.root_hash_or_empty(), commitment_count)));
+        *current = Arc::new(tree);
+        self.proof_cache().clear();
+    }
+
+    /// Rebuild a tree over at-rest values off the async runtime
+    async fn rebuild_from(&self, stored: Vec<Option<Bytes>>) -> Result<MerkleTree, AppError> {
+        let config = self.config.clone();
+        let (tree, elapsed) = self
+            .config
+            .run_rebuild(move || {
+                let leaves = stored
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| config.leaf(i, v.as_deref()))
+                    .collect::<Result<Vec<_>, AppError>>()?;
+                let started = Instant::now();
+                let tree = config.build_tree(leaves)?;
+                Ok((tree, started.elapsed()))
+            })
+            .await?;
+        self.rebuilds.fetch_add(1, Ordering::Relaxed);
+        self.config.report_rebuild(elapsed, tree.leaf_count());
+        Ok(tree)
+    }
+
+    /// Replace every commitment matching `select` with a placeholder in one
+    /// write. Returns the tombstoned indices and the resulting root.
+    async fn tombstone(
+        &self,
+        select: impl Fn(&Commitment) -> bool + Send,
+    ) -> Result<(Vec<usize>, Vec<u8>), AppError> {
+        let _write = self.write_lock.lock().await;
+
+        let (mut stored, selected): (Vec<Option<Bytes>>, Vec<usize>) = {
+            let commitments = self.commitments.read().await;
+            let selected = commitments
+                .iter()
+                .filter(|c| !c.placeholder && select(c))
+                .map(|c| c.index)
+                .collect();
+            (stored_slots(&commitments), selected)
+        };
+        if selected.is_empty() {
+            return Ok((selected, self.tree.read().await.root_hash_or_empty()));
+        }
+
+        let mut removed_bytes = 0;
+        for &index in &selected {
+            if let Some(value) = stored[index].take() {
+                removed_bytes += self.config.decode_value(&value)?.len();
+            }
+        }
+
+        let tree = self.rebuild_from(stored).await?;
+        let merkle_root = tree.root_hash_or_empty();
+
+        // Tombstones become placeholders, linked into the chain as one write
+        let prev_root = self.tree.read().await.root_hash_or_empty();
         let mut commitments = self.commitments.write().await;
-        let index = commitments.len();
+        for &index in &selected {
+            commitments[index] =
+                Commitment::new_placeholder(index, merkle_root.clone()).with_prev_root(prev_root.clone());
+        }
+        self.total_bytes.fetch_sub(removed_bytes, Ordering::Relaxed);
+        self.audit_log.write().await.extend(
+            selected
+                .iter()
+                .map(|&index| AuditEntry::now(AuditOp::Delete, index, merkle_root.clone())),
+        );
+        self.root_history.write().await.push(merkle_root.clone());
+
+        self.install_tree(tree, commitments.len()).await;
+
+        Ok((selected, merkle_root))
+    }
+
+    /// Store an already-committed value (hashed in hash-only mode), rebuilding the tree.
+    /// Appends go through the write batcher when a batch window is configured.
+    async fn store_committed(
+        &self,
+        index: Option<usize>,
+        value: Vec<u8>,
+        metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        let _pending = PendingWrite::new(&self.pending_writes);
+        if let (None, Some(window)) = (index, self.config.batch_window) {
+            let (reply, result) = oneshot::channel();
+            self.batcher(window)
+                .send(PendingAppend { value, metadata, reply })
+                .map_err(|_| AppError::Internal("Write batcher has stopped".to_string()))?;
+            return result
+                .await
+                .map_err(|_| AppError::Internal("Write batcher dropped a write".to_string()))?;
+        }
+        self.write_slots(index, vec![SlotWrite::Value(value, metadata)]).await
+    }
+
+    /// Sender to the background task flushing batched appends, started on first use.
+    /// The task holds a copy of this storage without the sender, so it exits once
+    /// every handle to the storage is dropped.
+    fn batcher(&self, window: Duration) -> &mpsc::UnboundedSender<PendingAppend> {
+        self.batcher.get_or_init(|| {
+            let (sender, pending) = mpsc::unbounded_channel();
+            let flusher = MemoryStorage {
+                batcher: Arc::new(OnceLock::new()),
+                ..self.clone()
+            };
+            tokio::spawn(flusher.flush_appends(window, pending));
+            sender
+        })
+    }
+
+    /// Collect appends arriving within `window` of the first and write them with one
+    /// rebuild, answering each with its own index and the shared root
+    async fn flush_appends(self, window: Duration, mut pending: mpsc::UnboundedReceiver<PendingAppend>) {
+        while let Some(first) = pending.recv().await {
+            tokio::time::sleep(window).await;
+            let mut batch = vec![first];
+            while let Ok(next) = pending.try_recv() {
+                batch.push(next);
+            }
+
+            let (values, replies): (Vec<_>, Vec<_>) = batch
+                .into_iter()
+                .map(|p| (SlotWrite::Value(p.value, p.metadata), p.reply))
+                .unzip();
+            match self.write_slots(None, values).await {
+                Ok((first_index, merkle_root)) => {
+                    for (offset, reply) in replies.into_iter().enumerate() {
+                        let _ = reply.send(Ok((first_index + offset, merkle_root.clone())));
+                    }
+                }
+                Err(error) => {
+                    for reply in replies {
+                        let _ = reply.send(Err(error.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write already-committed values into consecutive slots from `index` (appending
+    /// for None) with a single rebuild. Returns the first slot written and the new root.
+    async fn write_slots(
+        &self,
+        index: Option<usize>,
+        values: Vec<SlotWrite>,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        let _write = self.write_lock.lock().await;
 
-        // Build leaves for all commitments including the new one
-        let mut leaves = Vec::new();
-        for c in commitments.iter() {
-            leaves.push(MerkleNode::new_leaf(&c.value));
+        // Snapshot the existing values; readers stay unblocked during the rebuild
+        let mut stored = stored_slots(&self.commitments.read().await);
+        let existing_len = stored.len();
+        let op = if index.is_some() { AuditOp::Insert } else { AuditOp::Add };
+        let index = index.unwrap_or(existing_len);
+        let end = index
+            .checked_add(values.len())
+            .ok_or(AppError::InvalidInput(format!("Index {} is out of range", index)))?;
+        if let Some(taken) = (index..end).find(|&i| stored.get(i).is_some_and(Option::is_some)) {
+            return Err(AppError::Conflict(format!(
+                "Commitment with index {} already exists",
+                taken
+            )));
+        }
+        // Refuse before padding, which would allocate every placeholder up to `index`
+        let gap = index.saturating_sub(existing_len);
+        if let Some(max_gap) = self.config.max_index_gap.filter(|&max| gap > max) {
+            return Err(AppError::InvalidInput(format!(
+                "Index {} is {} slots past the end (max {})",
+                index, gap, max_gap
+            )));
+        }
+        if let Some(max_leaves) = self.config.max_leaves.filter(|&max| end > max) {
+            return Err(AppError::InvalidInput(format!(
+                "Index {} exceeds the maximum of {} leaves",
+                end - 1,
+                max_leaves
+            )));
         }
-        leaves.push(MerkleNode::new_leaf(&value));
+        if end > existing_len {
+            stored.resize(end, None);
+        }
+        let value_len: usize = values.iter().map(|slot| slot.value().len()).sum();
+
+        // A plain append only rehashes the paths to the new leaves. A duplicated
+        // single leaf isn't shaped like a prefix of a larger tree, so it's rebuilt.
+        let appending = index == existing_len
+            && (self.config.single_leaf_mode == SingleLeafMode::LeafIsRoot || existing_len > 1);
+        let current = match appending {
+            true => Some(self.tree.read().await.clone()),
+            false => None,
+        };
+
+        // Hashing is CPU-bound, so build the tree off the async runtime
+        let config = self.config.clone();
+        let (tree, elapsed, values) = self
+            .config
+            .run_rebuild(move || {
+                let new_leaves = values.iter().enumerate().map(|(offset, slot)| match slot.placeholder() {
+                    true => MerkleNode::empty_leaf(),
+                    false => config.leaf_for(index + offset, slot.value()),
+                });
+                let (tree, started) = match current {
+                    Some(current) => {
+                        let new_leaves: Vec<_> = new_leaves.collect();
+                        let started = Instant::now();
+                        let mut tree = MerkleTree::clone(&current);
+                        for leaf in new_leaves {
+                            tree.push_leaf(leaf);
+                        }
+                        (tree, started)
+                    }
+                    None => {
+                        let mut leaves = stored[..index]
+                            .iter()
+                            .enumerate()
+                            .map(|(i, v)| config.leaf(i, v.as_deref()))
+                            .collect::<Result<Vec<_>, AppError>>()?;
+                        leaves.extend(new_leaves);
+                        for (i, v) in stored.iter().enumerate().skip(end) {
+                            leaves.push(config.leaf(i, v.as_deref())?);
+                        }
+                        let started = Instant::now();
+                        (config.build_tree(leaves)?, started)
+                    }
+                };
+                let values = values
+                    .into_iter()
+                    .map(|slot| match slot {
+                        SlotWrite::Value(value, metadata) => Ok(SlotWrite::Value(config.encode_value(value)?, metadata)),
+                        SlotWrite::Restored(c) if c.placeholder => Ok(SlotWrite::Restored(c)),
+                        SlotWrite::Restored(c) => Ok(SlotWrite::Restored(Commitment {
+                            value: config.encode_value(c.value.to_vec())?.into(),
+                            ..c
+                        })),
+                    })
+                    .collect::<Result<Vec<_>, AppError>>()?;
+                Ok((tree, started.elapsed(), values))
+            })
+            .await?;
+        self.rebuilds.fetch_add(1, Ordering::Relaxed);
+        self.config.report_rebuild(elapsed, tree.leaf_count());
 
-        // Build merkle tree
-        let tree = MerkleTree::from_leaves(leaves);
         let merkle_root = tree
             .root_hash()
             .ok_or(AppError::TreeBuildError("Failed to build tree".to_string()))?;
 
-        // Store commitment
-        let commitment = Commitment::new(index, value, merkle_root.clone());
-        commitments.push(commitment);
+        // Store commitments, padding with placeholders up to the index.
+        // Writers are serialized, so the cached tree still holds the previous root.
+        let prev_root = self.tree.read().await.root_hash_or_empty();
+        let mut commitments = self.commitments.write().await;
+        self.total_bytes.fetch_add(value_len, Ordering::Relaxed);
+        for placeholder in existing_len..index {
+            commitments.push(
+                Commitment::new_placeholder(placeholder, merkle_root.clone()).with_prev_root(prev_root.clone()),
+            );
+        }
+        for (slot, value) in (index..end).zip(values) {
+            let commitment = match value {
+                SlotWrite::Value(value, metadata) => Commitment::new(slot, value, merkle_root.clone())
+                    .with_prev_root(prev_root.clone())
+                    .with_metadata(metadata),
+                SlotWrite::Restored(commitment) => Commitment { index: slot, ..commitment },
+            };
+            if slot < existing_len {
+                commitments[slot] = commitment;
+            } else {
+                commitments.push(commitment);
+            }
+        }
+        self.record_checkpoint(commitments.len(), &merkle_root).await;
+        self.root_history.write().await.push(merkle_root.clone());
+        self.audit_log
+            .write()
+            .await
+            .extend((index..end).map(|slot| AuditEntry::now(op, slot, merkle_root.clone())));
 
         // Update tree
-        *self.tree.write().await = tree;
+        self.install_tree(tree, commitments.len()).await;
 
         Ok((index, merkle_root))
     }
 
+    /// Keep `root` as a checkpoint if the tree just grew past the next multiple of the
+    /// interval. A padded or batched write can jump past a multiple, in which case the
+    /// checkpoint covers the size actually reached.
+    async fn record_checkpoint(&self, tree_size: usize, root: &[u8]) {
+        let Some(interval) = self.config.checkpoint_interval.filter(|&n| n > 0) else {
+            return;
+        };
+        let mut checkpoints = self.checkpoints.write().await;
+        let last = checkpoints.last().map_or(0, |c| c.tree_size / interval);
+        if tree_size / interval > last {
+            checkpoints.push(Checkpoint {
+                tree_size,
+                root: root.to_vec(),
+                timestamp: unix_now(),
+            });
+        }
+    }
+
+    /// Number of tree rebuilds writes have run so far
+    pub fn rebuild_count(&self) -> usize {
+        self.rebuilds.load(Ordering::Relaxed)
+    }
+
+    /// Test hook: the cached proof for `index`, if any
+    #[cfg(test)]
+    fn cached_proof(&self, index: usize) -> Option<Arc<MerkleProof>> {
+        self.proof_cache().get(&index).cloned()
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CommitmentStorage for MemoryStorage {
+    /// Rebuilds the tree on every write. Gaps are padded with placeholders;
+    /// only placeholders may be overwritten.
+    async fn store_commitment(
+        &self,
+        index: Option<usize>,
+        value: Vec<u8>,
+        metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        let value = self.config.committed_value(value)?;
+        self.store_committed(index, value, metadata).await
+    }
+
+    /// Skips the write batcher, whose batches could split the block
+    async fn append_block(&self, values: Vec<Vec<u8>>) -> Result<(usize, Vec<u8>), AppError> {
+        if values.is_empty() {
+            return Err(AppError::InvalidInput("Block must have at least one value".to_string()));
+        }
+        let values = values
+            .into_iter()
+            .map(|value| Ok(SlotWrite::Value(self.config.committed_value(value)?, CommitmentMetadata::default())))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        let _pending = PendingWrite::new(&self.pending_writes);
+        self.write_slots(None, values).await
+    }
+
+    async fn restore_slots(&self, slots: Vec<Commitment>) -> Result<Vec<u8>, AppError> {
+        let _pending = PendingWrite::new(&self.pending_writes);
+        let slots = slots.into_iter().map(SlotWrite::Restored).collect();
+        let (_, root) = self.write_slots(None, slots).await?;
+        Ok(root)
+    }
+
+    async fn store_leaf_hash(
+        &self,
+        leaf_hash: Vec<u8>,
+        metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        if !self.config.hash_only {
+            return Err(AppError::InvalidInput(
+                "Leaf hashes can only be stored in hash-only storage".to_string(),
+            ));
+        }
+        self.config.check_hashable_in_advance()?;
+        if leaf_hash.len() != HASH_LEN {
+            return Err(AppError::InvalidInput(format!(
+                "Leaf hash must be {} bytes (got {})",
+                HASH_LEN,
+                leaf_hash.len()
+            )));
+        }
+        self.store_committed(None, leaf_hash, metadata).await
+    }
+
+    async fn mark_revealed(&self, index: usize) -> Result<(), AppError> {
+        let mut commitments = self.commitments.write().await;
+        let commitment = commitments
+            .get_mut(index)
+            .filter(|c| !c.is_expired())
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )))?;
+        match commitment.reveal {
+            Some(RevealState::Sealed) => {
+                commitment.reveal = Some(RevealState::Revealed);
+                Ok(())
+            }
+            Some(RevealState::Revealed) => Err(AppError::Conflict(format!(
+                "Commitment {} was already revealed",
+                index
+            ))),
+            None => Err(AppError::InvalidInput(format!(
+                "Commitment {} was not made through commit-reveal",
+                index
+            ))),
+        }
+    }
+
+    fn stores_hashes_only(&self) -> bool {
+        self.config.hash_only
+    }
+
+    fn leaf_hash_mode(&self) -> LeafHashMode {
+        self.config.leaf_hash_mode
+    }
+
+    fn single_leaf_mode(&self) -> SingleLeafMode {
+        self.config.single_leaf_mode
+    }
+
+    fn stale_root(&self) -> Option<StaleRoot> {
+        let published = self.published.load();
+        Some(StaleRoot {
+            root: published.0.clone(),
+            commitment_count: published.1,
+            pending: self.pending_writes.load(Ordering::Relaxed),
+        })
+    }
+
     async fn get_commitment(&self, index: usize) -> Result<Commitment, AppError> {
         let commitments = self.commitments.read().await;
+        let commitment = commitments
+            .get(index)
+            .filter(|c| !c.is_expired())
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )))?;
+        self.config.decode_commitment(commitment)
+    }
+
+    async fn get_commitment_owned_value(&self, index: usize) -> Result<Bytes, AppError> {
+        let commitments = self.commitments.read().await;
+        let commitment = commitments
+            .get(index)
+            .filter(|c| !c.is_expired())
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )))?;
+        self.config.decode_bytes(&commitment.value)
+    }
+
+    async fn get_leaf_hash(&self, index: usize) -> Result<Vec<u8>, AppError> {
+        let commitments = self.commitments.read().await;
+        let tree = self.tree.read().await;
         commitments
             .get(index)
-            .cloned()
+            .filter(|c| !c.is_expired())
+            .and_then(|_| tree.leaf(index))
+            .map(|leaf| leaf.hash.clone())
             .ok_or(AppError::NotFound(format!(
                 "Commitment with index {} not found",
                 index
@@ -73,29 +875,384 @@ impl CommitmentStorage for MemoryStorage {
 
     async fn get_all_commitments(&self) -> Result<Vec<Commitment>, AppError> {
         let commitments = self.commitments.read().await;
-        Ok(commitments.clone())
+        let now = unix_now();
+        commitments
+            .iter()
+            .filter(|c| !c.is_expired_at(now))
+            .map(|c| self.config.decode_commitment(c))
+            .collect()
+    }
+
+    async fn get_commitment_by_root(&self, root: &[u8]) -> Result<Commitment, AppError> {
+        let commitments = self.commitments.read().await;
+        let now = unix_now();
+        let commitment = commitments
+            .iter()
+            .rfind(|c| c.merkle_root == root && !c.is_expired_at(now))
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with root {} not found",
+                hex::encode(root)
+            )))?;
+        self.config.decode_commitment(commitment)
+    }
+
+    async fn get_commitments_after(
+        &self,
+        cursor: Option<usize>,
+    ) -> Result<Vec<Commitment>, AppError> {
+        let start = cursor.map_or(0, |c| c.saturating_add(1));
+        let commitments = self.commitments.read().await;
+        commitments
+            .iter()
+            .skip(start)
+            .map(|c| self.config.decode_commitment(c))
+            .collect()
+    }
+
+    async fn replica_checkpoint(&self) -> Result<Option<(usize, Vec<u8>)>, AppError> {
+        let commitments = self.commitments.read().await;
+        Ok(commitments.last().map(|c| (c.index, c.merkle_root.clone())))
+    }
+
+    async fn get_proof(&self, index: usize) -> Result<Arc<MerkleProof>, AppError> {
+        // Same lock order as writers, so the cache can't outlive the tree it was built from
+        let commitments = self.commitments.read().await;
+        let tree = self.tree.read().await;
+        let commitment = commitments
+            .get(index)
+            .filter(|c| !c.is_expired())
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )))?;
+        if let Some(proof) = self.proof_cache().get(&index) {
+            return Ok(proof.clone());
+        }
+
+        let root = tree
+            .root()
+            .ok_or(AppError::NotFound("Merkle tree root not found".to_string()))?;
+        let proof = Arc::new(MerkleProof::for_commitment(
+            &self.config.decode_commitment(commitment)?,
+            generate_proof(root, index, tree.leaf_count()),
+            root.hash.clone(),
+            self.config.hash_only,
+            self.config.leaf_hash_mode,
+        ));
+
+        // A concurrent request may have raced us here; keep whichever landed first
+        Ok(self.proof_cache().entry(index).or_insert(proof).clone())
+    }
+
+    async fn sweep_expired(&self, now: u64) -> Result<usize, AppError> {
+        let (expired, _) = self.tombstone(|c| c.is_expired_at(now)).await?;
+        Ok(expired.len())
+    }
+
+    async fn delete_commitment(&self, index: usize) -> Result<Vec<u8>, AppError> {
+        let (deleted, root) = self
+            .tombstone(|c| c.index == index)
+            .await?;
+        if deleted.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )));
+        }
+        Ok(root)
+    }
+
+    async fn compact_delete(&self, index: usize) -> Result<(Vec<u8>, Vec<IndexRemap>), AppError> {
+        let _write = self.write_lock.lock().await;
+
+        let mut stored = stored_slots(&self.commitments.read().await);
+        let old_len = stored.len();
+        let Some(removed) = stored.get(index).cloned().flatten() else {
+            return Err(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )));
+        };
+        stored.remove(index);
+        let removed_bytes = self.config.decode_value(&removed)?.len();
+
+        let tree = self.rebuild_from(stored).await?;
+        let merkle_root = tree.root_hash_or_empty();
+
+        // Later commitments keep their own roots but move down one slot
+        let mut commitments = self.commitments.write().await;
+        commitments.remove(index);
+        for commitment in &mut commitments[index..] {
+            commitment.index -= 1;
+        }
+        self.total_bytes.fetch_sub(removed_bytes, Ordering::Relaxed);
+        self.audit_log
+            .write()
+            .await
+            .push(AuditEntry::now(AuditOp::Compact, index, merkle_root.clone()));
+        self.root_history.write().await.push(merkle_root.clone());
+
+        self.install_tree(tree, commitments.len()).await;
+
+        let remapped = (index + 1..old_len)
+            .map(|old_index| IndexRemap {
+                old_index,
+                new_index: old_index - 1,
+            })
+            .collect();
+        Ok((merkle_root, remapped))
     }
 
     async fn get_tree(&self) -> Result<MerkleTree, AppError> {
         let tree = self.tree.read().await;
-        Ok(tree.clone())
+        Ok(MerkleTree::clone(&tree))
+    }
+
+    async fn snapshot(&self) -> Result<TreeSnapshot, AppError> {
+        // Same lock order as writers, so the values and tree are from one write
+        let commitments = self.commitments.read().await;
+        let tree = self.tree.read().await;
+        let commitments = commitments
+            .iter()
+            .map(|c| self.config.decode_commitment(c))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(TreeSnapshot::new(
+            tree.clone(),
+            commitments,
+            self.config.hash_only,
+            self.config.leaf_hash_mode,
+        )
+        .with_single_leaf_mode(self.config.single_leaf_mode))
+    }
+
+    async fn with_tree<'a>(
+        &'a self,
+        f: Box<dyn for<'t> FnOnce(&'t MerkleTree) + Send + 'a>,
+    ) -> Result<(), AppError> {
+        let tree = self.tree.read().await;
+        f(&tree);
+        Ok(())
     }
 
     async fn get_root_hash(&self) -> Result<Vec<u8>, AppError> {
         let tree = self.tree.read().await;
-        tree.root_hash()
-            .ok_or(AppError::NotFound("No root hash available".to_string()))
+        Ok(tree.root_hash_or_empty())
+    }
+
+    async fn try_get_root_hash(&self) -> Result<Option<Vec<u8>>, AppError> {
+        let tree = self.tree.read().await;
+        Ok(tree.root_hash())
     }
 
     async fn commitment_count(&self) -> Result<usize, AppError> {
         let commitments = self.commitments.read().await;
         Ok(commitments.len())
     }
+
+    async fn total_bytes(&self) -> Result<usize, AppError> {
+        Ok(self.total_bytes.load(Ordering::Relaxed))
+    }
+
+    async fn verify_integrity(&self) -> Result<bool, AppError> {
+        let commitments = self.commitments.read().await;
+        let cached = self.tree.read().await;
+
+        let (hash_only, mode) = (self.config.hash_only, self.config.leaf_hash_mode);
+        let decoded = commitments
+            .iter()
+            .map(|c| self.config.decode_commitment(c))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        let rebuilt = self
+            .config
+            .build_tree(decoded.iter().map(|c| c.leaf(hash_only, mode)).collect())?;
+        if rebuilt.root_hash() != cached.root_hash() || rebuilt.leaf_count() != cached.leaf_count() {
+            return Ok(false);
+        }
+
+        let Some(root) = cached.root() else {
+            return Ok(true);
+        };
+
+        // Spot-check proofs from the cached tree against the stored values
+        let random = RandomState::new();
+        for i in 0..INTEGRITY_SAMPLE_SIZE.min(decoded.len()) {
+            let index = (random.hash_one(i) as usize) % decoded.len();
+            let proof = MerkleProof::for_commitment(
+                &decoded[index],
+                generate_proof(root, index, cached.leaf_count()),
+                root.hash.clone(),
+                hash_only,
+                mode,
+            );
+            if !proof.verify() {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, AppError> {
+        Ok(self.audit_log.read().await.clone())
+    }
+
+    async fn checkpoints(&self) -> Result<Vec<Checkpoint>, AppError> {
+        Ok(self.checkpoints.read().await.clone())
+    }
+
+    async fn root_history(&self) -> Result<Vec<Vec<u8>>, AppError> {
+        Ok(self.root_history.read().await.clone())
+    }
+
+    async fn verify_chain(&self) -> Result<bool, AppError> {
+        let commitments = self.commitments.read().await;
+        let history = self.root_history.read().await;
+        let audit = self.audit_log.read().await;
+        let tree = self.tree.read().await;
+
+        let head = history.last().map_or(EMPTY_ROOT.as_slice(), Vec::as_slice);
+        Ok(self.replays_history(&commitments, &history, &audit)? && head == tree.root_hash_or_empty())
+    }
+}
+
+/// Record what a replayed slot holds, keeping count of the slots whose value is unknown
+fn set_slot<'a>(slots: &mut Vec<Option<Option<&'a [u8]>>>, unknown: &mut usize, index: usize, slot: Option<Option<&'a [u8]>>) {
+    if index >= slots.len() {
+        *unknown += index + 1 - slots.len();
+        slots.resize(index + 1, None);
+    }
+    *unknown -= usize::from(slots[index].is_none());
+    *unknown += usize::from(slot.is_none());
+    slots[index] = slot;
+}
+
+impl MemoryStorage {
+    /// Replay the writes in `history` in order, putting each commitment back in the
+    /// slot it held when the write whose link it carries ran, and check every root
+    /// that can be recomputed. A write whose slots were all rewritten since (tombstoned,
+    /// filled or compacted away) is carried by no commitment; the audit log says which
+    /// slots it touched, and roots go unchecked while any of their values is unknown.
+    fn replays_history(
+        &self,
+        commitments: &[Commitment],
+        history: &[Vec<u8>],
+        audit: &[AuditEntry],
+    ) -> Result<bool, AppError> {
+        let steps: Vec<(&[u8], &[u8])> = std::iter::once(EMPTY_ROOT.as_slice())
+            .chain(history.iter().map(Vec::as_slice))
+            .zip(history.iter().map(Vec::as_slice))
+            .collect();
+        // A root can recur, e.g. after compacting away the last slot; the latest step wins
+        let step_of: HashMap<(&[u8], &[u8]), usize> =
+            steps.iter().enumerate().map(|(step, &link)| (link, step)).collect();
+        let mut carried: Vec<Vec<&Commitment>> = vec![Vec::new(); steps.len()];
+        for commitment in commitments {
+            match step_of.get(&(commitment.prev_root.as_slice(), commitment.merkle_root.as_slice())) {
+                Some(&step) => carried[step].push(commitment),
+                None => return Ok(false),
+            }
+        }
+
+        // Every write logs one audit entry per slot written, all with its root
+        let mut writes: Vec<&[AuditEntry]> = Vec::with_capacity(steps.len());
+        let mut rest = audit;
+        for root in history {
+            let (write, tail) = rest.split_at(rest.iter().take_while(|entry| entry.root == *root).count());
+            writes.push(write);
+            rest = tail;
+        }
+        let compactions: Vec<(usize, usize)> = writes
+            .iter()
+            .enumerate()
+            .flat_map(|(step, write)| {
+                write
+                    .iter()
+                    .filter(|entry| entry.op == AuditOp::Compact)
+                    .map(move |entry| (step, entry.index))
+            })
+            .collect();
+        // Where slot `index` sat at `step`, undoing the compactions since
+        let position_at = |index: usize, step: usize| {
+            compactions
+                .iter()
+                .rev()
+                .take_while(|&&(at, _)| at > step)
+                .fold(index, |position, &(_, removed)| position + usize::from(position >= removed))
+        };
+
+        // Each slot's stored value as of the current step: None while unknown, Some(None)
+        // for a placeholder. `replayed` covers a prefix of the slots.
+        let mut slots: Vec<Option<Option<&[u8]>>> = Vec::new();
+        let mut unknown = 0;
+        let mut unaccounted = false;
+        let mut replayed = MerkleTree::new();
+        for (step, (write, carried)) in writes.iter().zip(&carried).enumerate() {
+            let mut touched = usize::MAX;
+            for entry in write.iter() {
+                touched = touched.min(entry.index);
+                match entry.op {
+                    AuditOp::Compact if entry.index < slots.len() => {
+                        unknown -= usize::from(slots.remove(entry.index).is_none());
+                    }
+                    AuditOp::Compact => return Ok(false),
+                    // Set again below if the slot still carries this write's link
+                    op => set_slot(&mut slots, &mut unknown, entry.index, (op == AuditOp::Delete).then_some(None)),
+                }
+            }
+            for commitment in carried {
+                let position = position_at(commitment.index, step);
+                touched = touched.min(position);
+                let value = (!commitment.placeholder).then_some(commitment.value.as_ref());
+                set_slot(&mut slots, &mut unknown, position, Some(value));
+            }
+            let (prev_root, root) = steps[step];
+            unaccounted |= write.is_empty() && carried.is_empty() && prev_root != root;
+            if touched < replayed.leaf_count() {
+                replayed = MerkleTree::new();
+            }
+            if unaccounted || unknown > 0 {
+                continue;
+            }
+
+            for (position, slot) in slots.iter().enumerate().skip(replayed.leaf_count()) {
+                replayed.push_leaf(self.config.leaf(position, slot.flatten())?);
+            }
+            // Appending keeps the multi-leaf shape, so only a lone leaf needs the mode
+            let recomputed = match replayed.root() {
+                Some(leaf) if replayed.leaf_count() == 1 => {
+                    self.config.build_tree(vec![leaf.clone()])?.root_hash_or_empty()
+                }
+                _ => replayed.root_hash_or_empty(),
+            };
+            if recomputed != root {
+                return Ok(false);
+            }
+        }
+        if unknown > 0 || slots.len() != commitments.len() {
+            return Ok(false);
+        }
+
+        // Without every intermediate root, at least the values left must give the head
+        if unaccounted {
+            let leaves = slots
+                .iter()
+                .enumerate()
+                .map(|(position, slot)| self.config.leaf(position, slot.flatten()))
+                .collect::<Result<Vec<_>, AppError>>()?;
+            let head = history.last().map_or(EMPTY_ROOT.as_slice(), Vec::as_slice);
+            return Ok(self.config.build_tree(leaves)?.root_hash_or_empty() == head);
+        }
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::merkle::{EMPTY_LEAF_HASH, EMPTY_ROOT};
+    use sha2::{Digest, Sha256};
+    use std::collections::HashSet;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_add_and_get_commitment() {
@@ -109,6 +1266,67 @@ mod tests {
         assert_eq!(commitment.value, data);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_rebuild_limiter_caps_concurrent_rebuilds() {
+        let limiter = RebuildLimiter::new(2);
+        let storages: Vec<Arc<MemoryStorage>> = (0..4)
+            .map(|_| {
+                Arc::new(MemoryStorage::with_config(MemoryStorageConfig {
+                    rebuild_limiter: Some(limiter.clone()),
+                    ..Default::default()
+                }))
+            })
+            .collect();
+
+        let tasks: Vec<_> = (0..100)
+            .map(|i| {
+                let storage = storages[i % storages.len()].clone();
+                tokio::spawn(async move { storage.add_commitment(format!("data{}", i).into_bytes()).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert!((1..=2).contains(&limiter.peak()), "peak {}", limiter.peak());
+        for (s, storage) in storages.iter().enumerate() {
+            assert_eq!(storage.commitment_count().await.unwrap(), 25);
+            assert!(storage.verify_integrity().await.unwrap());
+            let mut values: Vec<_> = storage
+                .get_all_commitments()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|c| String::from_utf8(c.value.to_vec()).unwrap())
+                .collect();
+            values.sort();
+            let mut expected: Vec<_> = (0..100).filter(|i| i % 4 == s).map(|i| format!("data{}", i)).collect();
+            expected.sort();
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_owned_value_shares_buffer() {
+        let storage = MemoryStorage::new();
+        let data = vec![7u8; 4096];
+        storage.add_commitment(data.clone()).await.unwrap();
+
+        let value = storage.get_commitment_owned_value(0).await.unwrap();
+        assert_eq!(value, data);
+
+        // Clones and repeat reads point at the same stored allocation
+        let again = storage.get_commitment_owned_value(0).await.unwrap();
+        assert_eq!(value.clone().as_ptr(), value.as_ptr());
+        assert_eq!(again.as_ptr(), value.as_ptr());
+        assert_eq!(storage.get_commitment(0).await.unwrap().value.as_ptr(), value.as_ptr());
+
+        assert!(matches!(
+            storage.get_commitment_owned_value(1).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_multiple_commitments() {
         let storage = MemoryStorage::new();
@@ -120,6 +1338,9 @@ mod tests {
 
         let count = storage.commitment_count().await.unwrap();
         assert_eq!(count, 5);
+
+        let total_bytes = storage.total_bytes().await.unwrap();
+        assert_eq!(total_bytes, 5 * "data0".len());
     }
 
     #[tokio::test]
@@ -128,4 +1349,786 @@ mod tests {
         let result = storage.get_commitment(999).await;
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_root_hash_empty_and_populated() {
+        let storage = MemoryStorage::new();
+        assert_eq!(storage.get_root_hash().await.unwrap(), EMPTY_ROOT.to_vec());
+        assert_eq!(storage.try_get_root_hash().await.unwrap(), None);
+
+        let (_index, root) = storage.add_commitment(b"data".to_vec()).await.unwrap();
+        assert_eq!(storage.get_root_hash().await.unwrap(), root);
+        assert_eq!(storage.try_get_root_hash().await.unwrap(), Some(root));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_every_interval() {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            checkpoint_interval: Some(10),
+            ..Default::default()
+        });
+        let mut roots = Vec::new();
+        for i in 0..25 {
+            let (_, root) = storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+            roots.push(root);
+        }
+
+        let checkpoints = storage.checkpoints().await.unwrap();
+        let sizes: Vec<usize> = checkpoints.iter().map(|c| c.tree_size).collect();
+        assert_eq!(sizes, vec![10, 20]);
+        assert_eq!(checkpoints[0].root, roots[9]);
+        assert_eq!(checkpoints[1].root, roots[19]);
+
+        // A padded write past the next multiple checkpoints the size it reached
+        storage.insert_at(34, b"far".to_vec()).await.unwrap();
+        let last = storage.checkpoints().await.unwrap().pop().unwrap();
+        assert_eq!(last.tree_size, 35);
+        assert!(MemoryStorage::new().checkpoints().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_batch_window_coalesces_appends() {
+        let storage = Arc::new(MemoryStorage::with_config(MemoryStorageConfig {
+            batch_window: Some(Duration::from_millis(50)),
+            ..Default::default()
+        }));
+
+        let tasks: Vec<_> = (0..100)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    let value = format!("data{}", i).into_bytes();
+                    (value.clone(), storage.add_commitment(value).await)
+                })
+            })
+            .collect();
+        let mut indices = HashSet::new();
+        for task in tasks {
+            let (value, result) = task.await.unwrap();
+            let (index, root) = result.unwrap();
+            assert!(indices.insert(index), "index {} handed out twice", index);
+
+            // Each write sees the root of the batch it was flushed in
+            let commitment = storage.get_commitment(index).await.unwrap();
+            assert_eq!(commitment.value.as_ref(), value.as_slice());
+            assert_eq!(commitment.merkle_root, root);
+        }
+
+        assert_eq!(indices, (0..100).collect());
+        assert!(storage.rebuild_count() < 10, "{} rebuilds", storage.rebuild_count());
+        assert!(storage.verify_integrity().await.unwrap());
+        assert!(storage.verify_chain().await.unwrap());
+        assert_eq!(storage.audit_log().await.unwrap().len(), 100);
+        for index in [0, 57, 99] {
+            assert!(storage.get_proof(index).await.unwrap().verify());
+        }
+
+        // Inserts at a chosen index bypass the batcher
+        storage.insert_at(150, b"direct".to_vec()).await.unwrap();
+        assert_eq!(storage.commitment_count().await.unwrap(), 151);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_reads_not_starved_during_rebuild() {
+        let storage = MemoryStorage::new();
+        {
+            let mut commitments = storage.commitments.write().await;
+            for i in 0..50_000 {
+                commitments.push(Commitment::new(i, format!("data{}", i).into_bytes(), Vec::new()));
+            }
+        }
+
+        let writer = storage.clone();
+        let handle = tokio::spawn(async move { writer.add_commitment(b"new".to_vec()).await });
+
+        // Wait until the writer is inside its rebuild
+        while storage.write_lock.try_lock().is_ok() {
+            tokio::task::yield_now().await;
+        }
+
+        let count = tokio::time::timeout(Duration::from_millis(50), storage.commitment_count())
+            .await
+            .expect("read was blocked by the rebuild")
+            .unwrap();
+        assert_eq!(count, 50_000);
+
+        let (index, _root) = handle.await.unwrap().unwrap();
+        assert_eq!(index, 50_000);
+        assert_eq!(storage.commitment_count().await.unwrap(), 50_001);
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_compressed_values_round_trip() {
+        let compressed = MemoryStorage::with_config(MemoryStorageConfig {
+            compression_level: Some(3),
+            ..Default::default()
+        });
+        let plain = MemoryStorage::new();
+        let values = vec![vec![b'a'; 10_000], b"short".to_vec(), vec![7u8; 4096]];
+
+        for value in &values {
+            compressed.add_commitment(value.clone()).await.unwrap();
+            plain.add_commitment(value.clone()).await.unwrap();
+        }
+
+        // Values are smaller at rest but decompress to the original bytes
+        let stored_len = compressed.commitments.read().await[0].value.len();
+        assert!(stored_len < values[0].len());
+        for (index, value) in values.iter().enumerate() {
+            let commitment = compressed.get_commitment(index).await.unwrap();
+            assert_eq!(&commitment.value, value);
+            assert_eq!(
+                MerkleNode::new_leaf(&commitment.value).hash,
+                MerkleNode::new_leaf(value).hash
+            );
+        }
+
+        // Compression doesn't affect the root or byte accounting
+        assert_eq!(
+            compressed.get_root_hash().await.unwrap(),
+            plain.get_root_hash().await.unwrap()
+        );
+        assert_eq!(
+            compressed.total_bytes().await.unwrap(),
+            plain.total_bytes().await.unwrap()
+        );
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_encrypted_values_round_trip() {
+        let encrypted = MemoryStorage::with_config(MemoryStorageConfig {
+            encryption_key: Some(EncryptionKey::new([42; 32])),
+            ..Default::default()
+        });
+        let plain = MemoryStorage::new();
+        let values: Vec<Vec<u8>> = (0..5).map(|i| format!("secret{}", i).into_bytes()).collect();
+        for value in &values {
+            encrypted.add_commitment(value.clone()).await.unwrap();
+            plain.add_commitment(value.clone()).await.unwrap();
+        }
+        encrypted.insert_at(7, b"gap".to_vec()).await.unwrap();
+        plain.insert_at(7, b"gap".to_vec()).await.unwrap();
+
+        // Only ciphertext is held at rest; placeholders stay empty
+        {
+            let stored = encrypted.commitments.read().await;
+            for (commitment, value) in stored.iter().zip(&values) {
+                assert_ne!(&commitment.value, value);
+                assert!(!commitment.value.windows(value.len()).any(|w| w == value.as_slice()));
+            }
+            assert!(stored[5].value.is_empty());
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(&encrypted.get_commitment(index).await.unwrap().value, value);
+            assert!(encrypted.get_proof(index).await.unwrap().verify());
+        }
+
+        // Leaves are hashed over the plaintext
+        assert_eq!(
+            encrypted.get_root_hash().await.unwrap(),
+            plain.get_root_hash().await.unwrap()
+        );
+        assert_eq!(
+            encrypted.total_bytes().await.unwrap(),
+            plain.total_bytes().await.unwrap()
+        );
+        assert!(encrypted.verify_integrity().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_read_tree_borrows_without_cloning() {
+        let storage: Arc<dyn CommitmentStorage> = Arc::new(MemoryStorage::new());
+        for i in 0..4 {
+            storage
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+        }
+
+        let root_addr = |tree: &MerkleTree| tree.root().unwrap() as *const MerkleNode as usize;
+
+        // Both reads see the stored tree itself rather than a copy
+        let first = storage.read_tree(root_addr).await.unwrap();
+        let second = storage.read_tree(root_addr).await.unwrap();
+        assert_eq!(first, second);
+
+        let cloned = storage.get_tree().await.unwrap();
+        assert_ne!(root_addr(&cloned), first);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_is_unaffected_by_later_writes() {
+        let storage = MemoryStorage::new();
+        for i in 0..4 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        let snapshot = storage.snapshot().await.unwrap();
+        let root = snapshot.root_hash().unwrap();
+
+        for i in 4..8 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        storage.delete_commitment(1).await.unwrap();
+        assert_ne!(storage.get_root_hash().await.unwrap(), root);
+
+        assert_eq!(snapshot.root_hash().unwrap(), root);
+        assert_eq!(snapshot.leaf_count(), 4);
+        let proof = snapshot.proof(1).unwrap();
+        assert_eq!(proof.value, b"data1".to_vec());
+        assert_eq!(proof.root, root);
+        assert!(proof.verify());
+        assert!(matches!(snapshot.proof(4), Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_integrity() {
+        let storage = MemoryStorage::new();
+        assert!(storage.verify_integrity().await.unwrap());
+
+        for i in 0..10 {
+            storage
+                .add_commitment(format!("data{}", i).into_bytes())
+                .await
+                .unwrap();
+        }
+        assert!(storage.verify_integrity().await.unwrap());
+
+        // Corrupt a stored value behind the tree's back
+        storage.commitments.write().await[3].value = Bytes::from_static(b"corrupted");
+        assert!(!storage.verify_integrity().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_insert_at_gap_adds_placeholders() {
+        let storage = MemoryStorage::new();
+        storage.add_commitment(b"first".to_vec()).await.unwrap();
+
+        let (index, root) = storage.insert_at(4, b"keyed".to_vec()).await.unwrap();
+        assert_eq!(index, 4);
+        assert_eq!(storage.commitment_count().await.unwrap(), 5);
+        for placeholder in 1..4 {
+            let commitment = storage.get_commitment(placeholder).await.unwrap();
+            assert!(commitment.value.is_empty());
+        }
+        assert_eq!(storage.get_commitment(4).await.unwrap().value, b"keyed".to_vec());
+
+        // Root matches a tree with null leaves in the gap, and their proofs verify
+        let mut leaves = vec![MerkleNode::new_leaf(b"first")];
+        leaves.extend((1..4).map(|_| MerkleNode::empty_leaf()));
+        leaves.push(MerkleNode::new_leaf(b"keyed"));
+        assert_eq!(Some(root), MerkleTree::from_leaves(leaves).root_hash());
+        let proof = storage.get_proof(2).await.unwrap();
+        assert_eq!(proof.leaf_hash(), EMPTY_LEAF_HASH.to_vec());
+        assert!(proof.verify());
+
+        // Placeholders can be filled, real values can't be overwritten
+        storage.insert_at(2, b"filled".to_vec()).await.unwrap();
+        assert_eq!(storage.get_commitment(2).await.unwrap().value, b"filled".to_vec());
+        let result = storage.insert_at(2, b"again".to_vec()).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+        let result = storage.insert_at(0, b"again".to_vec()).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+
+        // Appending continues after the highest slot
+        let (index, _) = storage.add_commitment(b"next".to_vec()).await.unwrap();
+        assert_eq!(index, 5);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_proofs_share_allocation() {
+        let storage = MemoryStorage::new();
+        for i in 0..5 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        let (a, b) = tokio::join!(storage.get_proof(3), storage.get_proof(3));
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(Arc::ptr_eq(&a, &storage.cached_proof(3).unwrap()));
+        assert!(a.verify());
+
+        // A write moves the root, so the cached proof is dropped
+        storage.add_commitment(b"data5".to_vec()).await.unwrap();
+        assert!(storage.cached_proof(3).is_none());
+        let fresh = storage.get_proof(3).await.unwrap();
+        assert!(!Arc::ptr_eq(&a, &fresh));
+        assert_eq!(fresh.root, storage.get_root_hash().await.unwrap());
+        assert!(fresh.verify());
+    }
+
+    #[tokio::test]
+    async fn test_verify_chain_intact() {
+        let storage = MemoryStorage::new();
+        assert!(storage.verify_chain().await.unwrap());
+
+        for i in 0..4 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        let commitments = storage.get_all_commitments().await.unwrap();
+        assert_eq!(commitments[0].prev_root, EMPTY_ROOT.to_vec());
+        for pair in commitments.windows(2) {
+            assert_eq!(pair[1].prev_root, pair[0].merkle_root);
+        }
+        assert!(storage.verify_chain().await.unwrap());
+
+        // Keyed writes into and over a gap still form one chain
+        storage.insert_at(7, b"keyed".to_vec()).await.unwrap();
+        storage.insert_at(5, b"filled".to_vec()).await.unwrap();
+        storage.add_commitment(b"next".to_vec()).await.unwrap();
+        assert!(storage.verify_chain().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_chain_detects_tampering() {
+        let storage = MemoryStorage::new();
+        for i in 0..4 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        // Rewrite a past commitment's root as if it had been retroactively edited
+        storage.commitments.write().await[1].merkle_root = vec![0; 32];
+        assert!(!storage.verify_chain().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_chain_detects_edited_value() {
+        let storage = MemoryStorage::new();
+        for i in 0..4 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        storage.delete_commitment(3).await.unwrap();
+        assert!(storage.verify_chain().await.unwrap());
+
+        // The links still line up, but the roots replayed from the values don't
+        storage.commitments.write().await[1].value = Bytes::from_static(b"edited");
+        assert!(!storage.verify_chain().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_chain_detects_swapped_links() {
+        let storage = MemoryStorage::new();
+        for i in 0..4 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        // Every link is still a real step of the history, just on the wrong slot
+        let mut commitments = storage.commitments.write().await;
+        let (first, second) = commitments.split_at_mut(2);
+        std::mem::swap(&mut first[1].prev_root, &mut second[0].prev_root);
+        std::mem::swap(&mut first[1].merkle_root, &mut second[0].merkle_root);
+        drop(commitments);
+        assert!(!storage.verify_chain().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_expired() {
+        let storage = MemoryStorage::new();
+        let now = unix_now();
+        storage.add_commitment(b"keep".to_vec()).await.unwrap();
+        let expiring = CommitmentMetadata {
+            expires_at: Some(now),
+            ..Default::default()
+        };
+        storage
+            .store_commitment(None, b"ephemeral".to_vec(), expiring)
+            .await
+            .unwrap();
+        storage.add_commitment(b"also kept".to_vec()).await.unwrap();
+        let root_before = storage.get_root_hash().await.unwrap();
+
+        // Hidden from reads before the sweep runs
+        let all = storage.get_all_commitments().await.unwrap();
+        assert_eq!(all.iter().map(|c| c.index).collect::<Vec<_>>(), vec![0, 2]);
+        assert!(matches!(storage.get_commitment(1).await, Err(AppError::NotFound(_))));
+        assert!(matches!(storage.get_proof(1).await, Err(AppError::NotFound(_))));
+
+        assert_eq!(storage.sweep_expired(now).await.unwrap(), 1);
+        let root_after = storage.get_root_hash().await.unwrap();
+        assert_ne!(root_before, root_after);
+        assert!(storage.get_commitment(1).await.unwrap().value.is_empty());
+        assert_eq!(storage.total_bytes().await.unwrap(), b"keepalso kept".len());
+        assert!(storage.verify_integrity().await.unwrap());
+        assert!(storage.verify_chain().await.unwrap());
+
+        // Remaining proofs verify against the new root
+        let proof = storage.get_proof(2).await.unwrap();
+        assert_eq!(proof.root, root_after);
+        assert!(proof.verify());
+
+        assert_eq!(storage.sweep_expired(now).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_delete_commitment_leaves_placeholder() {
+        let storage = MemoryStorage::new();
+        for i in 0..3 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        let root = storage.delete_commitment(1).await.unwrap();
+        assert_eq!(root, storage.get_root_hash().await.unwrap());
+        assert!(storage.get_commitment(1).await.unwrap().value.is_empty());
+        assert_eq!(storage.get_commitment(2).await.unwrap().value, b"data2".to_vec());
+        assert!(storage.verify_integrity().await.unwrap());
+
+        // Already deleted, and never written
+        assert!(matches!(storage.delete_commitment(1).await, Err(AppError::NotFound(_))));
+        assert!(matches!(storage.delete_commitment(7).await, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_incremental_appends_match_full_rebuild() {
+        for single_leaf_mode in [SingleLeafMode::LeafIsRoot, SingleLeafMode::DuplicateToParent] {
+            let storage = MemoryStorage::with_config(MemoryStorageConfig {
+                single_leaf_mode,
+                ..Default::default()
+            });
+            for i in 0..20 {
+                match i {
+                    // A block, a gap filled by padding, and a tombstone mid-way
+                    7 => storage.append_block(vec![b"b0".to_vec(), b"b1".to_vec(), b"b2".to_vec()]).await.map(|_| ()),
+                    11 => storage.insert_at(14, b"after gap".to_vec()).await.map(|_| ()),
+                    16 => storage.delete_commitment(3).await.map(|_| ()),
+                    _ => storage.add_commitment(format!("data{}", i).into_bytes()).await.map(|_| ()),
+                }
+                .unwrap();
+
+                let leaves = storage
+                    .get_commitments_after(None)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|c| c.leaf(false, LeafHashMode::Plain))
+                    .collect();
+                let rebuilt = MerkleTree::from_leaves_with_mode(leaves, single_leaf_mode);
+                assert_eq!(storage.get_root_hash().await.unwrap(), rebuilt.root_hash().unwrap(), "{:?} {}", single_leaf_mode, i);
+                for index in 0..rebuilt.leaf_count() {
+                    assert!(storage.get_proof(index).await.unwrap().verify(), "{:?} {} {}", single_leaf_mode, i, index);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_committed_empty_value_differs_from_deleted_slot() {
+        for hash_only in [false, true] {
+            let storage = MemoryStorage::with_config(MemoryStorageConfig {
+                hash_only,
+                ..Default::default()
+            });
+            storage.add_commitment(Vec::new()).await.unwrap();
+            storage.add_commitment(b"deleted".to_vec()).await.unwrap();
+            storage.delete_commitment(1).await.unwrap();
+
+            assert!(!storage.get_commitment(0).await.unwrap().is_placeholder());
+            assert!(storage.get_commitment(1).await.unwrap().is_placeholder());
+
+            let (empty, deleted) = (storage.get_proof(0).await.unwrap(), storage.get_proof(1).await.unwrap());
+            assert_eq!(empty.leaf_hash(), LeafHashMode::Plain.hash(0, b""));
+            assert_eq!(deleted.leaf_hash(), EMPTY_LEAF_HASH.to_vec());
+            assert!(empty.verify() && deleted.verify());
+
+            // A null slot's proof can't be passed off as proving a value
+            let mut forged = MerkleProof::clone(&deleted);
+            forged.value = b"I owe you 1M".to_vec();
+            assert!(!forged.verify());
+            assert!(storage.verify_integrity().await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compact_delete_remaps_indices() {
+        let storage = MemoryStorage::new();
+        for i in 0..5 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        let (root, remapped) = storage.compact_delete(1).await.unwrap();
+        assert_eq!(
+            remapped
+                .iter()
+                .map(|r| (r.old_index, r.new_index))
+                .collect::<Vec<_>>(),
+            vec![(2, 1), (3, 2), (4, 3)]
+        );
+        assert_eq!(root, storage.get_root_hash().await.unwrap());
+        assert_eq!(storage.commitment_count().await.unwrap(), 4);
+        assert_eq!(storage.total_bytes().await.unwrap(), 20);
+
+        // Each moved value is found at its new index and proves against the new root
+        for remap in &remapped {
+            let commitment = storage.get_commitment(remap.new_index).await.unwrap();
+            assert_eq!(commitment.index, remap.new_index);
+            assert_eq!(commitment.value, format!("data{}", remap.old_index).into_bytes());
+
+            let proof = storage.get_proof(remap.new_index).await.unwrap();
+            assert_eq!(proof.root, root);
+            assert!(proof.verify());
+        }
+        assert!(storage.verify_integrity().await.unwrap());
+        assert!(storage.verify_chain().await.unwrap());
+
+        // Removing the last leaf moves nothing
+        let (_, remapped) = storage.compact_delete(3).await.unwrap();
+        assert!(remapped.is_empty());
+        assert!(matches!(storage.compact_delete(3).await, Err(AppError::NotFound(_))));
+        assert_eq!(
+            storage.audit_log().await.unwrap().last().map(|e| (e.op, e.index)),
+            Some((AuditOp::Compact, 3))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_records_mutations_in_order() {
+        let storage = MemoryStorage::new();
+        let now = unix_now();
+        let (_, first_root) = storage.add_commitment(b"keep".to_vec()).await.unwrap();
+        let expiring = CommitmentMetadata {
+            expires_at: Some(now),
+            ..Default::default()
+        };
+        let (_, second_root) = storage
+            .store_commitment(None, b"ephemeral".to_vec(), expiring)
+            .await
+            .unwrap();
+        let (_, third_root) = storage.insert_at(3, b"later".to_vec()).await.unwrap();
+        storage.sweep_expired(now).await.unwrap();
+        let swept_root = storage.get_root_hash().await.unwrap();
+
+        let log = storage.audit_log().await.unwrap();
+        let ops: Vec<_> = log.iter().map(|e| (e.op, e.index, e.root.clone())).collect();
+        assert_eq!(
+            ops,
+            vec![
+                (AuditOp::Add, 0, first_root),
+                (AuditOp::Add, 1, second_root),
+                (AuditOp::Insert, 3, third_root),
+                (AuditOp::Delete, 1, swept_root),
+            ]
+        );
+        assert!(log.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        assert!(log.iter().all(|e| e.timestamp >= now));
+    }
+
+    #[tokio::test]
+    async fn test_get_leaf_hash() {
+        let storage = MemoryStorage::new();
+        for i in 0..5 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+
+        for i in 0..5 {
+            let expected = Sha256::digest(format!("data{}", i).as_bytes()).to_vec();
+            assert_eq!(storage.get_leaf_hash(i).await.unwrap(), expected);
+        }
+        assert!(matches!(storage.get_leaf_hash(5).await, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_length_prefixed_proofs() {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            leaf_hash_mode: LeafHashMode::LengthPrefixed,
+            ..Default::default()
+        });
+        let plain = MemoryStorage::new();
+
+        // The 64-byte value is the concatenation of the other two leaves' hashes
+        let (left, right) = (b"left".to_vec(), b"right".to_vec());
+        let spliced = [Sha256::digest(&left).to_vec(), Sha256::digest(&right).to_vec()].concat();
+        for value in [left, right, spliced] {
+            storage.add_commitment(value.clone()).await.unwrap();
+            plain.add_commitment(value).await.unwrap();
+        }
+
+        // In plain mode the spliced leaf collides with the parent of the first two
+        let plain_tree = plain.get_tree().await.unwrap();
+        let plain_parent = plain_tree.root().unwrap().left.as_ref().unwrap().hash.clone();
+        assert_eq!(plain.get_leaf_hash(2).await.unwrap(), plain_parent);
+
+        let tree = storage.get_tree().await.unwrap();
+        let parent = tree.root().unwrap().left.as_ref().unwrap().hash.clone();
+        assert_ne!(storage.get_leaf_hash(2).await.unwrap(), parent);
+        assert_ne!(storage.get_root_hash().await.unwrap(), plain.get_root_hash().await.unwrap());
+
+        for i in 0..3 {
+            let proof = storage.get_proof(i).await.unwrap();
+            assert_eq!(proof.leaf_mode, LeafHashMode::LengthPrefixed);
+            assert!(proof.verify());
+            assert!(!proof.as_ref().clone().with_leaf_mode(LeafHashMode::Plain).verify());
+        }
+        assert!(storage.verify_integrity().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_index_bound_proofs() {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            leaf_hash_mode: LeafHashMode::IndexBound,
+            ..Default::default()
+        });
+        for _ in 0..4 {
+            storage.add_commitment(b"same".to_vec()).await.unwrap();
+        }
+
+        // The same value gets a different leaf in every slot
+        let leaves: HashSet<Vec<u8>> = futures_util::future::try_join_all((0..4).map(|i| storage.get_leaf_hash(i)))
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(leaves.len(), 4);
+
+        // A proof only verifies at the index it was issued for
+        let proof = storage.get_proof(1).await.unwrap();
+        assert_eq!(proof.leaf_mode, LeafHashMode::IndexBound);
+        assert!(proof.verify());
+        let mut replayed = proof.as_ref().clone();
+        replayed.index = 2;
+        assert!(!replayed.verify());
+        assert!(storage.verify_integrity().await.unwrap());
+
+        // Compacting moves later leaves down, rebinding them to their new slots
+        storage.compact_delete(0).await.unwrap();
+        assert!(storage.get_proof(0).await.unwrap().verify());
+        assert!(storage.verify_integrity().await.unwrap());
+
+        let hash_only = MemoryStorage::with_config(MemoryStorageConfig {
+            hash_only: true,
+            leaf_hash_mode: LeafHashMode::IndexBound,
+            ..Default::default()
+        });
+        let result = hash_only.add_commitment(b"value".to_vec()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_max_leaves_rejects_growth() {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            max_leaves: Some(3),
+            ..Default::default()
+        });
+        for i in 0..3 {
+            storage.add_commitment(vec![i]).await.unwrap();
+        }
+        let root = storage.get_root_hash().await.unwrap();
+
+        assert!(matches!(
+            storage.add_commitment(b"one too many".to_vec()).await,
+            Err(AppError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            storage.insert_at(usize::MAX - 1, b"far".to_vec()).await,
+            Err(AppError::InvalidInput(_))
+        ));
+        assert_eq!(storage.commitment_count().await.unwrap(), 3);
+        assert_eq!(storage.get_root_hash().await.unwrap(), root);
+    }
+
+    #[tokio::test]
+    async fn test_max_index_gap_caps_padding() {
+        let storage = MemoryStorage::new();
+        storage.add_commitment(b"first".to_vec()).await.unwrap();
+
+        let far = 1 + DEFAULT_MAX_INDEX_GAP + 1;
+        match storage.insert_at(far, b"far".to_vec()).await {
+            Err(AppError::InvalidInput(message)) => assert_eq!(
+                message,
+                format!("Index {} is {} slots past the end (max {})", far, far - 1, DEFAULT_MAX_INDEX_GAP)
+            ),
+            other => panic!("expected the gap to be refused, got {:?}", other),
+        }
+        assert!(matches!(
+            storage.insert_at(usize::MAX, b"far".to_vec()).await,
+            Err(AppError::InvalidInput(_))
+        ));
+        assert_eq!(storage.commitment_count().await.unwrap(), 1);
+
+        storage.insert_at(far - 1, b"edge".to_vec()).await.unwrap();
+        assert_eq!(storage.commitment_count().await.unwrap(), far);
+    }
+
+    #[tokio::test]
+    async fn test_hash_only_drops_values() {
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            hash_only: true,
+            ..Default::default()
+        });
+        let plain = MemoryStorage::new();
+        for i in 0..5 {
+            let value = format!("data{}", i).into_bytes();
+            storage.add_commitment(value.clone()).await.unwrap();
+            plain.add_commitment(value).await.unwrap();
+        }
+
+        // Only the hash is kept, and the tree matches one built from the originals
+        for i in 0..5 {
+            let value = format!("data{}", i).into_bytes();
+            let commitment = storage.get_commitment(i).await.unwrap();
+            assert_ne!(commitment.value, value);
+            assert_eq!(commitment.value, Sha256::digest(&value).to_vec());
+            assert_eq!(storage.get_leaf_hash(i).await.unwrap(), commitment.value);
+        }
+        assert_eq!(
+            storage.get_root_hash().await.unwrap(),
+            plain.get_root_hash().await.unwrap()
+        );
+        assert_eq!(storage.total_bytes().await.unwrap(), 5 * 32);
+
+        // Proofs carry the hash and still verify
+        for i in 0..5 {
+            let proof = storage.get_proof(i).await.unwrap();
+            assert!(proof.prehashed);
+            assert!(proof.verify());
+            assert_eq!(proof.proof, plain.get_proof(i).await.unwrap().proof);
+        }
+        assert!(storage.verify_integrity().await.unwrap());
+    }
+
+    /// Collects formatted log output for assertions
+    #[derive(Clone, Default)]
+    struct LogCapture(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_rebuild_logged() {
+        let logs = LogCapture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let quiet = MemoryStorage::with_config(MemoryStorageConfig {
+            slow_rebuild_threshold: None,
+            ..Default::default()
+        });
+        quiet.add_commitment(b"data".to_vec()).await.unwrap();
+        assert!(logs.0.lock().unwrap().is_empty());
+
+        let storage = MemoryStorage::with_config(MemoryStorageConfig {
+            slow_rebuild_threshold: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        storage.add_commitment(b"data".to_vec()).await.unwrap();
+        storage.add_commitment(b"more".to_vec()).await.unwrap();
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("WARN"), "{}", output);
+        assert!(output.contains("slow Merkle tree rebuild"), "{}", output);
+        assert!(output.contains("leaf_count=2"), "{}", output);
+        assert!(output.contains("elapsed_ms="), "{}", output);
+    }
+}
@@ -1,3 +1,13 @@
 pub mod traits;
 
-pub mod memory;
\ No newline at end of file
+pub mod memory;
+
+pub mod replica;
+
+pub mod expiry;
+
+pub mod dual_write;
+
+pub mod snapshot;
+
+pub mod self_test;
\ No newline at end of file
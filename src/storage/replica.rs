@@ -0,0 +1,157 @@
+use crate::error::AppError;
+use crate::models::commitment::Commitment;
+use crate::storage::traits::CommitmentStorage;
+use std::sync::Arc;
+
+/// Applies a leader's commitment stream to a follower, rejecting gaps,
+/// out-of-order entries and roots that don't match the follower's rebuild
+pub struct ReplicaApplier {
+    follower: Arc<dyn CommitmentStorage>,
+}
+
+impl ReplicaApplier {
+    /// Create an applier writing into the given follower storage
+    pub fn new(follower: Arc<dyn CommitmentStorage>) -> Self {
+        Self { follower }
+    }
+
+    /// Cursor to request the next batch from the leader with
+    pub async fn cursor(&self) -> Result<Option<usize>, AppError> {
+        Ok(self.follower.replica_checkpoint().await?.map(|(index, _)| index))
+    }
+
+    /// Validate and apply a single commitment
+    pub async fn apply(&self, commitment: Commitment) -> Result<(), AppError> {
+        self.apply_all(vec![commitment]).await.map(|_| ())
+    }
+
+    /// Apply a batch in order. Slots are replayed as the leader recorded them, null
+    /// slots included, and checked against the leader's roots the way an import is, so
+    /// a mismatch leaves only the checked commitments behind. A gap or out-of-order
+    /// entry refuses the whole batch. Returns the number of commitments applied.
+    pub async fn apply_all(&self, commitments: Vec<Commitment>) -> Result<usize, AppError> {
+        let next = self.follower.commitment_count().await?;
+        for (expected_index, commitment) in (next..).zip(&commitments) {
+            if commitment.index != expected_index {
+                return Err(AppError::InvalidInput(format!(
+                    "Out-of-order commitment: expected index {}, got {}",
+                    expected_index, commitment.index
+                )));
+            }
+        }
+        self.follower.append_validated(commitments).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::commitment::CommitmentMetadata;
+    use crate::models::merkle::{LeafHashMode, SingleLeafMode};
+    use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+    use bytes::Bytes;
+
+    async fn leader_with(values: &[&str]) -> MemoryStorage {
+        let leader = MemoryStorage::new();
+        for value in values {
+            leader.add_commitment(value.as_bytes().to_vec()).await.unwrap();
+        }
+        leader
+    }
+
+    #[tokio::test]
+    async fn test_replication_round_trip() {
+        let leader = leader_with(&["a", "b", "c", "d", "e"]).await;
+        let follower = Arc::new(MemoryStorage::new());
+        let applier = ReplicaApplier::new(follower.clone());
+
+        let batch = leader.get_commitments_after(applier.cursor().await.unwrap()).await.unwrap();
+        assert_eq!(applier.apply_all(batch).await.unwrap(), 5);
+        assert_eq!(
+            follower.replica_checkpoint().await.unwrap(),
+            leader.replica_checkpoint().await.unwrap()
+        );
+
+        // Incremental catch-up from the follower's cursor
+        for value in ["f", "g", "h"] {
+            leader.add_commitment(value.as_bytes().to_vec()).await.unwrap();
+        }
+        let cursor = applier.cursor().await.unwrap();
+        assert_eq!(cursor, Some(4));
+        let batch = leader.get_commitments_after(cursor).await.unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(applier.apply_all(batch).await.unwrap(), 3);
+
+        assert_eq!(
+            follower.replica_checkpoint().await.unwrap(),
+            Some((7, leader.get_root_hash().await.unwrap()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_gap_and_bad_root() {
+        let leader = leader_with(&["a", "b", "c"]).await;
+        let follower = Arc::new(MemoryStorage::new());
+        let applier = ReplicaApplier::new(follower.clone());
+        let stream = leader.get_commitments_after(None).await.unwrap();
+
+        // Skipping index 0 is a gap
+        let result = applier.apply(stream[1].clone()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+
+        applier.apply(stream[0].clone()).await.unwrap();
+
+        // A tampered value no longer matches the recorded root
+        let mut tampered = stream[1].clone();
+        tampered.value = Bytes::from_static(b"x");
+        let result = applier.apply(tampered).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+        assert_eq!(follower.commitment_count().await.unwrap(), 1);
+
+        // Replaying an applied commitment is out of order
+        let result = applier.apply(stream[0].clone()).await;
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replicates_null_slots_and_leaf_modes() {
+        let configs = [
+            MemoryStorageConfig {
+                leaf_hash_mode: LeafHashMode::LengthPrefixed,
+                single_leaf_mode: SingleLeafMode::DuplicateToParent,
+                ..Default::default()
+            },
+            MemoryStorageConfig {
+                hash_only: true,
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            let leader = MemoryStorage::with_config(config.clone());
+            let follower = Arc::new(MemoryStorage::with_config(config));
+            let applier = ReplicaApplier::new(follower.clone());
+
+            leader.add_commitment(b"a".to_vec()).await.unwrap();
+            let batch = leader.get_commitments_after(None).await.unwrap();
+            assert_eq!(applier.apply_all(batch).await.unwrap(), 1);
+
+            // An expired entry, a deleted slot and padding up to an inserted index
+            let expired = CommitmentMetadata {
+                expires_at: Some(0),
+                ..Default::default()
+            };
+            leader.store_commitment(None, b"b".to_vec(), expired).await.unwrap();
+            leader.add_commitment(b"c".to_vec()).await.unwrap();
+            leader.delete_commitment(2).await.unwrap();
+            leader.insert_at(5, b"f".to_vec()).await.unwrap();
+
+            let batch = leader.get_commitments_after(applier.cursor().await.unwrap()).await.unwrap();
+            assert_eq!(applier.apply_all(batch).await.unwrap(), 5);
+            assert_eq!(follower.get_root_hash().await.unwrap(), leader.get_root_hash().await.unwrap());
+            assert_eq!(
+                follower.get_commitments_after(None).await.unwrap(),
+                leader.get_commitments_after(None).await.unwrap()
+            );
+        }
+    }
+}
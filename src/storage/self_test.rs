@@ -0,0 +1,73 @@
+//! Startup self-test: commit a few values under the configured storage options and
+//! check every proof verifies, catching builder/verifier mismatches before serving
+
+use crate::crypto::proof::MerkleProof;
+use crate::error::AppError;
+use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+use crate::storage::traits::CommitmentStorage;
+
+/// Leaves committed by the self-test; five gives an odd level to duplicate
+const SELF_TEST_LEAVES: usize = 5;
+
+/// Whether `MERKLE_SELF_TEST` asks for the self-test to run at startup
+pub fn self_test_enabled_from_env() -> bool {
+    std::env::var("MERKLE_SELF_TEST").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Build a scratch store with `config`, then check each proof verifies as served
+/// and after a JSON round trip, as a client would receive it
+pub async fn self_test(config: MemoryStorageConfig) -> Result<(), AppError> {
+    let storage = MemoryStorage::with_config(config);
+    for i in 0..SELF_TEST_LEAVES {
+        storage
+            .add_commitment(format!("self-test-{}", i).into_bytes())
+            .await?;
+    }
+
+    let root = storage.get_root_hash().await?;
+    let failed = |index: usize, reason: &str| {
+        AppError::Internal(format!("Self-test proof for index {} {}", index, reason))
+    };
+    for index in 0..SELF_TEST_LEAVES {
+        let proof = storage.get_proof(index).await?;
+        if proof.root != root {
+            return Err(failed(index, "has a stale root"));
+        }
+        if !proof.verify() {
+            return Err(failed(index, "does not verify"));
+        }
+
+        let json = serde_json::to_vec(proof.as_ref())
+            .map_err(|e| AppError::Internal(format!("Failed to serialize proof: {}", e)))?;
+        let parsed: MerkleProof = serde_json::from_slice(&json)
+            .map_err(|e| AppError::Internal(format!("Failed to parse proof: {}", e)))?;
+        if !parsed.verify() {
+            return Err(failed(index, "does not verify after serialization"));
+        }
+    }
+
+    if !storage.verify_integrity().await? {
+        return Err(AppError::Internal(
+            "Self-test tree does not match its leaves".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::merkle::LeafHashMode;
+
+    #[tokio::test]
+    async fn test_self_test_passes() {
+        self_test(MemoryStorageConfig::default()).await.unwrap();
+
+        let hashed = MemoryStorageConfig {
+            hash_only: true,
+            leaf_hash_mode: LeafHashMode::LengthPrefixed,
+            ..Default::default()
+        };
+        self_test(hashed).await.unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+//! Snapshot files: a header (magic, format version, SHA-256 of the payload)
+//! followed by the JSON-encoded commitment log and the root it produced
+
+use crate::crypto::hash::sha256;
+use crate::error::AppError;
+use crate::models::commitment::Commitment;
+use crate::models::merkle::{MerkleNode, MerkleTree};
+use crate::storage::traits::CommitmentStorage;
+use serde::{Deserialize, Serialize};
+
+/// Leading bytes of every snapshot file
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"MRKLSNAP";
+
+/// Current snapshot format version. Version 1 marked null slots only by an empty
+/// value and hashed them like one; it is still loaded, see `migrate_v1`.
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/// Magic, big-endian version and payload checksum
+const HEADER_LEN: usize = SNAPSHOT_MAGIC.len() + 2 + 32;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotPayload {
+    root: Vec<u8>,
+    commitments: Vec<Commitment>,
+}
+
+/// Serialize every slot of `storage`, including placeholders and expired entries
+pub async fn save_snapshot(storage: &dyn CommitmentStorage) -> Result<Vec<u8>, AppError> {
+    let payload = serde_json::to_vec(&capture(storage).await?)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize snapshot: {}", e)))?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(SNAPSHOT_MAGIC);
+    bytes.extend_from_slice(&SNAPSHOT_VERSION.to_be_bytes());
+    bytes.extend_from_slice(&sha256(&[&payload]));
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Replay a snapshot into empty `storage`, returning the number of slots restored.
+/// The header is checked before anything is written, and the rebuilt root must match
+/// the one recorded in the snapshot.
+pub async fn load_snapshot(storage: &dyn CommitmentStorage, bytes: &[u8]) -> Result<usize, AppError> {
+    let (version, payload) = decode(bytes)?;
+    let payload = if version == 1 {
+        migrate_v1(storage, payload)?
+    } else {
+        payload
+    };
+    restore(storage, payload).await
+}
+
+/// Copy every slot of `from` into empty `to`, as a snapshot round trip would without
+/// the encoding. Returns the number of slots copied and the root both now share.
+pub async fn copy_storage(
+    from: &dyn CommitmentStorage,
+    to: &dyn CommitmentStorage,
+) -> Result<(usize, Vec<u8>), AppError> {
+    let payload = capture(from).await?;
+    let root = payload.root.clone();
+    Ok((restore(to, payload).await?, root))
+}
+
+/// Every slot of `storage` with the root they produce
+async fn capture(storage: &dyn CommitmentStorage) -> Result<SnapshotPayload, AppError> {
+    Ok(SnapshotPayload {
+        commitments: storage.get_commitments_after(None).await?,
+        root: storage.get_root_hash().await?,
+    })
+}
+
+/// Replay captured slots into empty `storage` and check the rebuilt root
+async fn restore(storage: &dyn CommitmentStorage, payload: SnapshotPayload) -> Result<usize, AppError> {
+    if storage.commitment_count().await? > 0 {
+        return Err(AppError::Conflict(
+            "Snapshots can only be loaded into empty storage".to_string(),
+        ));
+    }
+
+    // Placeholders are recreated by the padding around the slots that hold values
+    for commitment in payload.commitments.iter().filter(|c| !c.placeholder) {
+        storage
+            .store_commitment(Some(commitment.index), commitment.value.to_vec(), commitment.metadata())
+            .await?;
+    }
+    let restored = storage.commitment_count().await?;
+    if restored != payload.commitments.len() || storage.get_root_hash().await? != payload.root {
+        return Err(AppError::InvalidInput(
+            "Snapshot root does not match its commitments".to_string(),
+        ));
+    }
+    Ok(restored)
+}
+
+/// Flag the empty slots of a version 1 payload as placeholders, after checking its
+/// recorded root against the leaves version 1 hashed them to, and record the root
+/// the same slots produce with null leaves
+fn migrate_v1(storage: &dyn CommitmentStorage, mut payload: SnapshotPayload) -> Result<SnapshotPayload, AppError> {
+    let (hash_only, mode) = (storage.stores_hashes_only(), storage.leaf_hash_mode());
+    let root = |commitments: &[Commitment], legacy: bool| {
+        let leaves = commitments
+            .iter()
+            .map(|c| match legacy && c.placeholder {
+                true => MerkleNode::new_leaf_with_mode(c.index, b"", mode),
+                false => c.leaf(hash_only, mode),
+            })
+            .collect();
+        MerkleTree::from_leaves_with_mode(leaves, storage.single_leaf_mode())
+            .root_hash()
+            .unwrap_or_default()
+    };
+
+    for commitment in payload.commitments.iter_mut().filter(|c| c.value.is_empty()) {
+        commitment.placeholder = true;
+    }
+    if root(&payload.commitments, true) != payload.root {
+        return Err(AppError::InvalidInput(
+            "Snapshot root does not match its commitments".to_string(),
+        ));
+    }
+    payload.root = root(&payload.commitments, false);
+    Ok(payload)
+}
+
+/// Validate the header and checksum, then parse the payload and its format version
+fn decode(bytes: &[u8]) -> Result<(u16, SnapshotPayload), AppError> {
+    let invalid = |message: &str| AppError::InvalidInput(format!("Invalid snapshot: {}", message));
+    if bytes.len() < HEADER_LEN {
+        return Err(invalid("truncated header"));
+    }
+    let (magic, rest) = bytes.split_at(SNAPSHOT_MAGIC.len());
+    let (version, rest) = rest.split_at(2);
+    let (checksum, payload) = rest.split_at(32);
+
+    if magic != SNAPSHOT_MAGIC {
+        return Err(invalid("bad magic number"));
+    }
+    let version = u16::from_be_bytes([version[0], version[1]]);
+    if !(1..=SNAPSHOT_VERSION).contains(&version) {
+        return Err(invalid(&format!(
+            "unsupported version {} (expected at most {})",
+            version, SNAPSHOT_VERSION
+        )));
+    }
+    if sha256(&[payload]) != checksum {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let payload = serde_json::from_slice(payload).map_err(|e| invalid(&e.to_string()))?;
+    Ok((version, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::merkle::EMPTY_LEAF_HASH;
+    use crate::storage::memory::MemoryStorage;
+
+    async fn snapshot() -> (MemoryStorage, Vec<u8>) {
+        let storage = MemoryStorage::new();
+        for i in 0..5 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        storage.insert_at(8, b"gap".to_vec()).await.unwrap();
+        let bytes = save_snapshot(&storage).await.unwrap();
+        (storage, bytes)
+    }
+
+    fn rejected(result: Result<usize, AppError>, reason: &str) {
+        match result {
+            Err(AppError::InvalidInput(message)) => assert!(message.contains(reason), "{}", message),
+            other => panic!("expected InvalidInput({}), got {:?}", reason, other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let (original, bytes) = snapshot().await;
+        assert!(bytes.starts_with(SNAPSHOT_MAGIC));
+
+        let restored = MemoryStorage::new();
+        assert_eq!(load_snapshot(&restored, &bytes).await.unwrap(), 9);
+        assert_eq!(
+            restored.get_root_hash().await.unwrap(),
+            original.get_root_hash().await.unwrap()
+        );
+        assert!(restored.get_commitment(6).await.unwrap().value.is_empty());
+
+        // Loading over existing data is refused
+        let result = load_snapshot(&restored, &bytes).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_copy_storage() {
+        let (original, _) = snapshot().await;
+        let copy = MemoryStorage::new();
+
+        let (copied, root) = copy_storage(&original, &copy).await.unwrap();
+        assert_eq!(copied, 9);
+        assert_eq!(root, original.get_root_hash().await.unwrap());
+        assert_eq!(copy.get_root_hash().await.unwrap(), root);
+        assert_eq!(
+            copy.get_all_commitments().await.unwrap(),
+            original.get_all_commitments().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncated_snapshot_is_rejected() {
+        let (_, bytes) = snapshot().await;
+        let storage = MemoryStorage::new();
+
+        rejected(load_snapshot(&storage, &bytes[..bytes.len() - 10]).await, "checksum mismatch");
+        rejected(load_snapshot(&storage, &bytes[..HEADER_LEN - 1]).await, "truncated header");
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 0xff;
+        rejected(load_snapshot(&storage, &corrupted).await, "checksum mismatch");
+
+        let mut wrong_magic = bytes;
+        wrong_magic[0] = b'X';
+        rejected(load_snapshot(&storage, &wrong_magic).await, "bad magic number");
+
+        // Nothing was written by the failed loads
+        assert_eq!(storage.commitment_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_version_bump_is_rejected() {
+        let (_, mut bytes) = snapshot().await;
+        let version = SNAPSHOT_MAGIC.len();
+        bytes[version..version + 2].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_be_bytes());
+
+        rejected(load_snapshot(&MemoryStorage::new(), &bytes).await, "unsupported version 3");
+    }
+
+    /// A version 1 payload as it was written: null slots are empty values hashed like any other
+    fn v1_payload(values: &[&[u8]]) -> SnapshotPayload {
+        let leaves = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| MerkleNode::new_leaf_with_mode(i, v, Default::default()))
+            .collect();
+        let root = MerkleTree::from_leaves(leaves).root_hash().unwrap();
+        let commitments = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Commitment::new(i, v.to_vec(), root.clone()))
+            .collect();
+        SnapshotPayload { root, commitments }
+    }
+
+    fn v1_bytes(payload: &SnapshotPayload) -> Vec<u8> {
+        let payload = serde_json::to_vec(payload).unwrap();
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&sha256(&[&payload]));
+        bytes.extend_from_slice(&payload);
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_v1_snapshot_is_migrated() {
+        let mut payload = v1_payload(&[b"a", b"", b"c", b"d"]);
+        let storage = MemoryStorage::new();
+        assert_eq!(load_snapshot(&storage, &v1_bytes(&payload)).await.unwrap(), 4);
+
+        // The empty slot is now a placeholder with a null leaf
+        assert!(storage.get_commitment(1).await.unwrap().is_placeholder());
+        let proof = storage.get_proof(1).await.unwrap();
+        assert_eq!(proof.leaf_hash(), EMPTY_LEAF_HASH.to_vec());
+        assert!(proof.verify());
+
+        // A version 1 file whose root doesn't match its slots is still refused
+        payload.root[0] ^= 0xff;
+        rejected(load_snapshot(&MemoryStorage::new(), &v1_bytes(&payload)).await, "does not match");
+    }
+}
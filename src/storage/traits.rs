@@ -1,25 +1,718 @@
+use crate::crypto::{
+    hash::HashAlgorithm,
+    proof::{generate_proof, MerkleProof},
+};
 use crate::error::AppError;
-use crate::models::{commitment::Commitment, merkle::MerkleTree};
+use crate::models::{
+    audit::{AuditEntry, Checkpoint},
+    commitment::{unix_now, Commitment, CommitmentMetadata, IndexRemap},
+    merkle::{LeafHashMode, MerkleNode, MerkleTree, SingleLeafMode},
+};
 use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::Arc;
+
+/// An immutable view of the tree and its values at one instant, for serving several
+/// reads against the same root while writes carry on. Cloning shares the tree.
+#[derive(Debug, Clone)]
+pub struct TreeSnapshot {
+    tree: Arc<MerkleTree>,
+    /// Every slot with its decoded value, placeholders and expired entries included
+    commitments: Arc<[Commitment]>,
+    /// Unix time the snapshot was taken, which decides what had expired
+    taken_at: u64,
+    hashes_only: bool,
+    leaf_mode: LeafHashMode,
+    single_leaf_mode: SingleLeafMode,
+}
+
+impl TreeSnapshot {
+    pub fn new(
+        tree: Arc<MerkleTree>,
+        commitments: Vec<Commitment>,
+        hashes_only: bool,
+        leaf_mode: LeafHashMode,
+    ) -> Self {
+        Self {
+            tree,
+            commitments: commitments.into(),
+            taken_at: unix_now(),
+            hashes_only,
+            leaf_mode,
+            single_leaf_mode: SingleLeafMode::default(),
+        }
+    }
+
+    /// Set how the storage forms the root of a one-leaf tree, which `tree_at` follows
+    pub fn with_single_leaf_mode(mut self, mode: SingleLeafMode) -> Self {
+        self.single_leaf_mode = mode;
+        self
+    }
+
+    /// Get the tree as it was when the snapshot was taken
+    pub fn tree(&self) -> &MerkleTree {
+        &self.tree
+    }
+
+    /// Get the root hash, or None for an empty tree
+    pub fn root_hash(&self) -> Option<Vec<u8>> {
+        self.tree.root_hash()
+    }
+
+    /// Get the number of leaves
+    pub fn leaf_count(&self) -> usize {
+        self.tree.leaf_count()
+    }
+
+    /// Every slot the tree was built over, including placeholders and entries that
+    /// had expired (which are still leaves until swept)
+    pub fn commitments(&self) -> &[Commitment] {
+        &self.commitments
+    }
+
+    /// The tree over the first `size` slots, built the way the storage builds its own,
+    /// or None past the end
+    pub fn tree_at(&self, size: usize) -> Option<Arc<MerkleTree>> {
+        if size == self.leaf_count() {
+            return Some(self.tree.clone());
+        }
+        let leaves = self
+            .commitments
+            .get(..size)?
+            .iter()
+            .map(|c| c.leaf(self.hashes_only, self.leaf_mode))
+            .collect();
+        Some(Arc::new(MerkleTree::from_leaves_with_mode(leaves, self.single_leaf_mode)))
+    }
+
+    /// Get the commitment at `index` (`NotFound` if it had expired when the snapshot
+    /// was taken)
+    pub fn commitment(&self, index: usize) -> Result<&Commitment, AppError> {
+        self.commitments
+            .get(index)
+            .filter(|c| !c.is_expired_at(self.taken_at))
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with index {} not found",
+                index
+            )))
+    }
+
+    /// Prove the value at `index` against the snapshot's root
+    pub fn proof(&self, index: usize) -> Result<MerkleProof, AppError> {
+        let commitment = self.commitment(index)?;
+        let root = self
+            .tree
+            .root()
+            .ok_or(AppError::NotFound("Merkle tree root not found".to_string()))?;
+        Ok(MerkleProof::for_commitment(
+            commitment,
+            generate_proof(root, index, self.leaf_count()),
+            root.hash.clone(),
+            self.hashes_only,
+            self.leaf_mode,
+        ))
+    }
+}
+
+/// The last published root, read without waiting on writers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleRoot {
+    pub root: Vec<u8>,
+    /// Commitments covered by `root`
+    pub commitment_count: usize,
+    /// Writes accepted but not yet reflected in `root`
+    pub pending: usize,
+}
 
 /// Storage trait for commitment and Merkle tree operations
 #[async_trait]
 pub trait CommitmentStorage: Send + Sync {
     /// Add a new commitment and return its index
-    async fn add_commitment(&self, value: Vec<u8>) -> Result<(usize, Vec<u8>), AppError>;
+    async fn add_commitment(&self, value: Vec<u8>) -> Result<(usize, Vec<u8>), AppError> {
+        self.store_commitment(None, value, CommitmentMetadata::default()).await
+    }
+
+    /// Insert a commitment at a chosen index, padding any gap with placeholders.
+    /// Fails with `Conflict` if the slot already holds a value.
+    async fn insert_at(&self, index: usize, value: Vec<u8>) -> Result<(usize, Vec<u8>), AppError> {
+        self.store_commitment(Some(index), value, CommitmentMetadata::default()).await
+    }
+
+    /// Store a value at `index` (appending for None) with its content type and expiry.
+    /// The metadata is kept alongside the value and never hashed.
+    async fn store_commitment(
+        &self,
+        index: Option<usize>,
+        value: Vec<u8>,
+        metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError>;
+
+    /// Append `values` to consecutive slots in one write, so no other write lands
+    /// between them. Returns the first slot and the new root.
+    async fn append_block(&self, _values: Vec<Vec<u8>>) -> Result<(usize, Vec<u8>), AppError> {
+        Err(AppError::InvalidInput(
+            "This storage backend cannot append blocks".to_string(),
+        ))
+    }
+
+    /// Append `values` with a single tree rebuild, returning each one's index with
+    /// the root they were all committed under
+    async fn add_commitments(&self, values: Vec<Vec<u8>>) -> Result<Vec<(usize, Vec<u8>)>, AppError> {
+        let count = values.len();
+        let (first, root) = self.append_block(values).await?;
+        Ok((first..first + count).map(|index| (index, root.clone())).collect())
+    }
+
+    /// Append exported slots as they were in one write: values already committed (their
+    /// hashes in hash-only storage), placeholders as placeholders, and each slot keeping
+    /// the roots it was recorded with. Returns the new root.
+    async fn restore_slots(&self, _slots: Vec<Commitment>) -> Result<Vec<u8>, AppError> {
+        Err(AppError::InvalidInput(
+            "This storage backend cannot restore slots".to_string(),
+        ))
+    }
+
+    /// Append a leaf hash computed elsewhere, e.g. by streaming a value too large to
+    /// buffer. Only backends storing hashes in place of values can accept one.
+    async fn store_leaf_hash(
+        &self,
+        _leaf_hash: Vec<u8>,
+        _metadata: CommitmentMetadata,
+    ) -> Result<(usize, Vec<u8>), AppError> {
+        Err(AppError::InvalidInput(
+            "This storage backend cannot store leaf hashes directly".to_string(),
+        ))
+    }
+
+    /// Move a sealed commit-reveal commitment to `Revealed`. The caller checks the
+    /// value; a commitment already revealed is a `Conflict`.
+    async fn mark_revealed(&self, _index: usize) -> Result<(), AppError> {
+        Err(AppError::InvalidInput(
+            "This storage backend cannot record reveals".to_string(),
+        ))
+    }
+
+    /// Tombstone every commitment that had expired by `now` (Unix seconds), replacing
+    /// its value with a placeholder and rebuilding the tree once.
+    /// Returns the number of commitments removed.
+    async fn sweep_expired(&self, now: u64) -> Result<usize, AppError>;
+
+    /// Tombstone the commitment at `index`, leaving a placeholder so later
+    /// indices are stable. Returns the new root.
+    async fn delete_commitment(&self, index: usize) -> Result<Vec<u8>, AppError>;
+
+    /// Remove the commitment at `index` entirely and shift every later one down a
+    /// slot. Returns the new root and where each moved commitment now lives.
+    async fn compact_delete(&self, index: usize) -> Result<(Vec<u8>, Vec<IndexRemap>), AppError>;
+
+    /// Whether values are replaced by their SHA-256 on write, so each stored value
+    /// is the leaf hash itself and proofs are prehashed
+    fn stores_hashes_only(&self) -> bool {
+        false
+    }
 
-    /// Get a commitment by its index
+    /// Hash function the tree is built with
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        HashAlgorithm::Sha256
+    }
+
+    /// How values are hashed into leaves
+    fn leaf_hash_mode(&self) -> LeafHashMode {
+        LeafHashMode::default()
+    }
+
+    /// How a tree with exactly one leaf forms its root
+    fn single_leaf_mode(&self) -> SingleLeafMode {
+        SingleLeafMode::default()
+    }
+
+    /// The last published root without taking any lock, for serving reads during
+    /// write bursts; None when the backend can't provide one
+    fn stale_root(&self) -> Option<StaleRoot> {
+        None
+    }
+
+    /// Get a commitment by its index (`NotFound` once it has expired)
     async fn get_commitment(&self, index: usize) -> Result<Commitment, AppError>;
 
-    /// Get all commitments
+    /// Just the value at `index`, as a reference-counted buffer that responses can
+    /// share without copying
+    async fn get_commitment_owned_value(&self, index: usize) -> Result<Bytes, AppError> {
+        Ok(self.get_commitment(index).await?.value)
+    }
+
+    /// Get the leaf hash at `index` without copying the committed value
+    async fn get_leaf_hash(&self, index: usize) -> Result<Vec<u8>, AppError>;
+
+    /// Get all commitments, leaving out any that have expired
     async fn get_all_commitments(&self) -> Result<Vec<Commitment>, AppError>;
 
+    /// Get the commitment whose write produced `root`. When one write stamped several
+    /// slots with the same root (padding placeholders), the last of them is returned.
+    async fn get_commitment_by_root(&self, root: &[u8]) -> Result<Commitment, AppError> {
+        self.get_all_commitments()
+            .await?
+            .into_iter()
+            .rfind(|c| c.merkle_root == root)
+            .ok_or(AppError::NotFound(format!(
+                "Commitment with root {} not found",
+                hex::encode(root)
+            )))
+    }
+
+    /// Get commitments with an index greater than `cursor` (all of them for None)
+    async fn get_commitments_after(&self, cursor: Option<usize>)
+        -> Result<Vec<Commitment>, AppError>;
+
+    /// Get the last commitment index and the root it produced, or None when empty
+    async fn replica_checkpoint(&self) -> Result<Option<(usize, Vec<u8>)>, AppError>;
+
+    /// Get a proof for `index` against the current root. The proof is shared, so
+    /// repeated requests for the same leaf and root can reuse one allocation
+    async fn get_proof(&self, index: usize) -> Result<Arc<MerkleProof>, AppError>;
+
     /// Get the current Merkle tree
     async fn get_tree(&self) -> Result<MerkleTree, AppError>;
 
-    /// Get the current root hash
+    /// Take an immutable snapshot of the tree and its values; later writes don't
+    /// change it. The default reads the two separately, so backends that can should
+    /// take both under one lock.
+    async fn snapshot(&self) -> Result<TreeSnapshot, AppError> {
+        let commitments = self.get_commitments_after(None).await?;
+        let tree = self.get_tree().await?;
+        Ok(TreeSnapshot::new(
+            Arc::new(tree),
+            commitments,
+            self.stores_hashes_only(),
+            self.leaf_hash_mode(),
+        )
+        .with_single_leaf_mode(self.single_leaf_mode()))
+    }
+
+    /// Run a closure against the current Merkle tree without cloning it
+    async fn with_tree<'a>(
+        &'a self,
+        f: Box<dyn for<'t> FnOnce(&'t MerkleTree) + Send + 'a>,
+    ) -> Result<(), AppError>;
+
+    /// Get the current root hash, or the canonical empty root for an empty tree
     async fn get_root_hash(&self) -> Result<Vec<u8>, AppError>;
 
+    /// Get the current root hash, or None for an empty tree
+    async fn try_get_root_hash(&self) -> Result<Option<Vec<u8>>, AppError>;
+
     /// Get the total number of commitments
     async fn commitment_count(&self) -> Result<usize, AppError>;
-}
\ No newline at end of file
+
+    /// Get the total size of all committed values in bytes
+    async fn total_bytes(&self) -> Result<usize, AppError>;
+
+    /// Rebuild the tree from the stored commitments and check it against the
+    /// cached root, then spot-check proofs for a random sample of leaves
+    async fn verify_integrity(&self) -> Result<bool, AppError>;
+
+    /// Every mutation so far, oldest first. Backends that don't keep an audit
+    /// log return an empty one.
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, AppError> {
+        Ok(Vec::new())
+    }
+
+    /// Checkpointed roots, oldest first. Backends that don't take checkpoints return
+    /// none.
+    async fn checkpoints(&self) -> Result<Vec<Checkpoint>, AppError> {
+        Ok(Vec::new())
+    }
+
+    /// Every root the tree has had, oldest first. Backends that don't keep a root
+    /// history return an empty one.
+    async fn root_history(&self) -> Result<Vec<Vec<u8>>, AppError> {
+        Ok(Vec::new())
+    }
+
+    /// Reconstruct the in-memory tree from stored commitments, for backends that
+    /// persist commitments but not the tree. Called once at startup; memory storage
+    /// has nothing to recover, so the default does nothing.
+    async fn rebuild(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    /// Replay an exported commitment log into empty storage with `append_validated`.
+    /// Returns the number of slots imported.
+    async fn import_validated(&self, entries: Vec<Commitment>) -> Result<usize, AppError> {
+        if self.commitment_count().await? > 0 {
+            return Err(AppError::Conflict(
+                "Commitments can only be imported into empty storage".to_string(),
+            ));
+        }
+        self.append_validated(entries).await?;
+        self.commitment_count().await
+    }
+
+    /// Append exported slots after the ones already stored, in index order. Every
+    /// slot is held back until the root of the slots up to some entry at or after it
+    /// matches a held-back entry's recorded `merkle_root`, then written with the rest
+    /// of its group. Every slot in the group must record that same root, unless a
+    /// tombstone in the group recorded it: deleting or sweeping a slot rewrites the
+    /// tree the roots around it describe. A mismatch leaves only checked slots behind
+    /// and reports the first offending index with both roots. Placeholders are
+    /// replayed as placeholders and values as committed. Returns the number of slots
+    /// appended.
+    async fn append_validated(&self, entries: Vec<Commitment>) -> Result<usize, AppError> {
+        let (hash_only, leaf_mode) = (self.stores_hashes_only(), self.leaf_hash_mode());
+        let single_leaf_mode = self.single_leaf_mode();
+        let snapshot = self.snapshot().await?;
+        let mut tree = MerkleTree::new();
+        for slot in snapshot.commitments() {
+            tree.push_leaf(slot.leaf(hash_only, leaf_mode));
+        }
+        let start = tree.leaf_count();
+        let mut staged: Vec<Commitment> = Vec::new();
+        // Staged slots still waiting for their recorded root, with the root computed at
+        // each and whether the slot is a tombstone from the log rather than padding
+        let mut pending: Vec<(usize, Vec<u8>, Vec<u8>, bool)> = Vec::new();
+        let mismatch = |index: &usize, recorded: &[u8], computed: &[u8]| {
+            AppError::InvalidInput(format!(
+                "Commitment {} root mismatch: recorded {}, computed {}",
+                index,
+                hex::encode(recorded),
+                hex::encode(computed)
+            ))
+        };
+        for entry in entries {
+            let next = tree.leaf_count();
+            if entry.index < next {
+                return Err(AppError::InvalidInput(format!(
+                    "Commitment {} is out of order (expected index {} or later)",
+                    entry.index, next
+                )));
+            }
+            // A gap is padding, written along with the entry after it
+            let padding = (next..entry.index).map(|index| {
+                Commitment::new_placeholder(index, entry.merkle_root.clone())
+                    .with_prev_root(entry.prev_root.clone())
+            });
+            let slots = padding.map(|slot| (slot, false)).collect::<Vec<_>>();
+            let tombstone = entry.placeholder;
+            for (slot, tombstone) in slots.into_iter().chain([(entry, tombstone)]) {
+                tree.push_leaf(slot.leaf(hash_only, leaf_mode));
+                // Appending keeps the multi-leaf shape, so only a lone leaf needs the mode
+                let root = match tree.root() {
+                    Some(leaf) if tree.leaf_count() == 1 => {
+                        MerkleTree::from_leaves_with_mode(vec![leaf.clone()], single_leaf_mode)
+                            .root_hash_or_empty()
+                    }
+                    _ => tree.root_hash_or_empty(),
+                };
+                pending.push((slot.index, slot.merkle_root.clone(), root.clone(), tombstone));
+                staged.push(slot);
+                if !pending.iter().any(|(_, recorded, _, _)| *recorded == root) {
+                    continue;
+                }
+                let rewritten = pending
+                    .iter()
+                    .any(|(_, recorded, _, tombstone)| *tombstone && *recorded == root);
+                if !rewritten
+                    && let Some((index, recorded, computed, _)) =
+                        pending.iter().find(|(_, recorded, _, _)| *recorded != root)
+                {
+                    return Err(mismatch(index, recorded, computed));
+                }
+                pending.clear();
+                self.restore_slots(std::mem::take(&mut staged)).await?;
+            }
+        }
+        if let Some((index, recorded, computed, _)) = pending.first() {
+            return Err(mismatch(index, recorded, computed));
+        }
+        Ok(tree.leaf_count() - start)
+    }
+
+    /// Check that every write's `prev_root` links to the root left by the write before
+    /// it, from the empty root up to the current root, and that replaying the stored
+    /// values write by write reproduces those roots, so retroactive edits show up
+    async fn verify_chain(&self) -> Result<bool, AppError>;
+}
+
+impl dyn CommitmentStorage {
+    /// Borrow the current Merkle tree for the duration of `f` and return its result
+    pub async fn read_tree<R, F>(&self, f: F) -> Result<R, AppError>
+    where
+        R: Send,
+        F: FnOnce(&MerkleTree) -> R + Send,
+    {
+        let mut result = None;
+        self.with_tree(Box::new(|tree| result = Some(f(tree)))).await?;
+        result.ok_or(AppError::Internal("Tree closure was not run".to_string()))
+    }
+
+    /// Borrow the root node and leaf count for the duration of `f`; None for an empty
+    /// tree. The root owns every node below it, so this lends it rather than cloning.
+    pub async fn with_root_node<R, F>(&self, f: F) -> Result<Option<R>, AppError>
+    where
+        R: Send,
+        F: FnOnce(&MerkleNode, usize) -> R + Send,
+    {
+        self.read_tree(|tree| tree.root().map(|root| f(root, tree.leaf_count())))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof::generate_proof;
+    use crate::storage::memory::{MemoryStorage, MemoryStorageConfig};
+    use tokio::sync::RwLock;
+
+    /// Stand-in for a persistent backend: commitments survive a restart, the tree doesn't
+    struct PersistedLog {
+        commitments: Vec<Commitment>,
+        tree: RwLock<MerkleTree>,
+    }
+
+    #[async_trait]
+    impl CommitmentStorage for PersistedLog {
+        async fn store_commitment(
+            &self,
+            _index: Option<usize>,
+            _value: Vec<u8>,
+            _metadata: CommitmentMetadata,
+        ) -> Result<(usize, Vec<u8>), AppError> {
+            unimplemented!()
+        }
+
+        async fn sweep_expired(&self, _now: u64) -> Result<usize, AppError> {
+            unimplemented!()
+        }
+
+        async fn delete_commitment(&self, _index: usize) -> Result<Vec<u8>, AppError> {
+            unimplemented!()
+        }
+
+        async fn compact_delete(
+            &self,
+            _index: usize,
+        ) -> Result<(Vec<u8>, Vec<IndexRemap>), AppError> {
+            unimplemented!()
+        }
+
+        async fn get_commitment(&self, _index: usize) -> Result<Commitment, AppError> {
+            unimplemented!()
+        }
+
+        async fn get_leaf_hash(&self, _index: usize) -> Result<Vec<u8>, AppError> {
+            unimplemented!()
+        }
+
+        async fn get_all_commitments(&self) -> Result<Vec<Commitment>, AppError> {
+            Ok(self.commitments.clone())
+        }
+
+        async fn get_commitments_after(
+            &self,
+            _cursor: Option<usize>,
+        ) -> Result<Vec<Commitment>, AppError> {
+            unimplemented!()
+        }
+
+        async fn replica_checkpoint(&self) -> Result<Option<(usize, Vec<u8>)>, AppError> {
+            unimplemented!()
+        }
+
+        async fn get_proof(&self, _index: usize) -> Result<Arc<MerkleProof>, AppError> {
+            unimplemented!()
+        }
+
+        async fn get_tree(&self) -> Result<MerkleTree, AppError> {
+            Ok(self.tree.read().await.clone())
+        }
+
+        async fn with_tree<'a>(
+            &'a self,
+            f: Box<dyn for<'t> FnOnce(&'t MerkleTree) + Send + 'a>,
+        ) -> Result<(), AppError> {
+            f(&*self.tree.read().await);
+            Ok(())
+        }
+
+        async fn get_root_hash(&self) -> Result<Vec<u8>, AppError> {
+            Ok(self.tree.read().await.root_hash_or_empty())
+        }
+
+        async fn try_get_root_hash(&self) -> Result<Option<Vec<u8>>, AppError> {
+            Ok(self.tree.read().await.root_hash())
+        }
+
+        async fn commitment_count(&self) -> Result<usize, AppError> {
+            Ok(self.commitments.len())
+        }
+
+        async fn total_bytes(&self) -> Result<usize, AppError> {
+            unimplemented!()
+        }
+
+        async fn verify_integrity(&self) -> Result<bool, AppError> {
+            unimplemented!()
+        }
+
+        async fn rebuild(&self) -> Result<(), AppError> {
+            let leaves = self.commitments.iter().map(|c| MerkleNode::new_leaf(&c.value)).collect();
+            *self.tree.write().await = MerkleTree::from_leaves(leaves);
+            Ok(())
+        }
+
+        async fn verify_chain(&self) -> Result<bool, AppError> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_recovers_tree_from_persisted_commitments() {
+        let live = MemoryStorage::new();
+        for i in 0..5 {
+            live.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        let expected_root = live.get_root_hash().await.unwrap();
+
+        // After a restart only the commitments are left
+        let restarted = PersistedLog {
+            commitments: live.get_all_commitments().await.unwrap(),
+            tree: RwLock::new(MerkleTree::new()),
+        };
+        assert_eq!(restarted.try_get_root_hash().await.unwrap(), None);
+
+        restarted.rebuild().await.unwrap();
+        assert_eq!(restarted.get_root_hash().await.unwrap(), expected_root);
+        assert_eq!(restarted.get_tree().await.unwrap().leaf_count(), 5);
+
+        // Memory storage has nothing to recover and keeps its tree
+        live.rebuild().await.unwrap();
+        assert_eq!(live.get_root_hash().await.unwrap(), expected_root);
+    }
+
+    #[tokio::test]
+    async fn test_with_root_node_generates_proofs() {
+        let storage: Arc<dyn CommitmentStorage> = Arc::new(MemoryStorage::new());
+        assert_eq!(storage.with_root_node(|_, _| ()).await.unwrap(), None);
+
+        for i in 0..5 {
+            storage.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        let proof = storage
+            .with_root_node(|root, leaf_count| {
+                MerkleProof::new(
+                    3,
+                    b"data3".to_vec(),
+                    generate_proof(root, 3, leaf_count),
+                    root.hash.clone(),
+                )
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(proof.verify());
+        assert_eq!(proof, *storage.get_proof(3).await.unwrap());
+    }
+
+    async fn exported_log() -> Vec<Commitment> {
+        let source = MemoryStorage::new();
+        for i in 0..5 {
+            source.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+        }
+        source.insert_at(8, b"gap".to_vec()).await.unwrap();
+        source.get_commitments_after(None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_import_validated() {
+        let entries = exported_log().await;
+        let root = entries.last().unwrap().merkle_root.clone();
+
+        let storage = MemoryStorage::new();
+        assert_eq!(storage.import_validated(entries.clone()).await.unwrap(), 9);
+        assert_eq!(storage.get_root_hash().await.unwrap(), root);
+        assert_eq!(storage.get_all_commitments().await.unwrap(), entries);
+
+        // Importing over existing data is refused
+        let result = storage.import_validated(entries).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+
+        // A lone leaf's root follows the storage's single-leaf mode
+        let config = || MemoryStorageConfig {
+            single_leaf_mode: SingleLeafMode::DuplicateToParent,
+            ..Default::default()
+        };
+        let source = MemoryStorage::with_config(config());
+        source.add_commitment(b"data0".to_vec()).await.unwrap();
+        source.add_commitment(b"data1".to_vec()).await.unwrap();
+        let entries = source.get_commitments_after(None).await.unwrap();
+        let storage = MemoryStorage::with_config(config());
+        assert_eq!(storage.import_validated(entries).await.unwrap(), 2);
+        assert_eq!(storage.get_root_hash().await.unwrap(), source.get_root_hash().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_import_validated_stops_at_tampered_entry() {
+        let mut entries = exported_log().await;
+        entries[2].value = Bytes::from_static(b"tampered");
+
+        let storage = MemoryStorage::new();
+        match storage.import_validated(entries).await {
+            Err(AppError::InvalidInput(message)) => {
+                assert!(message.starts_with("Commitment 2 root mismatch"), "{}", message)
+            }
+            other => panic!("expected a root mismatch, got {:?}", other),
+        }
+        // Only the entries before the tampered one were imported
+        assert_eq!(storage.commitment_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_validated_rejects_tampered_root() {
+        let mut entries = exported_log().await;
+        entries[1].merkle_root = vec![0xab; 32];
+
+        // Entry 2's root covers the value at 1, but not the root recorded there
+        let storage = MemoryStorage::new();
+        match storage.import_validated(entries).await {
+            Err(AppError::InvalidInput(message)) => {
+                assert!(message.starts_with("Commitment 1 root mismatch"), "{}", message)
+            }
+            other => panic!("expected a root mismatch, got {:?}", other),
+        }
+        assert_eq!(storage.commitment_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_validated_replays_placeholders() {
+        for hash_only in [false, true] {
+            let config = || MemoryStorageConfig {
+                hash_only,
+                ..Default::default()
+            };
+            let source = MemoryStorage::with_config(config());
+            let now = unix_now();
+            for i in 0..3 {
+                source.add_commitment(format!("data{}", i).into_bytes()).await.unwrap();
+            }
+            let expiring = CommitmentMetadata {
+                expires_at: Some(now),
+                ..Default::default()
+            };
+            source.store_commitment(None, b"ephemeral".to_vec(), expiring).await.unwrap();
+            source.add_commitment(b"data4".to_vec()).await.unwrap();
+            source.add_commitment(b"data5".to_vec()).await.unwrap();
+            source.sweep_expired(now).await.unwrap();
+            // Leaves a trailing placeholder
+            source.delete_commitment(5).await.unwrap();
+            let entries = source.get_commitments_after(None).await.unwrap();
+
+            let storage = MemoryStorage::with_config(config());
+            assert_eq!(storage.import_validated(entries.clone()).await.unwrap(), 6);
+            assert_eq!(storage.get_root_hash().await.unwrap(), source.get_root_hash().await.unwrap());
+            assert_eq!(storage.get_commitments_after(None).await.unwrap(), entries);
+            assert!(storage.verify_integrity().await.unwrap());
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! OpenTelemetry export of tracing spans (enabled by the `otel` feature)
+
+use crate::error::AppError;
+
+use axum::{extract::Request, http::HeaderMap, middleware::Next, response::Response};
+use opentelemetry::{global, propagation::Extractor, trace::TracerProvider as _};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Collector endpoint used when `OTEL_EXPORTER_OTLP_ENDPOINT` is unset
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4317";
+
+/// Collector endpoint from `OTEL_EXPORTER_OTLP_ENDPOINT`, falling back to the default
+pub fn otlp_endpoint_from_env() -> String {
+    std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").unwrap_or_else(|_| DEFAULT_OTLP_ENDPOINT.to_string())
+}
+
+/// Build a tracer provider exporting spans over OTLP/gRPC and register the
+/// W3C trace-context propagator
+pub fn init_tracer_provider(endpoint: &str) -> Result<SdkTracerProvider, AppError> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| AppError::Internal(format!("Failed to build OTLP exporter: {}", e)))?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(env!("CARGO_PKG_NAME")).build())
+        .build();
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(provider)
+}
+
+/// Install a global tracing subscriber that exports spans to the collector.
+/// Keep the returned provider alive for the lifetime of the process.
+pub fn init(endpoint: &str) -> Result<SdkTracerProvider, AppError> {
+    let provider = init_tracer_provider(endpoint)?;
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .map_err(|e| AppError::Internal(format!("Failed to install tracing subscriber: {}", e)))?;
+
+    Ok(provider)
+}
+
+/// Run each request in a span parented to the trace context from its headers
+pub async fn propagate_context(request: Request, next: Next) -> Response {
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    // Without the OpenTelemetry layer installed there's nothing to link to
+    let _ = span.set_parent(parent);
+
+    next.run(request).instrument(span).await
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{route::create_router, state::AppState};
+    use crate::storage::memory::MemoryStorage;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_tracer_installed_without_breaking_routing() {
+        let provider = init_tracer_provider(DEFAULT_OTLP_ENDPOINT).unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = create_router(AppState::new(Arc::new(MemoryStorage::new())));
+        let request = Request::get("/health")
+            .header(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
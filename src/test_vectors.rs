@@ -0,0 +1,137 @@
+//! Canonical inputs and their expected roots and proofs for the default tree:
+//! SHA-256 leaves over the raw value, SHA-256(left || right) interior nodes, a single
+//! leaf as its own root, and an odd level's last node paired with itself. Other
+//! implementations can check against these to interoperate; an empty tree's root is
+//! `EMPTY_ROOT`.
+
+/// One published vector. Hashes are lowercase hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// Leaf values, in index order
+    pub leaves: &'static [&'static str],
+    /// Root of the tree over `leaves`
+    pub root: &'static str,
+    /// Per leaf index, the proof path from leaf to root as (sibling hash, is_left)
+    pub proofs: &'static [&'static [(&'static str, bool)]],
+}
+
+/// The published vectors
+pub const TEST_VECTORS: &[TestVector] = &[
+    // A single leaf is the root
+    TestVector {
+        leaves: &["a"],
+        root: "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb",
+        proofs: &[
+            &[],
+        ],
+    },
+    // Two leaves pair directly
+    TestVector {
+        leaves: &["a", "b"],
+        root: "e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a",
+        proofs: &[
+            &[
+                ("3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d", false),
+            ],
+            &[
+                ("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb", true),
+            ],
+        ],
+    },
+    // An odd level pairs its last node with itself
+    TestVector {
+        leaves: &["a", "b", "c"],
+        root: "d31a37ef6ac14a2db1470c4316beb5592e6afd4465022339adafda76a18ffabe",
+        proofs: &[
+            &[
+                ("3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d", false),
+                ("a3e333fbee455b9a054cf05077f0f9d45b91bd13db4cd4a3681ec47455af085c", false),
+            ],
+            &[
+                ("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb", true),
+                ("a3e333fbee455b9a054cf05077f0f9d45b91bd13db4cd4a3681ec47455af085c", false),
+            ],
+            &[
+                ("2e7d2c03a9507ae265ecf5b5356885a53393a2029d241394997265a1a25aefc6", false),
+                ("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a", true),
+            ],
+        ],
+    },
+    // Duplication repeats on every odd level
+    TestVector {
+        leaves: &["a", "b", "c", "d", "e"],
+        root: "dd14d0ba516bb654a3052b76f051db026f4e322d0be081468fab99440f9e7305",
+        proofs: &[
+            &[
+                ("3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d", false),
+                ("bffe0b34dba16bc6fac17c08bac55d676cded5a4ade41fe2c9924a5dde8f3e5b", false),
+                ("de913ac41aae6129f7358dadea47a987a81509a6fb267b01f0508280f8dd5b46", false),
+            ],
+            &[
+                ("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb", true),
+                ("bffe0b34dba16bc6fac17c08bac55d676cded5a4ade41fe2c9924a5dde8f3e5b", false),
+                ("de913ac41aae6129f7358dadea47a987a81509a6fb267b01f0508280f8dd5b46", false),
+            ],
+            &[
+                ("18ac3e7343f016890c510e93f935261169d9e3f565436429830faf0934f4f8e4", false),
+                ("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a", true),
+                ("de913ac41aae6129f7358dadea47a987a81509a6fb267b01f0508280f8dd5b46", false),
+            ],
+            &[
+                ("2e7d2c03a9507ae265ecf5b5356885a53393a2029d241394997265a1a25aefc6", true),
+                ("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a", true),
+                ("de913ac41aae6129f7358dadea47a987a81509a6fb267b01f0508280f8dd5b46", false),
+            ],
+            &[
+                ("3f79bb7b435b05321651daefd374cdc681dc06faa65e374e38337b88ca046dea", false),
+                ("75de222d8adebd767f99a5fe35a5f3f58dbfa3d51ec28b54e9da4225ec8f170d", false),
+                ("14ede5e8e97ad9372327728f5099b95604a39593cac3bd38a343ad76205213e7", true),
+            ],
+        ],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof::{generate_proof, MerkleProof};
+    use crate::models::merkle::{MerkleNode, MerkleTree};
+
+    #[test]
+    fn test_crate_reproduces_vectors() {
+        for vector in TEST_VECTORS {
+            let leaves = vector.leaves.iter().map(|v| MerkleNode::new_leaf(v.as_bytes())).collect();
+            let tree = MerkleTree::from_leaves(leaves);
+            let root = tree.root().unwrap();
+            assert_eq!(hex::encode(&root.hash), vector.root, "{:?}", vector.leaves);
+
+            assert_eq!(vector.proofs.len(), vector.leaves.len());
+            for (index, expected) in vector.proofs.iter().enumerate() {
+                let path = generate_proof(root, index, tree.leaf_count());
+                let path: Vec<(String, bool)> = path.iter().map(|e| (hex::encode(&e.hash), e.is_left)).collect();
+                let expected: Vec<(String, bool)> = expected.iter().map(|&(h, l)| (h.to_string(), l)).collect();
+                assert_eq!(path, expected, "{:?} index {}", vector.leaves, index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_vector_proofs_verify() {
+        for vector in TEST_VECTORS {
+            for (index, path) in vector.proofs.iter().enumerate() {
+                let path: Vec<_> = path
+                    .iter()
+                    .map(|&(hash_hex, is_left)| serde_json::json!({ "hash_hex": hash_hex, "is_left": is_left }))
+                    .collect();
+                let json = serde_json::json!({
+                    "index": index,
+                    "value_hex": hex::encode(vector.leaves[index]),
+                    "path": path,
+                    "root_hex": vector.root,
+                });
+                let proof = MerkleProof::from_json_compact(&json.to_string()).unwrap();
+                assert!(proof.verify(), "{:?} index {}", vector.leaves, index);
+            }
+        }
+    }
+}